@@ -1,14 +1,38 @@
-use crate::config::{Config, VpnProfile};
+use crate::config::{
+    Config, ConfigWriter, DisplayPrimary, MIN_INTERVAL_SECONDS, ProfileField, Protocol, Sequence,
+    Settings, UiState, ValidationError, VpnProfile,
+};
+use crate::history::{ConnectAction, ConnectionEvent, Outcome};
+use crate::input::TextInput;
 use crate::vpn::{VpnConnection, VpnManager, VpnStatus};
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Number of recent search queries kept in the settings
+const SEARCH_HISTORY_LIMIT: usize = 20;
+
+/// Two clicks on the same row within this count as a double-click
+const DOUBLE_CLICK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(400);
+
+/// Fields of the add/edit form: six text inputs followed by the protocol picker
+const FORM_FIELDS: usize = 7;
+pub const PROTOCOL_FIELD: usize = 6;
 
 pub enum AppEvent {
     Input(KeyEvent),
+    Mouse(MouseEvent),
     Tick,
     VpnStatusUpdated,
-    Notification(String),
+    /// A line for the logs from a background task
+    Notification(LogLevel, String),
     SetStatusMessage(String),
+    /// A status bar message shown as a warning
+    SetStatusWarning(String),
+    /// Terminal resized to the given columns and rows; triggers a redraw
+    Resize(u16, u16),
 }
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Screen {
@@ -19,10 +43,188 @@ pub enum Screen {
     FileBrowser,
     Help,
     DeleteConfirmation,
+    ConnectConfirmation,
+    KillSwitchConfirm,
     Search,
     AliasModal,
+    ColorModal,
+    GatewayModal,
+    PasswordModal,
+    ConnectWizard,
+    SequenceModal,
+    ProfileDetails,
+    History,
+    FilterByCategory,
+    Logs,
+    Settings,
+}
+
+/// Severity of a line in the TUI logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    pub message: String,
+    pub timestamp: chrono::DateTime<chrono::Local>,
+}
+
+/// "[14:32:05] message", as shown in the logs panel
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] {}",
+            self.timestamp.format("%H:%M:%S"),
+            self.message
+        )
+    }
+}
+
+/// Refreshes of throughput kept per connected profile for the details view
+const THROUGHPUT_SAMPLES: usize = 60;
+
+/// Send and receive rates over one refresh interval, in bytes per second
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThroughputSample {
+    pub sent: u64,
+    pub received: u64,
+}
+
+/// Recent rates of a connected profile, from the deltas of its byte
+/// counters between refreshes
+#[derive(Debug, Default)]
+pub struct Throughput {
+    /// Counters at the previous refresh
+    last: Option<(std::time::Instant, u64, u64)>,
+    /// Oldest first, at most `THROUGHPUT_SAMPLES`
+    pub samples: VecDeque<ThroughputSample>,
+}
+
+impl Throughput {
+    fn record(&mut self, at: std::time::Instant, sent: u64, received: u64) {
+        if let Some((last_at, last_sent, last_received)) = self.last {
+            let secs = at.duration_since(last_at).as_secs_f64();
+            // Counters that went backwards belong to a new session
+            if secs > 0.0 && sent >= last_sent && received >= last_received {
+                if self.samples.len() == THROUGHPUT_SAMPLES {
+                    self.samples.pop_front();
+                }
+                self.samples.push_back(ThroughputSample {
+                    sent: ((sent - last_sent) as f64 / secs) as u64,
+                    received: ((received - last_received) as f64 / secs) as u64,
+                });
+            }
+        }
+        self.last = Some((at, sent, received));
+    }
+
+    /// Rates over the latest refresh interval
+    pub fn current(&self) -> ThroughputSample {
+        self.samples.back().copied().unwrap_or_default()
+    }
+
+    /// Highest rate of each direction among the kept samples
+    pub fn peak(&self) -> ThroughputSample {
+        ThroughputSample {
+            sent: self.samples.iter().map(|s| s.sent).max().unwrap_or(0),
+            received: self.samples.iter().map(|s| s.received).max().unwrap_or(0),
+        }
+    }
+}
+
+/// Which log lines the logs screen shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFilter {
+    All,
+    Errors,
+    Successes,
+}
+
+impl LogFilter {
+    pub fn matches(self, entry: &LogEntry) -> bool {
+        match self {
+            LogFilter::All => true,
+            LogFilter::Errors => entry.level == LogLevel::Error,
+            LogFilter::Successes => entry.level == LogLevel::Success,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogFilter::All => "all",
+            LogFilter::Errors => "errors",
+            LogFilter::Successes => "successes",
+        }
+    }
+}
+
+/// Steps of the guided setup for a profile with no registered system VPN service
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WizardStep {
+    /// Explain why the profile can't be connected yet
+    Explain,
+    /// Pick the profile file to hand to the Azure VPN Client
+    ChooseFile,
+    /// Wait for the user to finish the import, then check the service again
+    Recheck,
+}
+
+/// A background connect/disconnect started from the TUI
+pub struct PendingOperation {
+    /// e.g. "Connecting to prod"
+    pub label: String,
+    pub started: std::time::Instant,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+pub struct ConnectWizard {
+    pub profile_name: String,
+    pub step: WizardStep,
+    pub file_input: TextInput,
+}
+
+/// Levels cycled with `V`, least verbose first
+pub const LOG_LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Level used when the configured one isn't in [`LOG_LEVELS`]
+pub const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Tracing filter directive for `level`, or None when it isn't one of
+/// [`LOG_LEVELS`]
+pub fn log_directive(level: &str) -> Option<String> {
+    LOG_LEVELS
+        .iter()
+        .find(|l| l.eq_ignore_ascii_case(level.trim()))
+        .map(|l| format!("remipn={}", l))
 }
 
+/// Swaps the active tracing filter for the given level; installed by the binary
+pub type LogLevelSetter = Box<dyn Fn(&str) -> Result<()> + Send + Sync>;
+
+/// Accent colors offered by the color picker; the first entry clears the color
+pub const PROFILE_COLORS: [&str; 13] = [
+    "none",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "lightred",
+    "lightgreen",
+    "lightyellow",
+    "lightblue",
+    "lightmagenta",
+    "lightcyan",
+];
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct FileEntry {
     pub name: String,
@@ -126,101 +328,580 @@ impl FileBrowser {
     }
 }
 
+/// A row of the settings screen (`,`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsField {
+    AutoReconnect,
+    ReconnectDelay,
+    StatusCheckInterval,
+    LogLevel,
+    MultipleConnections,
+    KillSwitch,
+    KillSwitchDryRun,
+    BatchKeepGoing,
+    ConfirmBeforeConnect,
+    CheckDnsLeak,
+}
+
+impl SettingsField {
+    /// The rows in display order
+    pub const ALL: [SettingsField; 10] = [
+        SettingsField::AutoReconnect,
+        SettingsField::ReconnectDelay,
+        SettingsField::StatusCheckInterval,
+        SettingsField::LogLevel,
+        SettingsField::MultipleConnections,
+        SettingsField::KillSwitch,
+        SettingsField::KillSwitchDryRun,
+        SettingsField::BatchKeepGoing,
+        SettingsField::ConfirmBeforeConnect,
+        SettingsField::CheckDnsLeak,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingsField::AutoReconnect => "Auto-reconnect dropped tunnels",
+            SettingsField::ReconnectDelay => "Reconnect delay (seconds)",
+            SettingsField::StatusCheckInterval => "Status check interval (seconds)",
+            SettingsField::LogLevel => "Log level",
+            SettingsField::MultipleConnections => "Allow multiple connections",
+            SettingsField::KillSwitch => "Offer the kill switch (K)",
+            SettingsField::KillSwitchDryRun => "Kill switch dry run (log rules only)",
+            SettingsField::BatchKeepGoing => "Batch connect keeps going after a failure",
+            SettingsField::ConfirmBeforeConnect => "Confirm before connecting",
+            SettingsField::CheckDnsLeak => "Check for DNS leaks after connecting",
+        }
+    }
+}
+
+/// Values being edited on the settings screen; nothing is applied until
+/// they are saved
+#[derive(Debug, Clone)]
+pub struct SettingsForm {
+    /// Index into [`SettingsField::ALL`]
+    pub field: usize,
+    pub auto_reconnect: bool,
+    pub reconnect_delay: TextInput,
+    pub status_check_interval: TextInput,
+    /// Index into [`LOG_LEVELS`]
+    pub log_level: usize,
+    pub allow_multiple_connections: bool,
+    pub kill_switch: bool,
+    pub kill_switch_dry_run: bool,
+    pub batch_keep_going: bool,
+    pub confirm_before_connect: bool,
+    pub check_dns_leak: bool,
+    /// Row that failed validation on the last save, and why
+    pub error: Option<(SettingsField, String)>,
+}
+
+impl SettingsForm {
+    fn new(settings: &Settings, auto_reconnect: bool) -> Self {
+        let mut reconnect_delay = TextInput::default();
+        reconnect_delay.set(settings.reconnect_delay_seconds.to_string());
+        let mut status_check_interval = TextInput::default();
+        status_check_interval.set(settings.status_check_interval_seconds.to_string());
+        Self {
+            field: 0,
+            auto_reconnect,
+            reconnect_delay,
+            status_check_interval,
+            log_level: LOG_LEVELS
+                .iter()
+                .position(|l| l.eq_ignore_ascii_case(&settings.log_level))
+                .unwrap_or(2),
+            allow_multiple_connections: settings.allow_multiple_connections,
+            kill_switch: settings.kill_switch,
+            kill_switch_dry_run: settings.kill_switch_dry_run,
+            batch_keep_going: settings.batch_keep_going,
+            confirm_before_connect: settings.confirm_before_connect,
+            check_dns_leak: settings.check_dns_leak,
+            error: None,
+        }
+    }
+
+    pub fn selected(&self) -> SettingsField {
+        SettingsField::ALL[self.field]
+    }
+
+    /// Text input of the selected row, if it is a number
+    fn input_mut(&mut self) -> Option<&mut TextInput> {
+        match self.selected() {
+            SettingsField::ReconnectDelay => Some(&mut self.reconnect_delay),
+            SettingsField::StatusCheckInterval => Some(&mut self.status_check_interval),
+            _ => None,
+        }
+    }
+
+    /// Flip the selected toggle, or step the log level
+    fn change(&mut self, forward: bool) {
+        match self.selected() {
+            SettingsField::AutoReconnect => self.auto_reconnect = !self.auto_reconnect,
+            SettingsField::LogLevel => {
+                let len = LOG_LEVELS.len();
+                self.log_level = if forward {
+                    (self.log_level + 1) % len
+                } else {
+                    (self.log_level + len - 1) % len
+                };
+            }
+            SettingsField::MultipleConnections => {
+                self.allow_multiple_connections = !self.allow_multiple_connections
+            }
+            SettingsField::KillSwitch => self.kill_switch = !self.kill_switch,
+            SettingsField::KillSwitchDryRun => self.kill_switch_dry_run = !self.kill_switch_dry_run,
+            SettingsField::BatchKeepGoing => self.batch_keep_going = !self.batch_keep_going,
+            SettingsField::ConfirmBeforeConnect => {
+                self.confirm_before_connect = !self.confirm_before_connect
+            }
+            SettingsField::CheckDnsLeak => self.check_dns_leak = !self.check_dns_leak,
+            SettingsField::ReconnectDelay | SettingsField::StatusCheckInterval => {}
+        }
+    }
+}
+
+/// Whole seconds, at least [`MIN_INTERVAL_SECONDS`]
+fn parse_interval(input: &TextInput) -> std::result::Result<u64, String> {
+    match input.value().trim().parse::<u64>() {
+        Ok(secs) if secs >= MIN_INTERVAL_SECONDS => Ok(secs),
+        _ => Err(format!(
+            "Enter whole seconds, at least {}",
+            MIN_INTERVAL_SECONDS
+        )),
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputMode {
     Normal,
     Editing,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SortColumn {
+    #[default]
     Name,
     Category,
     Status,
+    /// Longest-connected first; profiles that aren't up stay at the bottom
+    Duration,
+    /// By address, IPv4 before IPv6; profiles without an IP stay at the bottom
+    Ip,
+    /// The order of the profiles in the config, arranged with Alt+Up/Down
+    Manual,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl SortColumn {
+    /// Column `s` moves to once the current one was sorted both ways
+    pub fn next(self) -> Self {
+        match self {
+            SortColumn::Name => SortColumn::Category,
+            SortColumn::Category => SortColumn::Status,
+            SortColumn::Status => SortColumn::Duration,
+            SortColumn::Duration => SortColumn::Ip,
+            SortColumn::Ip => SortColumn::Manual,
+            SortColumn::Manual => SortColumn::Name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SortDirection {
+    #[default]
     Asc,
     Desc,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusFilter {
+    All,
+    Connected,
+    Disconnected,
+    Error,
+}
+
+impl StatusFilter {
+    pub fn next(self) -> Self {
+        match self {
+            StatusFilter::All => StatusFilter::Connected,
+            StatusFilter::Connected => StatusFilter::Disconnected,
+            StatusFilter::Disconnected => StatusFilter::Error,
+            StatusFilter::Error => StatusFilter::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StatusFilter::All => "all",
+            StatusFilter::Connected => "connected",
+            StatusFilter::Disconnected => "disconnected",
+            StatusFilter::Error => "error",
+        }
+    }
+
+    pub fn matches(self, status: &VpnStatus) -> bool {
+        match self {
+            StatusFilter::All => true,
+            StatusFilter::Connected => matches!(status, VpnStatus::Connected),
+            StatusFilter::Disconnected => {
+                matches!(status, VpnStatus::Disconnected | VpnStatus::NotConfigured)
+            }
+            StatusFilter::Error => matches!(
+                status,
+                VpnStatus::Error(_)
+                    | VpnStatus::Degraded(_)
+                    | VpnStatus::TimedOut
+                    | VpnStatus::Unknown(_)
+            ),
+        }
+    }
+}
+
 pub struct App {
     pub config: Config,
+    /// Background writer used instead of blocking `Config::save` calls
+    config_writer: ConfigWriter,
     pub vpn_manager: VpnManager,
     pub screen: Screen,
     pub input_mode: InputMode,
     pub selected_profile: usize,
     pub table_state: TableState,
+    /// Where the profile table was last drawn and its first visible row,
+    /// for mapping mouse clicks to profiles
+    pub table_area: std::cell::Cell<ratatui::layout::Rect>,
+    pub table_offset: std::cell::Cell<usize>,
+    /// Time and row of the last left click, to detect double-clicks
+    last_click: Option<(std::time::Instant, usize)>,
     pub scroll_offset: usize,
-    pub input_buffer: String,
+    pub input_buffer: TextInput,
     pub input_field: usize,
     pub status_message: Option<(String, chrono::DateTime<chrono::Local>)>,
+    /// Show the status message in yellow
+    pub status_is_warning: bool,
     pub show_logs: bool,
-    pub logs: Vec<String>,
+    pub logs: Vec<LogEntry>,
     pub auto_reconnect: bool,
     pub connections: Vec<VpnConnection>,
-    pub last_update: std::time::Instant,
+    /// Set while a status refresh is querying the system, so the periodic
+    /// one and a manual one never run at the same time
+    refreshing: Arc<AtomicBool>,
+    /// Status check interval in seconds; the refresh task restarts its wait
+    /// when it changes
+    refresh_interval: tokio::sync::watch::Sender<u64>,
+    /// Profiles as last saved, for the refresh task to check
+    profiles_tx: tokio::sync::watch::Sender<Vec<VpnProfile>>,
+    /// Open settings screen
+    pub settings_form: Option<SettingsForm>,
+    /// "Don't ask again" was chosen on the connect confirmation
+    skip_connect_confirmation: bool,
+    pub last_verify: std::time::Instant,
     pub file_browser: Option<FileBrowser>,
-    pub search_query: String,
-    pub add_profile_data: Vec<String>,
+    pub search_query: TextInput,
+    /// Position while browsing search history with Up/Down, 0 being the most recent
+    pub search_history_pos: Option<usize>,
+    /// Query typed before browsing history, restored when stepping past the newest entry
+    search_draft: String,
+    pub add_profile_data: Vec<TextInput>,
+    /// Protocol chosen in the add/edit form
+    pub form_protocol: Protocol,
+    /// Problems that blocked the last save of the add/edit form
+    pub form_errors: Vec<ValidationError>,
     pub sort_column: SortColumn,
     pub sort_direction: SortDirection,
-    pub alias_input: String,
+    pub status_filter: StatusFilter,
+    pub alias_input: TextInput,
+    pub gateway_input: TextInput,
+    /// Typed into the password modal; replaced, not cleared, once used so
+    /// its undo history doesn't keep the secret
+    pub password_input: TextInput,
+    pub color_choice: usize,
+    /// Highlighted entry of the sequence picker
+    pub sequence_choice: usize,
+    /// Connection history shown by the history screen, newest first
+    pub history: Vec<ConnectionEvent>,
+    pub history_selected: usize,
+    /// Selected line of the logs screen, counted from the newest shown
+    pub logs_selected: usize,
+    /// Text the logs screen is narrowed to, and whether it is being typed
+    pub logs_query: TextInput,
+    pub logs_searching: bool,
+    pub log_filter: LogFilter,
+    /// Tags whose profiles are shown; empty shows every profile
+    pub category_filter: HashSet<String>,
+    /// Highlighted entry of the tag filter
+    pub filter_choice: usize,
+    /// Profiles the user asked to disconnect, so their drop isn't alerted
+    pub expected_disconnects: HashSet<String>,
+    /// Render a red flash on the next frame
+    pub alert_flash: bool,
+    pub alert_toast: Option<(String, chrono::DateTime<chrono::Local>)>,
+    /// Throughput history of connected profiles, by name
+    pub throughput: HashMap<String, Throughput>,
+    /// Profile the kill switch firewall rules protect; they stay installed
+    /// until removed with `K`, even across drops and restarts
+    pub kill_switch: Option<String>,
+    /// Missing-tool message last shown in the status bar, so each refresh
+    /// doesn't show it again
+    missing_tool_notice: Option<String>,
+    /// Profiles already warned about their data cap this session
+    data_cap_alerted: HashSet<String>,
+    /// List disabled profiles too
+    pub show_disabled: bool,
+    /// Only list favorite profiles
+    pub favorites_only: bool,
+    /// Profile pinned to the top and polled every second, with its poll task
+    watched: Option<(String, tokio::task::JoinHandle<()>)>,
+    /// Connect/disconnect in progress; input is blocked until it finishes
+    pub operation: Option<PendingOperation>,
+    /// Guided setup shown when connecting a profile without a system service
+    pub wizard: Option<ConnectWizard>,
+    /// Started with `--safe`: no auto-import at startup and no background
+    /// connection verification or auto-reconnect
+    pub safe_mode: bool,
+    /// Level currently applied to the tracing filter
+    pub log_level: String,
+    pub set_log_level: Option<LogLevelSetter>,
     pub event_tx: Option<tokio::sync::mpsc::Sender<AppEvent>>,
 }
 
 impl App {
     pub async fn new() -> Result<Self> {
         let config = Config::load()?;
-        let vpn_manager = VpnManager::new();
+        let vpn_manager = VpnManager::new()
+            .with_connect_timeout(std::time::Duration::from_secs(
+                config.settings.connect_timeout_seconds,
+            ))
+            .with_status_debounce(config.settings.status_debounce_refreshes)
+            .with_latency_interval(std::time::Duration::from_secs(
+                config.settings.status_check_interval_seconds,
+            ))
+            .with_history_limit(config.settings.history_max_lines)
+            .with_multiple_connections(config.settings.allow_multiple_connections)
+            .with_hook_policy(
+                std::time::Duration::from_secs(config.settings.hook_timeout_seconds),
+                config.settings.abort_on_hook_failure,
+            )
+            .with_kill_switch_dry_run(config.settings.kill_switch_dry_run);
+        let mut app = Self::from_parts(config, vpn_manager, ConfigWriter::spawn());
+
+        // Initial status load
+        app.refresh_status().await?;
+        Ok(app)
+    }
+
+    /// The app state for a loaded config, before the first status refresh
+    fn from_parts(config: Config, vpn_manager: VpnManager, config_writer: ConfigWriter) -> Self {
+        let kill_switch = vpn_manager.kill_switch_profile();
 
+        let log_level = match log_directive(&config.settings.log_level) {
+            Some(_) => config.settings.log_level.trim().to_lowercase(),
+            None => DEFAULT_LOG_LEVEL.to_string(),
+        };
+        let auto_reconnect = config.settings.auto_reconnect;
+        let (refresh_interval, _) =
+            tokio::sync::watch::channel(config.settings.status_check_interval_seconds);
+        let (profiles_tx, _) = tokio::sync::watch::channel(config.profiles.clone());
+        let ui = config.ui.clone();
         let mut app = Self {
             config,
+            config_writer,
             vpn_manager,
             screen: Screen::Main,
             input_mode: InputMode::Normal,
             selected_profile: 0,
             table_state: TableState::default().with_selected(Some(0)),
+            table_area: Default::default(),
+            table_offset: Default::default(),
+            last_click: None,
             scroll_offset: 0,
-            input_buffer: String::new(),
+            input_buffer: TextInput::default(),
             input_field: 0,
             status_message: None,
-            show_logs: false,
+            status_is_warning: false,
+            show_logs: ui.show_logs,
             logs: Vec::new(),
-            auto_reconnect: false,
+            auto_reconnect,
             connections: Vec::new(),
-            last_update: std::time::Instant::now(),
+            refreshing: Arc::new(AtomicBool::new(false)),
+            refresh_interval,
+            profiles_tx,
+            settings_form: None,
+            skip_connect_confirmation: false,
+            last_verify: std::time::Instant::now(),
             file_browser: None,
-            search_query: String::new(),
-            add_profile_data: vec![String::new(); 6],
-            sort_column: SortColumn::Name,
-            sort_direction: SortDirection::Asc,
-            alias_input: String::new(),
+            search_query: TextInput::default(),
+            search_history_pos: None,
+            search_draft: String::new(),
+            add_profile_data: vec![TextInput::default(); 6],
+            form_protocol: Protocol::IKEv2,
+            form_errors: Vec::new(),
+            sort_column: ui.sort_column,
+            sort_direction: ui.sort_direction,
+            status_filter: StatusFilter::All,
+            alias_input: TextInput::default(),
+            gateway_input: TextInput::default(),
+            password_input: TextInput::default(),
+            color_choice: 0,
+            sequence_choice: 0,
+            history: Vec::new(),
+            history_selected: 0,
+            logs_selected: 0,
+            logs_query: TextInput::default(),
+            logs_searching: false,
+            log_filter: LogFilter::All,
+            category_filter: HashSet::new(),
+            filter_choice: 0,
+            expected_disconnects: HashSet::new(),
+            alert_flash: false,
+            alert_toast: None,
+            throughput: HashMap::new(),
+            kill_switch,
+            missing_tool_notice: None,
+            data_cap_alerted: HashSet::new(),
+            watched: None,
+            show_disabled: false,
+            favorites_only: false,
+            wizard: None,
+            operation: None,
+            safe_mode: false,
+            log_level,
+            set_log_level: None,
             event_tx: None,
         };
 
-        // Initial status load
-        app.refresh_status().await?;
-        Ok(app)
+        for warning in std::mem::take(&mut app.config.load_warnings) {
+            app.log(LogLevel::Warning, warning);
+        }
+        app
+    }
+
+    /// Queue the current config for a debounced background write and hand
+    /// the profiles to the refresh task, which would otherwise see the old
+    /// ones until the write lands
+    pub fn save_config(&self) {
+        self.config_writer.save(&self.config);
+        self.profiles_tx.send_replace(self.config.profiles.clone());
+    }
+
+    /// Remember the sort order, log panel and auto-reconnect for the next
+    /// run. They go out with the rest of the config, so a profile edit queued
+    /// at the same time is written in the same snapshot.
+    fn save_ui_state(&mut self) {
+        self.config.ui = UiState {
+            sort_column: self.sort_column,
+            sort_direction: self.sort_direction,
+            show_logs: self.show_logs,
+        };
+        self.config.settings.auto_reconnect = self.auto_reconnect;
+        self.save_config();
+    }
+
+    /// Write any queued config change before exiting
+    pub async fn flush_config(&self) {
+        self.config_writer.flush().await;
     }
 
     pub async fn handle_event(&mut self, event: AppEvent) -> Result<Option<()>> {
         match event {
             AppEvent::Input(key) => return self.handle_key(key).await,
+            AppEvent::Mouse(mouse) => self.handle_mouse(mouse).await?,
             AppEvent::Tick => self.update().await?,
             AppEvent::VpnStatusUpdated => self.refresh_from_manager().await?,
-            AppEvent::Notification(msg) => {
-                self.add_log(msg.clone());
+            AppEvent::Notification(level, msg) => {
+                self.log(level, msg);
             }
             AppEvent::SetStatusMessage(msg) => {
                 self.set_status_message(msg);
             }
+            AppEvent::SetStatusWarning(msg) => {
+                self.set_status_message(msg);
+                self.status_is_warning = true;
+            }
+            // Handled by the draw loop; nothing in the app state depends on size
+            AppEvent::Resize(..) => {}
         }
         Ok(None)
     }
 
+    /// Main screen only: a click selects a profile, a double-click toggles
+    /// it and the wheel moves the selection. Clicks outside the table rows
+    /// are ignored.
+    async fn handle_mouse(&mut self, mouse: MouseEvent) -> Result<()> {
+        if self.screen != Screen::Main {
+            return Ok(());
+        }
+        let profiles_len = self.get_filtered_profiles_indices().len();
+        match mouse.kind {
+            MouseEventKind::ScrollUp => {
+                self.selected_profile = self.selected_profile.saturating_sub(1);
+                self.table_state.select(Some(self.selected_profile));
+            }
+            MouseEventKind::ScrollDown => {
+                self.selected_profile =
+                    (self.selected_profile + 1).min(profiles_len.saturating_sub(1));
+                self.table_state.select(Some(self.selected_profile));
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let Some(row) = self.table_row_at(mouse.column, mouse.row) else {
+                    return Ok(());
+                };
+                if row >= profiles_len {
+                    return Ok(());
+                }
+                self.selected_profile = row;
+                self.table_state.select(Some(row));
+
+                let double_click = self.last_click.is_some_and(|(at, last_row)| {
+                    last_row == row && at.elapsed() < DOUBLE_CLICK_INTERVAL
+                });
+                if double_click {
+                    self.last_click = None;
+                    // Same rule as the keyboard: one operation at a time
+                    if self.operation.is_none() {
+                        self.toggle_connection().await?;
+                    }
+                } else {
+                    self.last_click = Some((std::time::Instant::now(), row));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Position in the filtered list of the table row at a screen cell
+    fn table_row_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.table_area.get();
+        // Inside the border, below the header and its margin
+        let first_row = area.y + 3;
+        let inside = column > area.x
+            && column < area.right().saturating_sub(1)
+            && row >= first_row
+            && row < area.bottom().saturating_sub(1);
+        inside.then(|| self.table_offset.get() + usize::from(row - first_row))
+    }
+
     pub async fn handle_key(&mut self, key: KeyEvent) -> Result<Option<()>> {
+        if self.operation.is_some() {
+            // Moving around, reading the logs and quitting stay available;
+            // anything that could start another operation or change the
+            // config waits, including reordering with Alt+Up/Down
+            let passive = matches!(
+                key.code,
+                KeyCode::Up
+                    | KeyCode::Down
+                    | KeyCode::PageUp
+                    | KeyCode::PageDown
+                    | KeyCode::Char('k' | 'j' | 'l' | 'q')
+            ) && !key.modifiers.contains(KeyModifiers::ALT);
+            if passive && self.screen == Screen::Main {
+                return self.handle_main_screen_key(key).await;
+            }
+            self.handle_operation_key(key).await?;
+            return Ok(None);
+        }
         match self.screen {
             Screen::Main => return self.handle_main_screen_key(key).await,
             Screen::AddProfile => self.handle_add_profile_key(key).await?,
@@ -229,12 +910,28 @@ impl App {
             Screen::FileBrowser => self.handle_file_browser_key(key).await?,
             Screen::Search => self.handle_search_key(key).await?,
             Screen::AliasModal => self.handle_alias_modal_key(key).await?,
+            Screen::GatewayModal => self.handle_gateway_modal_key(key).await?,
+            Screen::PasswordModal => self.handle_password_modal_key(key).await?,
+            Screen::ColorModal => self.handle_color_modal_key(key).await?,
+            Screen::ConnectWizard => self.handle_wizard_key(key).await?,
+            Screen::SequenceModal => self.handle_sequence_modal_key(key)?,
+            Screen::History => self.handle_history_key(key),
+            Screen::Logs => self.handle_logs_key(key),
+            Screen::FilterByCategory => self.handle_category_filter_key(key),
             Screen::DeleteConfirmation => self.handle_delete_confirmation_key(key).await?,
+            Screen::ConnectConfirmation => self.handle_connect_confirmation_key(key).await?,
+            Screen::KillSwitchConfirm => self.handle_kill_switch_confirm_key(key).await?,
+            Screen::Settings => self.handle_settings_key(key),
             Screen::Help => {
                 if let KeyCode::Esc | KeyCode::Char('h') = key.code {
                     self.screen = Screen::Main;
                 }
             }
+            Screen::ProfileDetails => {
+                if let KeyCode::Esc | KeyCode::Char('v') = key.code {
+                    self.screen = Screen::Main;
+                }
+            }
         }
         Ok(None)
     }
@@ -242,6 +939,12 @@ impl App {
     async fn handle_main_screen_key(&mut self, key: KeyEvent) -> Result<Option<()>> {
         match key.code {
             KeyCode::Char('q') => return Ok(Some(())),
+            KeyCode::Up if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.move_selected_profile(false)
+            }
+            KeyCode::Down if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.move_selected_profile(true)
+            }
             KeyCode::Up | KeyCode::Char('k') => {
                 let profiles_len = self.get_filtered_profiles_indices().len();
                 if self.selected_profile > 0 {
@@ -278,12 +981,18 @@ impl App {
                 }
             }
             KeyCode::Enter | KeyCode::Char(' ') => {
-                self.toggle_connection().await?;
+                if self.needs_connect_confirmation() {
+                    self.screen = Screen::ConnectConfirmation;
+                } else {
+                    self.toggle_connection().await?;
+                }
             }
             KeyCode::Char('n') => {
                 self.screen = Screen::AddProfile;
                 self.input_mode = InputMode::Editing;
-                self.add_profile_data = vec![String::new(); 6];
+                self.add_profile_data = vec![TextInput::default(); 6];
+                self.form_protocol = Protocol::IKEv2;
+                self.form_errors.clear();
                 self.input_field = 0;
             }
             KeyCode::Char('e') => {
@@ -294,6 +1003,8 @@ impl App {
                     self.input_field = 1; // Start from Gateway Address when editing
                 }
             }
+            KeyCode::Char('c') => self.clone_selected_profile(),
+            KeyCode::Char('K') => self.toggle_kill_switch().await,
             KeyCode::Char('x') => {
                 if !self.get_filtered_profiles_indices().is_empty() {
                     self.screen = Screen::DeleteConfirmation;
@@ -310,37 +1021,237 @@ impl App {
                 self.input_buffer.clear();
                 self.input_field = 0;
             }
+            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let indices = self.get_filtered_profiles_indices();
+                if let Some(&actual_index) = indices.get(self.selected_profile) {
+                    let name = self.config.profiles[actual_index].name.clone();
+                    let status = self.vpn_manager.refresh_profile(&name).await;
+                    self.refresh_from_manager().await?;
+                    self.set_status_message(format!("{}: {}", name, status.as_str()));
+                }
+            }
             KeyCode::Char('r') => {
+                self.vpn_manager.clear_timeouts().await;
                 self.refresh_status().await?;
             }
             KeyCode::Char('l') => {
                 self.show_logs = !self.show_logs;
+                self.save_ui_state();
             }
             KeyCode::Char('s') => {
                 self.cycle_sort();
             }
+            KeyCode::Char('S') => {
+                self.status_filter = self.status_filter.next();
+                self.selected_profile = 0;
+                self.table_state.select(Some(0));
+                self.set_status_message(format!("Status filter: {}", self.status_filter.label()));
+            }
+            KeyCode::Char('f') => {
+                if self.tag_counts().is_empty() {
+                    self.set_status_message("No profiles have tags".to_string());
+                } else {
+                    self.filter_choice = 0;
+                    self.screen = Screen::FilterByCategory;
+                }
+            }
             KeyCode::Char('a') => {
                 let indices = self.get_filtered_profiles_indices();
                 if !indices.is_empty() && self.selected_profile < indices.len() {
                     let actual_index = indices[self.selected_profile];
-                    self.alias_input = self.config.profiles[actual_index]
-                        .aliases
-                        .clone()
-                        .unwrap_or_default();
+                    self.alias_input.set(
+                        self.config.profiles[actual_index]
+                            .aliases
+                            .clone()
+                            .unwrap_or_default(),
+                    );
                     self.screen = Screen::AliasModal;
                     self.input_mode = InputMode::Editing;
                 }
             }
-            KeyCode::Char('I') => {
-                if let Ok(imported) = self.config.auto_import_profiles() {
-                    if imported {
-                        self.add_log(
-                            "Manually imported new profiles from standard locations".to_string(),
-                        );
+            KeyCode::Char('v') => {
+                if self.selected_profile < self.get_filtered_profiles_indices().len() {
+                    self.screen = Screen::ProfileDetails;
+                }
+            }
+            KeyCode::Char('w') => {
+                let indices = self.get_filtered_profiles_indices();
+                if let Some(&actual_index) = indices.get(self.selected_profile) {
+                    let name = self.config.profiles[actual_index].name.clone();
+                    if self.watched_profile() == Some(name.as_str()) {
+                        self.unwatch();
+                        self.set_status_message(format!("Stopped watching {}", name));
+                    } else {
+                        self.watch(name.clone());
+                        self.set_status_message(format!("Watching {}", name));
+                    }
+                    // The watched profile moves to the top; keep it selected
+                    let indices = self.get_filtered_profiles_indices();
+                    self.selected_profile =
+                        indices.iter().position(|&i| i == actual_index).unwrap_or(0);
+                    self.table_state.select(Some(self.selected_profile));
+                }
+            }
+            KeyCode::Char('d') => {
+                let indices = self.get_filtered_profiles_indices();
+                if let Some(&actual_index) = indices.get(self.selected_profile) {
+                    let profile = &mut self.config.profiles[actual_index];
+                    profile.enabled = !profile.enabled;
+                    let msg = format!(
+                        "{} {}",
+                        if profile.enabled {
+                            "Enabled"
+                        } else {
+                            "Disabled"
+                        },
+                        profile.name
+                    );
+                    self.save_config();
+                    self.set_status_message(msg);
+
+                    // A newly disabled profile may drop out of the list
+                    let len = self.get_filtered_profiles_indices().len();
+                    self.selected_profile = self.selected_profile.min(len.saturating_sub(1));
+                    self.table_state.select(Some(self.selected_profile));
+                }
+            }
+            KeyCode::Char('u') => {
+                self.show_disabled = !self.show_disabled;
+                self.selected_profile = 0;
+                self.table_state.select(Some(0));
+                self.set_status_message(format!(
+                    "Disabled profiles {}",
+                    if self.show_disabled {
+                        "shown"
+                    } else {
+                        "hidden"
+                    }
+                ));
+            }
+            KeyCode::Char('*') => self.toggle_favorite(),
+            KeyCode::Char(',') => {
+                self.settings_form = Some(SettingsForm::new(
+                    &self.config.settings,
+                    self.auto_reconnect,
+                ));
+                self.screen = Screen::Settings;
+                self.input_mode = InputMode::Editing;
+            }
+            KeyCode::Char('C') => self.batch_filtered(true),
+            KeyCode::Char('D') => self.batch_filtered(false),
+            KeyCode::Char('F') => {
+                self.favorites_only = !self.favorites_only;
+                self.selected_profile = 0;
+                self.table_state.select(Some(0));
+                self.set_status_message(
+                    if self.favorites_only {
+                        "Showing favorites only"
+                    } else {
+                        "Showing all profiles"
+                    }
+                    .to_string(),
+                );
+            }
+            KeyCode::Char('P') => self.spawn_reachability_sweep(),
+            KeyCode::Char('O') => self.solo_selected(),
+            KeyCode::Char('V') => self.cycle_log_level(),
+            KeyCode::Char('M') => {
+                if self.config.sequences.is_empty() {
+                    self.set_status_message(
+                        "No sequences defined ([[sequences]] in config.toml)".to_string(),
+                    );
+                } else {
+                    self.sequence_choice = 0;
+                    self.screen = Screen::SequenceModal;
+                }
+            }
+            KeyCode::Char('H') => self.open_history(),
+            KeyCode::Char('L') => {
+                self.logs_selected = 0;
+                self.logs_searching = false;
+                self.screen = Screen::Logs;
+            }
+            KeyCode::Char('B') => self.spawn_report_bundle()?,
+            KeyCode::Char('T') => {
+                let show = !self.config.settings.show_last_ip;
+                self.config.settings.show_last_ip = show;
+                self.save_config();
+                self.set_status_message(
+                    if show {
+                        "Showing last IP of disconnected profiles"
+                    } else {
+                        "Hiding last IP of disconnected profiles"
+                    }
+                    .to_string(),
+                );
+            }
+            KeyCode::Char('y') => {
+                let indices = self.get_filtered_profiles_indices();
+                if let Some(&actual_index) = indices.get(self.selected_profile) {
+                    let profile = &self.config.profiles[actual_index];
+                    let snippet = profile.to_snippet(
+                        crate::config::SnippetFormat::Toml,
+                        self.config.settings.redact_shared_username,
+                    )?;
+                    let name = profile.name.clone();
+                    match crate::clipboard::copy(&snippet).await {
+                        Ok(()) => self.set_status_message(format!("Copied {} as TOML", name)),
+                        Err(e) => self.set_status_message(format!("Copy failed: {}", e)),
+                    }
+                }
+            }
+            KeyCode::Char('A') => {
+                let display = self.config.settings.display_primary.toggle();
+                self.config.settings.display_primary = display;
+                self.save_config();
+                self.set_status_message(format!(
+                    "Showing {} first",
+                    if display == DisplayPrimary::Alias {
+                        "aliases"
                     } else {
+                        "names"
+                    }
+                ));
+            }
+            KeyCode::Char('g') => {
+                let indices = self.get_filtered_profiles_indices();
+                if let Some(&actual_index) = indices.get(self.selected_profile) {
+                    self.gateway_input
+                        .set(self.config.profiles[actual_index].gateway_display());
+                    self.screen = Screen::GatewayModal;
+                    self.input_mode = InputMode::Editing;
+                }
+            }
+            KeyCode::Char('p') => {
+                if !self.get_filtered_profiles_indices().is_empty() {
+                    self.password_input = TextInput::default();
+                    self.screen = Screen::PasswordModal;
+                    self.input_mode = InputMode::Editing;
+                }
+            }
+            KeyCode::Char('o') => {
+                let indices = self.get_filtered_profiles_indices();
+                if !indices.is_empty() && self.selected_profile < indices.len() {
+                    let actual_index = indices[self.selected_profile];
+                    let current = self.config.profiles[actual_index]
+                        .color
+                        .clone()
+                        .unwrap_or_else(|| "none".to_string());
+                    self.color_choice = PROFILE_COLORS
+                        .iter()
+                        .position(|c| c.eq_ignore_ascii_case(&current))
+                        .unwrap_or(0);
+                    self.screen = Screen::ColorModal;
+                }
+            }
+            KeyCode::Char('I') => {
+                if let Ok(added) = self.config.auto_import_profiles() {
+                    if added.is_empty() {
                         self.set_status_message(
                             "No new profiles found in standard locations".to_string(),
                         );
+                    } else {
+                        self.announce_auto_import(&added);
                     }
                 }
             }
@@ -349,6 +1260,7 @@ impl App {
             }
             KeyCode::Char('R') => {
                 self.auto_reconnect = !self.auto_reconnect;
+                self.save_ui_state();
                 self.set_status_message(format!(
                     "Auto-reconnect: {}",
                     if self.auto_reconnect { "ON" } else { "OFF" }
@@ -366,10 +1278,10 @@ impl App {
                 self.input_mode = InputMode::Normal;
             }
             KeyCode::Enter => {
-                self.save_new_profile()?;
+                self.save_new_profile().await?;
             }
             KeyCode::Tab => {
-                self.input_field = (self.input_field + 1) % 6;
+                self.input_field = (self.input_field + 1) % FORM_FIELDS;
                 // Skip the name field (index 0) if editing
                 if self.screen == Screen::EditProfile && self.input_field == 0 {
                     self.input_field = 1;
@@ -377,28 +1289,28 @@ impl App {
             }
             KeyCode::BackTab => {
                 self.input_field = if self.input_field == 0 {
-                    5
+                    FORM_FIELDS - 1
                 } else {
                     self.input_field - 1
                 };
                 // Skip the name field (index 0) if editing
                 if self.screen == Screen::EditProfile && self.input_field == 0 {
-                    self.input_field = 5;
+                    self.input_field = FORM_FIELDS - 1;
                 }
             }
-            KeyCode::Char(c) => {
-                // Prevent editing name field if in EditProfile screen
-                if !(self.screen == Screen::EditProfile && self.input_field == 0) {
-                    self.add_profile_data[self.input_field].push(c);
-                }
+            KeyCode::Left if self.input_field == PROTOCOL_FIELD => {
+                self.form_protocol = self.form_protocol.cycle(false);
             }
-            KeyCode::Backspace => {
+            KeyCode::Right | KeyCode::Char(' ') if self.input_field == PROTOCOL_FIELD => {
+                self.form_protocol = self.form_protocol.cycle(true);
+            }
+            _ if self.input_field == PROTOCOL_FIELD => {}
+            _ => {
                 // Prevent editing name field if in EditProfile screen
                 if !(self.screen == Screen::EditProfile && self.input_field == 0) {
-                    self.add_profile_data[self.input_field].pop();
+                    self.add_profile_data[self.input_field].handle_key(key);
                 }
             }
-            _ => {}
         }
         Ok(())
     }
@@ -422,13 +1334,9 @@ impl App {
                 self.file_browser = Some(FileBrowser::new()?);
                 self.screen = Screen::FileBrowser;
             }
-            KeyCode::Char(c) => {
-                self.input_buffer.push(c);
+            _ => {
+                self.input_buffer.handle_key(key);
             }
-            KeyCode::Backspace => {
-                self.input_buffer.pop();
-            }
-            _ => {}
         }
         Ok(())
     }
@@ -447,7 +1355,7 @@ impl App {
                 }
                 KeyCode::Enter => {
                     if let Some(path) = browser.enter()? {
-                        self.input_buffer = path.to_string_lossy().to_string();
+                        self.input_buffer.set(path.to_string_lossy());
                         self.screen = Screen::ImportXml;
                     }
                 }
@@ -468,22 +1376,74 @@ impl App {
 
     async fn handle_search_key(&mut self, key: KeyEvent) -> Result<()> {
         match key.code {
-            KeyCode::Esc | KeyCode::Enter => {
+            KeyCode::Enter => {
+                self.screen = Screen::Main;
+                self.input_mode = InputMode::Normal;
+                self.search_history_pos = None;
+                self.record_search()?;
+            }
+            KeyCode::Esc => {
                 self.screen = Screen::Main;
                 self.input_mode = InputMode::Normal;
+                self.search_history_pos = None;
             }
-            KeyCode::Char(c) => {
-                self.search_query.push(c);
+            KeyCode::Up => {
+                let history = &self.config.settings.search_history;
+                let pos = match self.search_history_pos {
+                    None if history.is_empty() => return Ok(()),
+                    None => {
+                        self.search_draft = self.search_query.value().to_string();
+                        0
+                    }
+                    Some(pos) => (pos + 1).min(history.len() - 1),
+                };
+                self.search_query.set(history[pos].clone());
+                self.search_history_pos = Some(pos);
                 self.selected_profile = 0;
                 self.table_state.select(Some(0));
             }
-            KeyCode::Backspace => {
-                self.search_query.pop();
+            KeyCode::Down => {
+                match self.search_history_pos {
+                    None => return Ok(()),
+                    Some(0) => {
+                        self.search_query
+                            .set(std::mem::take(&mut self.search_draft));
+                        self.search_history_pos = None;
+                    }
+                    Some(pos) => {
+                        let query = self.config.settings.search_history[pos - 1].clone();
+                        self.search_query.set(query);
+                        self.search_history_pos = Some(pos - 1);
+                    }
+                }
                 self.selected_profile = 0;
                 self.table_state.select(Some(0));
             }
-            _ => {}
+            _ => {
+                if self.search_query.handle_key(key) {
+                    self.search_history_pos = None;
+                    self.selected_profile = 0;
+                    self.table_state.select(Some(0));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Move the current query to the front of the search history
+    fn record_search(&mut self) -> Result<()> {
+        let query = self.search_query.value().trim().to_string();
+        if query.is_empty() {
+            return Ok(());
         }
+        let history = &mut self.config.settings.search_history;
+        if history.first() == Some(&query) {
+            return Ok(());
+        }
+        history.retain(|q| q != &query);
+        history.insert(0, query);
+        history.truncate(SEARCH_HISTORY_LIMIT);
+        self.save_config();
         Ok(())
     }
 
@@ -500,589 +1460,2833 @@ impl App {
                     let alias = if self.alias_input.is_empty() {
                         None
                     } else {
-                        Some(self.alias_input.clone())
+                        Some(self.alias_input.value().to_string())
                     };
                     self.config.profiles[actual_index].aliases = alias;
-                    self.config.save()?;
+                    self.save_config();
                     self.set_status_message("Alias updated".to_string());
                 }
                 self.screen = Screen::Main;
                 self.input_mode = InputMode::Normal;
             }
-            KeyCode::Backspace => {
-                self.alias_input.pop();
+            _ => {
+                self.alias_input.handle_key(key);
             }
-            KeyCode::Char(c) => {
-                self.alias_input.push(c);
-            }
-            _ => {}
         }
         Ok(())
     }
 
-    async fn handle_delete_confirmation_key(&mut self, key: KeyEvent) -> Result<()> {
+    fn handle_settings_key(&mut self, key: KeyEvent) {
+        let Some(form) = &mut self.settings_form else {
+            self.screen = Screen::Main;
+            return;
+        };
+        let rows = SettingsField::ALL.len();
         match key.code {
-            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
-                self.delete_selected_profile()?;
+            KeyCode::Esc => {
+                self.settings_form = None;
                 self.screen = Screen::Main;
+                self.input_mode = InputMode::Normal;
             }
-            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
-                self.screen = Screen::Main;
+            KeyCode::Enter => self.save_settings(),
+            KeyCode::Tab | KeyCode::Down => form.field = (form.field + 1) % rows,
+            KeyCode::BackTab | KeyCode::Up => form.field = (form.field + rows - 1) % rows,
+            _ => {
+                if let Some(input) = form.input_mut() {
+                    input.handle_key(key);
+                } else if let KeyCode::Left = key.code {
+                    form.change(false);
+                } else if let KeyCode::Right | KeyCode::Char(' ') = key.code {
+                    form.change(true);
+                }
             }
-            _ => {}
         }
-        Ok(())
     }
 
-    fn import_profiles_from_file(&mut self) -> Result<()> {
-        let path = self.input_buffer.trim().to_string();
-        if path.is_empty() {
-            return Ok(());
+    /// Validate the settings screen and apply it: written to the config and
+    /// in effect right away, without a restart
+    fn save_settings(&mut self) {
+        let Some(form) = &mut self.settings_form else {
+            return;
+        };
+        let parsed = parse_interval(&form.status_check_interval)
+            .map_err(|e| (SettingsField::StatusCheckInterval, e))
+            .and_then(|interval| {
+                parse_interval(&form.reconnect_delay)
+                    .map(|delay| (interval, delay))
+                    .map_err(|e| (SettingsField::ReconnectDelay, e))
+            });
+        let (interval, delay) = match parsed {
+            Ok(values) => values,
+            Err((field, error)) => {
+                form.field = SettingsField::ALL
+                    .iter()
+                    .position(|f| *f == field)
+                    .unwrap_or(0);
+                form.error = Some((field, error));
+                return;
+            }
+        };
+        let Some(form) = self.settings_form.take() else {
+            return;
+        };
+
+        let log_level = LOG_LEVELS[form.log_level];
+        if !log_level.eq_ignore_ascii_case(&self.log_level)
+            && let Some(set_log_level) = &self.set_log_level
+        {
+            let result = set_log_level(log_level);
+            match result {
+                Ok(()) => self.log_level = log_level.to_string(),
+                Err(e) => self.log(
+                    LogLevel::Warning,
+                    format!("Could not change log level: {}", e),
+                ),
+            }
         }
 
-        match std::fs::read_to_string(&path) {
-            Ok(content) => {
-                match Config::import_from_xml(&content) {
-                    Ok(new_profiles) => {
-                        let count = new_profiles.len();
-                        for profile in new_profiles {
-                            // Avoid duplicates by name
-                            if !self.config.profiles.iter().any(|p| p.name == profile.name) {
-                                self.config.profiles.push(profile);
-                            }
+        let settings = &mut self.config.settings;
+        settings.reconnect_delay_seconds = delay;
+        settings.status_check_interval_seconds = interval;
+        settings.log_level = log_level.to_string();
+        settings.allow_multiple_connections = form.allow_multiple_connections;
+        settings.kill_switch = form.kill_switch;
+        settings.kill_switch_dry_run = form.kill_switch_dry_run;
+        settings.batch_keep_going = form.batch_keep_going;
+        settings.confirm_before_connect = form.confirm_before_connect;
+        settings.check_dns_leak = form.check_dns_leak;
+        self.auto_reconnect = form.auto_reconnect;
+        self.vpn_manager = self
+            .vpn_manager
+            .clone()
+            .with_latency_interval(std::time::Duration::from_secs(interval))
+            .with_multiple_connections(form.allow_multiple_connections)
+            .with_kill_switch_dry_run(form.kill_switch_dry_run);
+        self.refresh_interval.send_replace(interval);
+        // Also writes the config, with auto_reconnect
+        self.save_ui_state();
+
+        self.screen = Screen::Main;
+        self.input_mode = InputMode::Normal;
+        self.set_status_message("Settings saved".to_string());
+    }
+
+    async fn handle_gateway_modal_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.screen = Screen::Main;
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                let gateway = self.gateway_input.value().trim().to_string();
+                if gateway.is_empty() {
+                    self.set_status_message("Gateway cannot be empty".to_string());
+                    return Ok(());
+                }
+                let indices = self.get_filtered_profiles_indices();
+                if let Some(&actual_index) = indices.get(self.selected_profile) {
+                    let profile = &mut self.config.profiles[actual_index];
+                    profile.gateway_address = gateway;
+                    profile.port = None;
+                    profile.normalize_gateway();
+                    self.save_config();
+                    self.set_status_message("Gateway updated".to_string());
+                }
+                self.screen = Screen::Main;
+                self.input_mode = InputMode::Normal;
+            }
+            _ => {
+                self.gateway_input.handle_key(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Enter saves the typed password in the OS credential store, or removes
+    /// the saved one when nothing was typed
+    async fn handle_password_modal_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.password_input = TextInput::default();
+                self.screen = Screen::Main;
+                self.input_mode = InputMode::Normal;
+            }
+            KeyCode::Enter => {
+                let password = std::mem::take(&mut self.password_input);
+                let indices = self.get_filtered_profiles_indices();
+                if let Some(&actual_index) = indices.get(self.selected_profile) {
+                    let name = self.config.profiles[actual_index].name.clone();
+                    let result = if password.is_empty() {
+                        self.vpn_manager
+                            .delete_password(&name)
+                            .await
+                            .map(|()| format!("Password removed for {}", name))
+                    } else {
+                        self.vpn_manager
+                            .store_password(&name, password.value())
+                            .await
+                            .map(|()| format!("Password saved for {}", name))
+                    };
+                    match result {
+                        Ok(message) => {
+                            self.log(LogLevel::Success, message.clone());
+                            self.set_status_message(message);
+                        }
+                        Err(e) => {
+                            self.log(LogLevel::Error, e.to_string());
+                            self.set_status_message(e.to_string());
                         }
-                        self.config.save()?;
-                        self.screen = Screen::Main;
-                        self.input_mode = InputMode::Normal;
-                        self.set_status_message(format!("Imported {} profiles", count));
-                        self.add_log(format!(
-                            "Successfully imported {} profiles from {}",
-                            count, path
-                        ));
-                    }
-                    Err(e) => {
-                        self.set_status_message(format!("Import error: {}", e));
-                        self.add_log(format!("Error parsing XML from {}: {}", path, e));
                     }
                 }
+                self.screen = Screen::Main;
+                self.input_mode = InputMode::Normal;
             }
-            Err(e) => {
-                self.set_status_message(format!("File error: {}", e));
-                self.add_log(format!("Error reading file {}: {}", path, e));
+            _ => {
+                self.password_input.handle_key(key);
             }
         }
         Ok(())
     }
 
-    async fn toggle_connection(&mut self) -> Result<()> {
-        let indices = self.get_filtered_profiles_indices();
-        if indices.is_empty() || self.selected_profile >= indices.len() {
-            return Ok(());
+    fn open_wizard(&mut self, profile_name: String) {
+        let mut file_input = TextInput::default();
+        if let Some(path) = Config::find_profile_source(&profile_name) {
+            file_input.set(path.display().to_string());
         }
+        self.log(
+            LogLevel::Warning,
+            format!("No system VPN service for {}", profile_name),
+        );
+        self.wizard = Some(ConnectWizard {
+            profile_name,
+            step: WizardStep::Explain,
+            file_input,
+        });
+        self.screen = Screen::ConnectWizard;
+    }
 
-        let actual_index = indices[self.selected_profile];
-        let profile = self.config.profiles[actual_index].clone();
-        let profile_name = profile.name.clone();
-        let vpn_manager = self.vpn_manager.clone();
-        let event_tx = self.event_tx.clone();
+    fn close_wizard(&mut self) {
+        self.wizard = None;
+        self.screen = Screen::Main;
+        self.input_mode = InputMode::Normal;
+    }
 
-        if event_tx.is_none() {
+    async fn handle_wizard_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(wizard) = &mut self.wizard else {
+            self.close_wizard();
             return Ok(());
-        }
-        let event_tx = event_tx.unwrap();
-
-        tokio::spawn(async move {
-            use std::time::Instant;
-            use tokio::time::{Duration, sleep};
+        };
 
-            match vpn_manager.get_status(&profile_name).await {
-                VpnStatus::Connected => {
-                    let _ = event_tx
-                        .send(AppEvent::SetStatusMessage(format!(
-                            "Disconnecting from {}...",
-                            profile_name
-                        )))
-                        .await;
-                    let _ = event_tx
-                        .send(AppEvent::Notification(format!(
-                            "Disconnecting from {}...",
-                            profile_name
-                        )))
-                        .await;
+        if key.code == KeyCode::Esc {
+            let name = wizard.profile_name.clone();
+            for line in crate::vpn::not_configured_help(&name).lines() {
+                self.log(LogLevel::Info, line.to_string());
+            }
+            self.close_wizard();
+            return Ok(());
+        }
 
-                    match vpn_manager.disconnect(&profile_name).await {
-                        Ok(_) => {
-                            let start = Instant::now();
-                            let timeout = Duration::from_secs(20);
-                            loop {
-                                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
-                                match vpn_manager.get_status(&profile_name).await {
-                                    VpnStatus::Disconnected => {
-                                        let _ = event_tx
-                                            .send(AppEvent::SetStatusMessage(format!(
-                                                "Disconnected from {}",
-                                                profile_name
-                                            )))
-                                            .await;
-                                        let _ = event_tx
-                                            .send(AppEvent::Notification(format!(
-                                                "Successfully disconnected from {}",
-                                                profile_name
-                                            )))
-                                            .await;
-                                        break;
-                                    }
-                                    VpnStatus::Error(e) => {
-                                        let _ = event_tx
-                                            .send(AppEvent::SetStatusMessage(format!(
-                                                "Disconnect error: {}",
-                                                e
-                                            )))
-                                            .await;
-                                        let _ = event_tx
-                                            .send(AppEvent::Notification(format!(
-                                                "Disconnect error for {}: {}",
-                                                profile_name, e
-                                            )))
-                                            .await;
-                                        break;
-                                    }
-                                    _ => {
-                                        if start.elapsed() > timeout {
-                                            let _ = event_tx
-                                                .send(AppEvent::SetStatusMessage(format!(
-                                                    "Timeout while disconnecting {}",
-                                                    profile_name
-                                                )))
-                                                .await;
-                                            let _ = event_tx
-                                                .send(AppEvent::Notification(format!(
-                                                    "Timeout waiting for disconnection of {}",
-                                                    profile_name
-                                                )))
-                                                .await;
-                                            break;
-                                        }
-                                        sleep(Duration::from_millis(200)).await;
-                                    }
-                                }
-                            }
+        match wizard.step {
+            WizardStep::Explain => {
+                if key.code == KeyCode::Enter {
+                    wizard.step = WizardStep::ChooseFile;
+                    self.input_mode = InputMode::Editing;
+                }
+            }
+            WizardStep::ChooseFile => match key.code {
+                KeyCode::Enter => {
+                    let path = std::path::PathBuf::from(wizard.file_input.value().trim());
+                    if !path.is_file() {
+                        self.set_status_message(format!("File not found: {}", path.display()));
+                        return Ok(());
+                    }
+                    match crate::vpn::open_in_azure_client(&path).await {
+                        Ok(()) => {
+                            wizard.step = WizardStep::Recheck;
+                            self.input_mode = InputMode::Normal;
+                            self.log(
+                                LogLevel::Info,
+                                format!("Opened {} in the Azure VPN Client", path.display()),
+                            );
                         }
                         Err(e) => {
-                            let _ = event_tx
-                                .send(AppEvent::SetStatusMessage(format!(
-                                    "Failed to disconnect: {}",
-                                    e
-                                )))
-                                .await;
-                            let _ = event_tx
-                                .send(AppEvent::Notification(format!(
-                                    "Error disconnecting from {}: {}",
-                                    profile_name, e
-                                )))
-                                .await;
+                            self.set_status_message(format!("Could not open file: {}", e));
                         }
                     }
                 }
+                // Already imported by hand: go straight to the check
+                KeyCode::Tab => {
+                    wizard.step = WizardStep::Recheck;
+                    self.input_mode = InputMode::Normal;
+                }
                 _ => {
-                    let max_retries = 2u32;
-                    let mut attempt: u32 = 0;
-                    let timeout = Duration::from_secs(30);
+                    wizard.file_input.handle_key(key);
+                }
+            },
+            WizardStep::Recheck => {
+                if key.code == KeyCode::Enter {
+                    let name = wizard.profile_name.clone();
+                    let status = self.vpn_manager.refresh_profile(&name).await;
+                    self.refresh_from_manager().await?;
+                    if status == VpnStatus::NotConfigured {
+                        self.set_status_message(format!(
+                            "Still no system VPN service for {} — finish the import and retry",
+                            name
+                        ));
+                        return Ok(());
+                    }
 
-                    loop {
-                        let _ = event_tx
-                            .send(AppEvent::SetStatusMessage(format!(
-                                "Connecting to {}... (attempt {}/{})",
-                                profile_name,
-                                attempt + 1,
-                                max_retries + 1
-                            )))
-                            .await;
+                    self.log(
+                        LogLevel::Success,
+                        format!("System VPN service for {} found", name),
+                    );
+                    self.close_wizard();
+                    let indices = self.get_filtered_profiles_indices();
+                    let selected = indices
+                        .get(self.selected_profile)
+                        .map(|&i| self.config.profiles[i].name.as_str());
+                    if selected == Some(name.as_str()) {
+                        self.toggle_connection().await?;
+                    } else {
+                        self.set_status_message(format!("{} is ready to connect", name));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 
-                        // Check for other active VPNs and inform user if we need to disconnect them
-                        if let Ok(active) = vpn_manager.get_active_vpns().await {
-                            for (name, _) in active {
-                                if name != profile_name {
-                                    let _ = event_tx
-                                        .send(AppEvent::SetStatusMessage(format!(
-                                            "Closing previous VPN: {}...",
-                                            name
-                                        )))
-                                        .await;
-                                    let _ = event_tx
-                                        .send(AppEvent::Notification(format!(
-                                            "Closing previous VPN: {}...",
-                                            name
-                                        )))
-                                        .await;
-                                }
-                            }
-                        }
+    async fn handle_color_modal_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Esc => {
+                self.screen = Screen::Main;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.color_choice = if self.color_choice == 0 {
+                    PROFILE_COLORS.len() - 1
+                } else {
+                    self.color_choice - 1
+                };
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.color_choice = (self.color_choice + 1) % PROFILE_COLORS.len();
+            }
+            KeyCode::Enter => {
+                let indices = self.get_filtered_profiles_indices();
+                if !indices.is_empty() && self.selected_profile < indices.len() {
+                    let actual_index = indices[self.selected_profile];
+                    let color = match PROFILE_COLORS[self.color_choice] {
+                        "none" => None,
+                        c => Some(c.to_string()),
+                    };
+                    self.config.profiles[actual_index].color = color;
+                    self.save_config();
+                    self.set_status_message("Color updated".to_string());
+                }
+                self.screen = Screen::Main;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 
-                        let connect_res = vpn_manager.connect(&profile).await;
+    fn handle_sequence_modal_key(&mut self, key: KeyEvent) -> Result<()> {
+        let count = self.config.sequences.len();
+        match key.code {
+            KeyCode::Esc => {
+                self.screen = Screen::Main;
+            }
+            KeyCode::Up | KeyCode::Char('k') if count > 0 => {
+                self.sequence_choice = (self.sequence_choice + count - 1) % count;
+            }
+            KeyCode::Down | KeyCode::Char('j') if count > 0 => {
+                self.sequence_choice = (self.sequence_choice + 1) % count;
+            }
+            KeyCode::Enter => {
+                self.screen = Screen::Main;
+                if let Some(sequence) = self.config.sequences.get(self.sequence_choice).cloned() {
+                    self.run_sequence(sequence);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
 
-                        if let Err(e) = connect_res {
-                            let _ = event_tx
-                                .send(AppEvent::Notification(format!(
-                                    "Connect error for {}: {}",
-                                    profile_name, e
-                                )))
-                                .await;
+    /// Every distinct tag with how many profiles carry it and how many of
+    /// those are connected, sorted by name
+    pub fn tag_counts(&self) -> Vec<(String, usize, usize)> {
+        let connected: HashSet<&str> = self
+            .connections
+            .iter()
+            .filter(|c| c.status == VpnStatus::Connected)
+            .map(|c| c.profile_name.as_str())
+            .collect();
+        let mut counts: std::collections::BTreeMap<&str, (usize, usize)> =
+            std::collections::BTreeMap::new();
+        for p in &self.config.profiles {
+            for tag in &p.tags {
+                let entry = counts.entry(tag.as_str()).or_default();
+                entry.0 += 1;
+                if connected.contains(p.name.as_str()) {
+                    entry.1 += 1;
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(tag, (total, connected))| (tag.to_string(), total, connected))
+            .collect()
+    }
 
-                            // If it failed due to a disconnection error, let's update the status and potentially retry
-                            let _ = event_tx
-                                .send(AppEvent::SetStatusMessage(format!("Error: {}", e)))
-                                .await;
-                        }
+    fn handle_category_filter_key(&mut self, key: KeyEvent) {
+        let tags = self.tag_counts();
+        let count = tags.len();
+        match key.code {
+            KeyCode::Esc => {
+                self.category_filter.clear();
+                self.screen = Screen::Main;
+            }
+            KeyCode::Char('f') | KeyCode::Char('q') => {
+                self.screen = Screen::Main;
+            }
+            KeyCode::Up | KeyCode::Char('k') if count > 0 => {
+                self.filter_choice = (self.filter_choice + count - 1) % count;
+            }
+            KeyCode::Down | KeyCode::Char('j') if count > 0 => {
+                self.filter_choice = (self.filter_choice + 1) % count;
+            }
+            KeyCode::Char(' ') | KeyCode::Enter => {
+                if let Some((tag, _, _)) = tags.get(self.filter_choice)
+                    && !self.category_filter.remove(tag)
+                {
+                    self.category_filter.insert(tag.clone());
+                }
+            }
+            _ => return,
+        }
+        self.selected_profile = 0;
+        self.table_state.select(Some(0));
+    }
 
-                        let start = Instant::now();
-                        let mut connected = false;
-                        loop {
-                            let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
-                            match vpn_manager.get_status(&profile_name).await {
-                                VpnStatus::Connected => {
-                                    connected = true;
-                                    break;
-                                }
-                                VpnStatus::Error(e) => {
-                                    let _ = event_tx
-                                        .send(AppEvent::Notification(format!(
-                                            "Status error while connecting {}: {}",
-                                            profile_name, e
-                                        )))
-                                        .await;
-                                    break;
-                                }
-                                _ => {
-                                    if start.elapsed() > timeout {
-                                        break;
-                                    }
-                                    sleep(Duration::from_millis(200)).await;
-                                }
-                            }
-                        }
+    /// The active tag filter for display, e.g. "prod, emea"
+    pub fn category_filter_label(&self) -> Option<String> {
+        if self.category_filter.is_empty() {
+            return None;
+        }
+        let mut tags: Vec<&str> = self.category_filter.iter().map(|t| t.as_str()).collect();
+        tags.sort_by_key(|t| t.to_lowercase());
+        Some(tags.join(", "))
+    }
 
-                        if connected {
-                            // Verify stabilization
-                            let _ = event_tx
-                                .send(AppEvent::SetStatusMessage(format!(
-                                    "Verifying connection to {}...",
-                                    profile_name
-                                )))
-                                .await;
+    /// Events loaded into the history screen
+    const HISTORY_SCREEN_LIMIT: usize = 500;
 
-                            // Check the status for a few seconds to ensure it stays connected
-                            let mut stable = true;
-                            for _ in 0..15 {
-                                sleep(Duration::from_millis(200)).await;
-                                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+    fn open_history(&mut self) {
+        match crate::history::load(None, Self::HISTORY_SCREEN_LIMIT) {
+            Ok(events) => {
+                self.history = events;
+                self.history_selected = 0;
+                self.screen = Screen::History;
+            }
+            Err(e) => self.log(
+                LogLevel::Error,
+                format!("Failed to read connection history: {}", e),
+            ),
+        }
+    }
 
-                                // Ensure our target VPN is still connected
-                                if !matches!(
-                                    vpn_manager.get_status(&profile_name).await,
-                                    VpnStatus::Connected
-                                ) {
-                                    stable = false;
-                                    break;
-                                }
+    fn handle_history_key(&mut self, key: KeyEvent) {
+        let last = self.history.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('H') => {
+                self.screen = Screen::Main;
+                self.history.clear();
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.history_selected = self.history_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.history_selected = (self.history_selected + 1).min(last);
+            }
+            KeyCode::PageUp => {
+                self.history_selected = self.history_selected.saturating_sub(10);
+            }
+            KeyCode::PageDown => {
+                self.history_selected = (self.history_selected + 10).min(last);
+            }
+            KeyCode::Home => self.history_selected = 0,
+            KeyCode::End => self.history_selected = last,
+            _ => {}
+        }
+    }
 
-                                if let Ok(active) = vpn_manager.get_active_vpns().await
-                                    && active.iter().any(|(name, _)| name != &profile_name)
-                                {
-                                    let _ = event_tx.send(AppEvent::Notification("Another active VPN detected during stabilization. Ensuring exclusivity...".to_string())).await;
-                                    for (name, _) in active {
-                                        if name != profile_name {
-                                            let _ = vpn_manager.disconnect(&name).await;
-                                        }
-                                    }
-                                }
-                            }
+    /// Buffered log lines passing the logs screen's filter and search,
+    /// newest first
+    pub fn visible_logs(&self) -> Vec<&LogEntry> {
+        let query = self.logs_query.value().to_lowercase();
+        self.logs
+            .iter()
+            .rev()
+            .filter(|entry| self.log_filter.matches(entry))
+            .filter(|entry| query.is_empty() || entry.message.to_lowercase().contains(&query))
+            .collect()
+    }
 
-                            if stable {
-                                let _ = event_tx
-                                    .send(AppEvent::SetStatusMessage(format!(
-                                        "Connected to {}",
-                                        profile_name
-                                    )))
-                                    .await;
-                                let _ = event_tx
-                                    .send(AppEvent::Notification(format!(
-                                        "Successfully connected to {}",
-                                        profile_name
-                                    )))
-                                    .await;
-                                break;
-                            } else {
-                                let _ = event_tx
-                                    .send(AppEvent::Notification(format!(
-                                        "Connection to {} dropped during stabilization",
-                                        profile_name
-                                    )))
-                                    .await;
-                                // Fall through to retry logic
-                            }
+    fn handle_logs_key(&mut self, key: KeyEvent) {
+        if self.logs_searching {
+            match key.code {
+                KeyCode::Enter => self.logs_searching = false,
+                KeyCode::Esc => {
+                    self.logs_searching = false;
+                    self.logs_query.clear();
+                }
+                _ => {
+                    self.logs_query.handle_key(key);
+                }
+            }
+            self.logs_selected = 0;
+            return;
+        }
+
+        let last = self.visible_logs().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('L') | KeyCode::Char('q') => self.screen = Screen::Main,
+            KeyCode::Char('/') => self.logs_searching = true,
+            KeyCode::Char('e') | KeyCode::Char('s') | KeyCode::Char('a') => {
+                self.log_filter = match key.code {
+                    KeyCode::Char('e') => LogFilter::Errors,
+                    KeyCode::Char('s') => LogFilter::Successes,
+                    _ => LogFilter::All,
+                };
+                self.logs_selected = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.logs_selected = self.logs_selected.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.logs_selected = (self.logs_selected + 1).min(last);
+            }
+            KeyCode::PageUp => self.logs_selected = self.logs_selected.saturating_sub(10),
+            KeyCode::PageDown => self.logs_selected = (self.logs_selected + 10).min(last),
+            KeyCode::Home => self.logs_selected = 0,
+            KeyCode::End => self.logs_selected = last,
+            _ => {}
+        }
+    }
+
+    async fn handle_delete_confirmation_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                self.delete_selected_profile().await?;
+                self.screen = Screen::Main;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.screen = Screen::Main;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Whether Enter on the selected profile should ask first: only with
+    /// `confirm_before_connect`, until "don't ask again", and never for a
+    /// disconnect
+    fn needs_connect_confirmation(&self) -> bool {
+        if !self.config.settings.confirm_before_connect || self.skip_connect_confirmation {
+            return false;
+        }
+        let indices = self.get_filtered_profiles_indices();
+        let Some(&actual_index) = indices.get(self.selected_profile) else {
+            return false;
+        };
+        let name = &self.config.profiles[actual_index].name;
+        !self
+            .connections
+            .iter()
+            .any(|c| &c.profile_name == name && c.status == VpnStatus::Connected)
+    }
+
+    async fn handle_connect_confirmation_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                self.screen = Screen::Main;
+                self.toggle_connection().await?;
+            }
+            KeyCode::Char('a') | KeyCode::Char('A') => {
+                self.skip_connect_confirmation = true;
+                self.screen = Screen::Main;
+                self.set_status_message(
+                    "Connecting without confirmation until remipn restarts".to_string(),
+                );
+                self.toggle_connection().await?;
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.screen = Screen::Main;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Mark or unmark the selected profile as a favorite, keeping it selected
+    /// as it moves to or from the top
+    fn toggle_favorite(&mut self) {
+        let indices = self.get_filtered_profiles_indices();
+        let Some(&actual_index) = indices.get(self.selected_profile) else {
+            return;
+        };
+        let profile = &mut self.config.profiles[actual_index];
+        profile.favorite = !profile.favorite;
+        let message = format!(
+            "{} {} favorites",
+            profile.name,
+            if profile.favorite {
+                "added to"
+            } else {
+                "removed from"
+            }
+        );
+        self.save_config();
+        self.set_status_message(message);
+
+        let indices = self.get_filtered_profiles_indices();
+        self.selected_profile = match indices.iter().position(|&i| i == actual_index) {
+            Some(pos) => pos,
+            // Unmarked while only favorites are shown
+            None => self.selected_profile.min(indices.len().saturating_sub(1)),
+        };
+        self.table_state.select(Some(self.selected_profile));
+    }
+
+    /// Move the selected profile past its visible neighbour in the config,
+    /// switching to the manual sort so the move shows. Disabled while
+    /// searching, since the ranking then decides the order.
+    fn move_selected_profile(&mut self, down: bool) {
+        if !self.search_query.is_empty() {
+            self.set_status_message("Clear the search to reorder profiles".to_string());
+            return;
+        }
+        if self.sort_column != SortColumn::Manual {
+            self.sort_column = SortColumn::Manual;
+            self.sort_direction = SortDirection::Asc;
+            self.save_ui_state();
+        }
+
+        let indices = self.get_filtered_profiles_indices();
+        let target = if down {
+            self.selected_profile + 1
+        } else {
+            match self.selected_profile.checked_sub(1) {
+                Some(target) => target,
+                None => return,
+            }
+        };
+        let (Some(&from), Some(&to)) = (indices.get(self.selected_profile), indices.get(target))
+        else {
+            return;
+        };
+        let profile = self.config.profiles.remove(from);
+        self.config.profiles.insert(to, profile);
+        self.save_config();
+
+        // The watched profile stays pinned, so find where the moved one landed
+        let name = &self.config.profiles[to].name;
+        if let Some(pos) = self
+            .get_filtered_profiles_indices()
+            .iter()
+            .position(|&i| &self.config.profiles[i].name == name)
+        {
+            self.selected_profile = pos;
+            self.table_state.select(Some(pos));
+        }
+    }
+
+    /// Remove the kill switch, or ask before installing it for the selected
+    /// profile. Installing is confirmed since the rules cut off all other
+    /// traffic; removing never is, so there's always a quick way out.
+    async fn toggle_kill_switch(&mut self) {
+        if self.kill_switch.is_some() {
+            match self.vpn_manager.disable_kill_switch().await {
+                Ok(()) => {
+                    self.kill_switch = None;
+                    self.log(LogLevel::Success, "Kill switch removed".to_string());
+                    self.set_status_message("Kill switch removed".to_string());
+                }
+                Err(e) => {
+                    self.log(LogLevel::Error, e.to_string());
+                    self.set_status_message(e.to_string());
+                }
+            }
+            return;
+        }
+        if !self.config.settings.kill_switch {
+            self.set_status_message(
+                "Set kill_switch = true under [settings] to use the kill switch".to_string(),
+            );
+            return;
+        }
+        let indices = self.get_filtered_profiles_indices();
+        let Some(&actual_index) = indices.get(self.selected_profile) else {
+            return;
+        };
+        let name = &self.config.profiles[actual_index].name;
+        if self.vpn_manager.get_status(name).await == VpnStatus::Connected {
+            self.screen = Screen::KillSwitchConfirm;
+        } else {
+            self.set_status_message(format!("Connect {} before enabling the kill switch", name));
+        }
+    }
+
+    async fn handle_kill_switch_confirm_key(&mut self, key: KeyEvent) -> Result<()> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => {
+                self.screen = Screen::Main;
+                let indices = self.get_filtered_profiles_indices();
+                if let Some(&actual_index) = indices.get(self.selected_profile) {
+                    let profile = self.config.profiles[actual_index].clone();
+                    self.apply_kill_switch(&profile).await;
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.screen = Screen::Main;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Install the kill switch rules for `profile`'s tunnel
+    async fn apply_kill_switch(&mut self, profile: &VpnProfile) {
+        match self.vpn_manager.enable_kill_switch(profile).await {
+            Ok(()) => {
+                self.kill_switch = self.vpn_manager.kill_switch_profile();
+                let message = if self.kill_switch.is_some() {
+                    format!(
+                        "Kill switch active: only {} may carry traffic",
+                        profile.name
+                    )
+                } else {
+                    "Kill switch dry run: the rules are in the log".to_string()
+                };
+                self.log(LogLevel::Warning, message.clone());
+                self.set_status_message(message);
+            }
+            Err(e) => {
+                let message = format!("Kill switch not enabled: {}", e);
+                self.log(LogLevel::Error, message.clone());
+                self.set_status_message(message);
+            }
+        }
+    }
+
+    /// The tunnel may come back on another interface after a drop, so the
+    /// rules are rebuilt once the protected profile is connected again.
+    /// Until then they keep blocking everything but the gateway.
+    async fn reapply_kill_switch(&mut self, previous: &[VpnConnection]) {
+        let Some(name) = self.kill_switch.clone() else {
+            return;
+        };
+        let was_connected = previous
+            .iter()
+            .any(|c| c.profile_name == name && c.status == VpnStatus::Connected);
+        let is_connected = self
+            .connections
+            .iter()
+            .any(|c| c.profile_name == name && c.status == VpnStatus::Connected);
+        if !was_connected
+            && is_connected
+            && let Some(profile) = self
+                .config
+                .profiles
+                .iter()
+                .find(|p| p.name == name)
+                .cloned()
+        {
+            self.apply_kill_switch(&profile).await;
+        }
+    }
+
+    fn import_profiles_from_file(&mut self) -> Result<()> {
+        let path = self.input_buffer.value().trim().to_string();
+        if path.is_empty() {
+            return Ok(());
+        }
+
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                match Config::import_from_contents(&content, std::path::Path::new(&path)) {
+                    Ok(new_profiles) => {
+                        let count = new_profiles.len();
+                        for p in new_profiles.iter().filter(|p| !p.protocol.is_known()) {
+                            self.log(
+                                LogLevel::Warning,
+                                format!(
+                                    "Unknown protocol '{}' for profile '{}'",
+                                    p.protocol, p.name
+                                ),
+                            );
                         }
+                        // Avoid duplicates by name
+                        self.config
+                            .merge_imported(crate::config::with_source(new_profiles, &path), false);
+                        self.save_config();
+                        self.screen = Screen::Main;
+                        self.input_mode = InputMode::Normal;
+                        self.set_status_message(format!("Imported {} profiles", count));
+                        self.log(
+                            LogLevel::Success,
+                            format!("Successfully imported {} profiles from {}", count, path),
+                        );
+                    }
+                    Err(e) => {
+                        self.set_status_message(format!("Import error: {}", e));
+                        self.log(LogLevel::Error, format!("Error parsing {}: {}", path, e));
+                    }
+                }
+            }
+            Err(e) => {
+                self.set_status_message(format!("File error: {}", e));
+                self.log(
+                    LogLevel::Error,
+                    format!("Error reading file {}: {}", path, e),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn toggle_connection(&mut self) -> Result<()> {
+        let indices = self.get_filtered_profiles_indices();
+        if indices.is_empty() || self.selected_profile >= indices.len() {
+            return Ok(());
+        }
+
+        let actual_index = indices[self.selected_profile];
+        let profile = self.config.profiles[actual_index].clone();
+        let profile_name = profile.name.clone();
+        let policy =
+            crate::config::ConnectPolicy::resolve(None, None, &profile, &self.config.settings);
+
+        // Ask the system up front so a missing service leads into the setup
+        // wizard instead of a connect that can't succeed
+        let not_configured = match self.vpn_manager.get_status(&profile_name).await {
+            VpnStatus::NotConfigured => true,
+            VpnStatus::Disconnected => {
+                self.vpn_manager.refresh_profile(&profile_name).await == VpnStatus::NotConfigured
+            }
+            _ => false,
+        };
+        if not_configured {
+            self.open_wizard(profile_name);
+            return Ok(());
+        }
+
+        // Disconnecting the target, or closing the other tunnels to keep the
+        // new one exclusive, is user-initiated and must not trigger a drop alert
+        let disconnecting =
+            self.vpn_manager.get_status(&profile_name).await == VpnStatus::Connected;
+        if disconnecting {
+            self.expected_disconnects.insert(profile_name.clone());
+        } else {
+            self.expected_disconnects.remove(&profile_name);
+            for conn in &self.connections {
+                if conn.status == VpnStatus::Connected && conn.profile_name != profile_name {
+                    self.expected_disconnects.insert(conn.profile_name.clone());
+                }
+            }
+        }
+
+        let vpn_manager = self.vpn_manager.clone();
+        let event_tx = self.event_tx.clone();
+
+        if event_tx.is_none() {
+            return Ok(());
+        }
+        let event_tx = event_tx.unwrap();
+
+        // Every log line of this operation carries the same id so interleaved
+        // operations can be told apart
+        let op_id = crate::vpn::operation_id();
+        self.log(
+            LogLevel::Info,
+            format!("[{}] Toggling {}", op_id, profile_name),
+        );
+        let label = format!(
+            "{} {}",
+            if disconnecting {
+                "Disconnecting from"
+            } else {
+                "Connecting to"
+            },
+            profile_name
+        );
+
+        let handle = tokio::spawn(async move {
+            use std::time::Instant;
+            use tokio::time::{Duration, sleep};
+
+            let note = |level: LogLevel, msg: String| {
+                AppEvent::Notification(level, format!("[{}] {}", op_id, msg))
+            };
+
+            match vpn_manager.get_status(&profile_name).await {
+                VpnStatus::Connected => {
+                    let _ = event_tx
+                        .send(AppEvent::SetStatusMessage(format!(
+                            "Disconnecting from {}...",
+                            profile_name
+                        )))
+                        .await;
+                    let _ = event_tx
+                        .send(note(
+                            LogLevel::Info,
+                            format!("Disconnecting from {}...", profile_name),
+                        ))
+                        .await;
+
+                    match vpn_manager.disconnect(&profile_name).await {
+                        Ok(_) => {
+                            let start = Instant::now();
+                            let timeout = Duration::from_secs(20);
+                            loop {
+                                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                                match vpn_manager.get_status(&profile_name).await {
+                                    VpnStatus::Disconnected => {
+                                        let _ = event_tx
+                                            .send(AppEvent::SetStatusMessage(format!(
+                                                "Disconnected from {}",
+                                                profile_name
+                                            )))
+                                            .await;
+                                        let _ = event_tx
+                                            .send(note(
+                                                LogLevel::Success,
+                                                format!(
+                                                    "Successfully disconnected from {}",
+                                                    profile_name
+                                                ),
+                                            ))
+                                            .await;
+                                        break;
+                                    }
+                                    VpnStatus::Error(e) => {
+                                        let _ = event_tx
+                                            .send(AppEvent::SetStatusMessage(format!(
+                                                "Disconnect error: {}",
+                                                e
+                                            )))
+                                            .await;
+                                        let _ = event_tx
+                                            .send(note(
+                                                LogLevel::Error,
+                                                format!(
+                                                    "Disconnect error for {}: {}",
+                                                    profile_name, e
+                                                ),
+                                            ))
+                                            .await;
+                                        break;
+                                    }
+                                    _ => {
+                                        if start.elapsed() > timeout {
+                                            let _ = event_tx
+                                                .send(AppEvent::SetStatusMessage(format!(
+                                                    "Timeout while disconnecting {}",
+                                                    profile_name
+                                                )))
+                                                .await;
+                                            let _ = event_tx
+                                                .send(note(
+                                                    LogLevel::Error,
+                                                    format!(
+                                                        "Timeout waiting for disconnection of {}",
+                                                        profile_name
+                                                    ),
+                                                ))
+                                                .await;
+                                            break;
+                                        }
+                                        sleep(Duration::from_millis(200)).await;
+                                    }
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            let _ = event_tx
+                                .send(AppEvent::SetStatusMessage(format!(
+                                    "Failed to disconnect: {}",
+                                    e
+                                )))
+                                .await;
+                            let _ = event_tx
+                                .send(note(
+                                    LogLevel::Error,
+                                    format!("Error disconnecting from {}: {}", profile_name, e),
+                                ))
+                                .await;
+                        }
+                    }
+                }
+                _ => {
+                    let max_retries = policy.retries;
+                    let mut attempt: u32 = 0;
+                    let timeout = policy.timeout();
+
+                    loop {
+                        let _ = event_tx
+                            .send(AppEvent::SetStatusMessage(format!(
+                                "Connecting to {}... (attempt {}/{})",
+                                profile_name,
+                                attempt + 1,
+                                max_retries + 1
+                            )))
+                            .await;
+
+                        // Check for other active VPNs and inform user if we need to disconnect them
+                        if !vpn_manager.allows_multiple_connections()
+                            && let Ok(active) = vpn_manager.get_active_vpns().await
+                        {
+                            for (name, _) in active {
+                                if name != profile_name {
+                                    let _ = event_tx
+                                        .send(AppEvent::SetStatusMessage(format!(
+                                            "Closing previous VPN: {}...",
+                                            name
+                                        )))
+                                        .await;
+                                    let _ = event_tx
+                                        .send(note(
+                                            LogLevel::Info,
+                                            format!("Closing previous VPN: {}...", name),
+                                        ))
+                                        .await;
+                                }
+                            }
+                        }
+
+                        let connect_res = vpn_manager.connect(&profile).await;
+
+                        if let Err(e) = connect_res {
+                            let _ = event_tx
+                                .send(note(
+                                    LogLevel::Error,
+                                    format!("Connect error for {}: {}", profile_name, e),
+                                ))
+                                .await;
+
+                            // If it failed due to a disconnection error, let's update the status and potentially retry
+                            let _ = event_tx
+                                .send(AppEvent::SetStatusMessage(format!("Error: {}", e)))
+                                .await;
+                        }
+
+                        let start = Instant::now();
+                        let mut connected = false;
+                        loop {
+                            let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                            match vpn_manager.get_status(&profile_name).await {
+                                VpnStatus::Connected => {
+                                    connected = true;
+                                    break;
+                                }
+                                VpnStatus::Error(e) => {
+                                    let _ = event_tx
+                                        .send(note(
+                                            LogLevel::Error,
+                                            format!(
+                                                "Status error while connecting {}: {}",
+                                                profile_name, e
+                                            ),
+                                        ))
+                                        .await;
+                                    break;
+                                }
+                                _ => {
+                                    if start.elapsed() > timeout {
+                                        vpn_manager
+                                            .set_status(&profile_name, VpnStatus::TimedOut)
+                                            .await;
+                                        let _ = event_tx
+                                            .send(note(
+                                                LogLevel::Error,
+                                                format!(
+                                                    "Timed out waiting for {} to connect",
+                                                    profile_name
+                                                ),
+                                            ))
+                                            .await;
+                                        break;
+                                    }
+                                    sleep(Duration::from_millis(200)).await;
+                                }
+                            }
+                        }
+
+                        if connected {
+                            // Verify stabilization
+                            let _ = event_tx
+                                .send(AppEvent::SetStatusMessage(format!(
+                                    "Verifying connection to {}...",
+                                    profile_name
+                                )))
+                                .await;
+
+                            // Check the status for a few seconds to ensure it stays connected
+                            let mut stable = true;
+                            for _ in 0..15 {
+                                sleep(Duration::from_millis(200)).await;
+                                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+
+                                // Ensure our target VPN is still connected
+                                if !matches!(
+                                    vpn_manager.get_status(&profile_name).await,
+                                    VpnStatus::Connected
+                                ) {
+                                    stable = false;
+                                    break;
+                                }
+
+                                let closed = vpn_manager.enforce_exclusive(&profile_name).await;
+                                if !closed.is_empty() {
+                                    let _ = event_tx
+                                        .send(note(
+                                            LogLevel::Warning,
+                                            format!(
+                                                "Another active VPN detected during stabilization; closed {}",
+                                                closed.join(", ")
+                                            ),
+                                        ))
+                                        .await;
+                                }
+                            }
+
+                            if stable {
+                                let _ = event_tx
+                                    .send(AppEvent::SetStatusMessage(format!(
+                                        "Connected to {}",
+                                        profile_name
+                                    )))
+                                    .await;
+                                let _ = event_tx
+                                    .send(note(
+                                        LogLevel::Success,
+                                        format!("Successfully connected to {}", profile_name),
+                                    ))
+                                    .await;
+                                break;
+                            } else {
+                                let _ = event_tx
+                                    .send(note(
+                                        LogLevel::Warning,
+                                        format!(
+                                            "Connection to {} dropped during stabilization",
+                                            profile_name
+                                        ),
+                                    ))
+                                    .await;
+                                // Fall through to retry logic
+                            }
+                        }
+
+                        if attempt >= max_retries {
+                            let _ = event_tx
+                                .send(AppEvent::SetStatusMessage(format!(
+                                    "Failed to connect to {} after {} attempts",
+                                    profile_name,
+                                    max_retries + 1
+                                )))
+                                .await;
+                            let _ = event_tx
+                                .send(note(
+                                    LogLevel::Error,
+                                    format!(
+                                        "Failed to connect to {} after {} attempts",
+                                        profile_name,
+                                        max_retries + 1
+                                    ),
+                                ))
+                                .await;
+                            break;
+                        }
+
+                        attempt += 1;
+                        let _ = vpn_manager
+                            .set_status(
+                                &profile_name,
+                                VpnStatus::Retrying(attempt, max_retries + 1),
+                            )
+                            .await;
+                        let _ = event_tx
+                            .send(note(
+                                LogLevel::Info,
+                                format!("Retrying connection to {}...", profile_name),
+                            ))
+                            .await;
+                        sleep(Duration::from_millis(500)).await;
+                    }
+                }
+            }
+            let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+        });
+        self.operation = Some(PendingOperation {
+            label,
+            started: std::time::Instant::now(),
+            handle,
+        });
+
+        Ok(())
+    }
+
+    /// Write a bug-report bundle, including the in-app logs, next to the config
+    fn spawn_report_bundle(&mut self) -> Result<()> {
+        let Some(event_tx) = self.event_tx.clone() else {
+            return Ok(());
+        };
+        let path = Config::config_path()?.with_file_name(crate::report::default_bundle_name());
+        let logs: Vec<String> = self.logs.iter().map(LogEntry::to_string).collect();
+        self.set_status_message("Writing report bundle...".to_string());
+        tokio::spawn(async move {
+            let (level, msg) = match crate::report::write_bundle(&path, &logs).await {
+                Ok(()) => (
+                    LogLevel::Success,
+                    format!("Report written to {}", path.display()),
+                ),
+                Err(e) => (LogLevel::Error, format!("Report failed: {}", e)),
+            };
+            let _ = event_tx
+                .send(AppEvent::Notification(level, msg.clone()))
+                .await;
+            let _ = event_tx.send(AppEvent::SetStatusMessage(msg)).await;
+        });
+        Ok(())
+    }
+
+    /// Disconnect every other VPN, keeping the selected profile up (and
+    /// connecting it if it isn't)
+    fn solo_selected(&mut self) {
+        let indices = self.get_filtered_profiles_indices();
+        let Some(&actual_index) = indices.get(self.selected_profile) else {
+            return;
+        };
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+        let profile = self.config.profiles[actual_index].clone();
+
+        for conn in &self.connections {
+            if conn.status == VpnStatus::Connected && conn.profile_name != profile.name {
+                self.expected_disconnects.insert(conn.profile_name.clone());
+            }
+        }
+
+        let op_id = crate::vpn::operation_id();
+        self.log(
+            LogLevel::Info,
+            format!("[{}] Keeping only {}", op_id, profile.name),
+        );
+        let label = format!("Keeping only {}", profile.name);
+
+        let vpn_manager = self.vpn_manager.clone();
+        let handle = tokio::spawn(async move {
+            let note = |level: LogLevel, msg: String| {
+                AppEvent::Notification(level, format!("[{}] {}", op_id, msg))
+            };
+
+            let closed = match vpn_manager.disconnect_others(&profile.name).await {
+                Ok(closed) => closed,
+                Err(e) => {
+                    let _ = event_tx
+                        .send(note(LogLevel::Error, format!("Solo failed: {}", e)))
+                        .await;
+                    let _ = event_tx
+                        .send(AppEvent::SetStatusMessage(format!("Solo failed: {}", e)))
+                        .await;
+                    let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                    return;
+                }
+            };
+            for name in &closed {
+                let _ = event_tx
+                    .send(note(LogLevel::Info, format!("Disconnected {}", name)))
+                    .await;
+            }
+            let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+
+            let mut summary = format!("Closed {} other VPN(s)", closed.len());
+            if vpn_manager.get_status(&profile.name).await != VpnStatus::Connected {
+                let _ = event_tx
+                    .send(note(
+                        LogLevel::Info,
+                        format!("Connecting to {}...", profile.name),
+                    ))
+                    .await;
+                match vpn_manager.connect(&profile).await {
+                    Ok(()) => summary.push_str(&format!(", connected {}", profile.name)),
+                    Err(e) => {
+                        let _ = event_tx
+                            .send(note(
+                                LogLevel::Error,
+                                format!("Connect error for {}: {}", profile.name, e),
+                            ))
+                            .await;
+                        summary.push_str(&format!(", {} failed to connect", profile.name));
+                    }
+                }
+            }
+            let _ = event_tx.send(note(LogLevel::Info, summary.clone())).await;
+            let _ = event_tx.send(AppEvent::SetStatusMessage(summary)).await;
+            let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+        });
+        self.operation = Some(PendingOperation {
+            label,
+            started: std::time::Instant::now(),
+            handle,
+        });
+    }
+
+    /// Connect (or disconnect) every enabled profile of the tag-filtered
+    /// view, one after another. Stops at the first failure unless
+    /// `batch_keep_going` is set; Esc cancels the rest like any operation.
+    fn batch_filtered(&mut self, connect: bool) {
+        if self.category_filter.is_empty() {
+            self.set_status_message("Filter by tag (f) to pick the profiles first".to_string());
+            return;
+        }
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+
+        let wanted = if connect {
+            VpnStatus::Connected
+        } else {
+            VpnStatus::Disconnected
+        };
+        let (settled, mut profiles): (Vec<VpnProfile>, Vec<VpnProfile>) = self
+            .get_filtered_profiles_indices()
+            .into_iter()
+            .map(|i| self.config.profiles[i].clone())
+            .filter(|p| p.enabled)
+            .partition(|p| {
+                let status = self
+                    .connections
+                    .iter()
+                    .find(|c| c.profile_name == p.name)
+                    .map(|c| c.status.clone())
+                    .unwrap_or(VpnStatus::Disconnected);
+                status == wanted
+            });
+        if profiles.is_empty() {
+            self.set_status_message(format!(
+                "Every listed profile is already {}",
+                if connect { "connected" } else { "disconnected" }
+            ));
+            return;
+        }
+        // With one tunnel at a time each connect would drop the one before
+        // it, so only the first listed profile is brought up
+        if connect && !self.vpn_manager.allows_multiple_connections() {
+            if let Some(up) = settled.first() {
+                self.set_status_message(format!(
+                    "{} is already connected; set allow_multiple_connections = true to keep several tunnels up",
+                    up.name
+                ));
+                return;
+            }
+            if profiles.len() > 1 {
+                self.log(
+                    LogLevel::Warning,
+                    format!(
+                        "Only one tunnel can be up at a time: connecting {} and skipping {} other listed profile(s); set allow_multiple_connections = true to connect them all",
+                        profiles[0].name,
+                        profiles.len() - 1
+                    ),
+                );
+                profiles.truncate(1);
+            }
+        }
+        if !connect {
+            self.expected_disconnects
+                .extend(profiles.iter().map(|p| p.name.clone()));
+        }
+
+        let mut tags: Vec<&str> = self.category_filter.iter().map(String::as_str).collect();
+        tags.sort();
+        let verb = if connect {
+            "Connecting"
+        } else {
+            "Disconnecting"
+        };
+        let label = format!(
+            "{} {} profile(s) tagged {}",
+            verb,
+            profiles.len(),
+            tags.join(", ")
+        );
+        let op_id = crate::vpn::operation_id();
+        self.log(LogLevel::Info, format!("[{}] {}", op_id, label));
+
+        let keep_going = self.config.settings.batch_keep_going;
+        let vpn_manager = self.vpn_manager.clone();
+        let handle = tokio::spawn(async move {
+            let note = |level: LogLevel, msg: String| {
+                AppEvent::Notification(level, format!("[{}] {}", op_id, msg))
+            };
+
+            let total = profiles.len();
+            let mut done = 0;
+            let mut failed = Vec::new();
+            for (i, profile) in profiles.iter().enumerate() {
+                let step = format!("[{}/{}] {} {}...", i + 1, total, verb, profile.name);
+                let _ = event_tx.send(note(LogLevel::Info, step.clone())).await;
+                let _ = event_tx.send(AppEvent::SetStatusMessage(step)).await;
+                let result = if connect {
+                    vpn_manager.connect(profile).await
+                } else {
+                    vpn_manager.disconnect(&profile.name).await
+                };
+                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                match result {
+                    Ok(()) => done += 1,
+                    Err(e) => {
+                        let _ = event_tx
+                            .send(note(LogLevel::Error, format!("{}: {}", profile.name, e)))
+                            .await;
+                        failed.push(profile.name.clone());
+                        if !keep_going {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            let action = if connect { "connected" } else { "disconnected" };
+            let (level, summary) = if failed.is_empty() {
+                (
+                    LogLevel::Success,
+                    format!("{} of {} {}", done, total, action),
+                )
+            } else {
+                (
+                    LogLevel::Error,
+                    format!(
+                        "{} of {} {}; failed: {}{}",
+                        done,
+                        total,
+                        action,
+                        failed.join(", "),
+                        if keep_going { "" } else { " (stopped)" }
+                    ),
+                )
+            };
+            let _ = event_tx.send(note(level, summary.clone())).await;
+            let _ = event_tx.send(AppEvent::SetStatusMessage(summary)).await;
+            let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+        });
+        self.operation = Some(PendingOperation {
+            label,
+            started: std::time::Instant::now(),
+            handle,
+        });
+    }
+
+    /// Log each auto-imported profile and summarize them in the status bar
+    pub fn announce_auto_import(&mut self, added: &[String]) {
+        for name in added {
+            self.log(LogLevel::Info, format!("Auto-imported profile {}", name));
+        }
+        const SHOWN: usize = 5;
+        let mut summary = format!(
+            "Auto-imported {} profile(s): {}",
+            added.len(),
+            added
+                .iter()
+                .take(SHOWN)
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if added.len() > SHOWN {
+            summary.push_str(&format!(" and {} more", added.len() - SHOWN));
+        }
+        self.set_status_message(summary);
+    }
+
+    /// Switch the tracing filter to the next level for this session only
+    fn cycle_log_level(&mut self) {
+        let Some(set_log_level) = &self.set_log_level else {
+            return;
+        };
+        let next = LOG_LEVELS
+            .iter()
+            .position(|l| l.eq_ignore_ascii_case(&self.log_level))
+            .map_or(0, |i| (i + 1) % LOG_LEVELS.len());
+        match set_log_level(LOG_LEVELS[next]) {
+            Ok(()) => {
+                self.log_level = LOG_LEVELS[next].to_string();
+                self.log(
+                    LogLevel::Info,
+                    format!("Log level set to {}", self.log_level),
+                );
+                self.set_status_message(format!("Log level: {}", self.log_level));
+            }
+            Err(e) => self.set_status_message(format!("Could not change log level: {}", e)),
+        }
+    }
+
+    /// Run a sequence's steps in the background, reporting each in the logs.
+    /// Stops at the first failing step unless the sequence sets `keep_going`.
+    fn run_sequence(&mut self, sequence: Sequence) {
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+        // Tunnels the sequence takes down shouldn't raise drop alerts
+        for conn in &self.connections {
+            if conn.status == VpnStatus::Connected {
+                self.expected_disconnects.insert(conn.profile_name.clone());
+            }
+        }
+
+        let op_id = crate::vpn::operation_id();
+        self.log(
+            LogLevel::Info,
+            format!("[{}] Running sequence {}", op_id, sequence.name),
+        );
+        let label = format!("Running {}", sequence.name);
+
+        let vpn_manager = self.vpn_manager.clone();
+        let profiles = self.config.profiles.clone();
+        let handle = tokio::spawn(async move {
+            let note = |level: LogLevel, msg: String| {
+                AppEvent::Notification(level, format!("[{}] {}", op_id, msg))
+            };
+            let total = sequence.steps.len();
+            let mut failures = 0;
+            for (i, step) in sequence.steps.iter().enumerate() {
+                let progress = format!("[{}/{}] {}", i + 1, total, step);
+                let _ = event_tx.send(note(LogLevel::Info, progress.clone())).await;
+                let _ = event_tx.send(AppEvent::SetStatusMessage(progress)).await;
+                let result = vpn_manager.run_step(&profiles, step).await;
+                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                if let Err(e) = result {
+                    failures += 1;
+                    let _ = event_tx
+                        .send(note(LogLevel::Error, format!("{} failed: {}", step, e)))
+                        .await;
+                    if !sequence.keep_going {
+                        break;
+                    }
+                }
+            }
+            let summary = if failures == 0 {
+                format!("Sequence {} finished", sequence.name)
+            } else {
+                format!("Sequence {}: {} step(s) failed", sequence.name, failures)
+            };
+            let _ = event_tx.send(note(LogLevel::Info, summary.clone())).await;
+            let _ = event_tx.send(AppEvent::SetStatusMessage(summary)).await;
+        });
+        self.operation = Some(PendingOperation {
+            label,
+            started: std::time::Instant::now(),
+            handle,
+        });
+    }
+
+    /// While a connect/disconnect runs, keys other than navigation and Esc
+    /// are swallowed so queued input can't trigger actions once it finishes,
+    /// and a second toggle can't start a duplicate task
+    async fn handle_operation_key(&mut self, key: KeyEvent) -> Result<()> {
+        if key.code != KeyCode::Esc {
+            return Ok(());
+        }
+        if let Some(op) = self.operation.take() {
+            op.handle.abort();
+            self.log(LogLevel::Warning, format!("{} cancelled", op.label));
+            self.set_status_message(format!("{} cancelled", op.label));
+            self.refresh_status().await?;
+        }
+        Ok(())
+    }
+
+    async fn save_new_profile(&mut self) -> Result<()> {
+        let name = self.add_profile_data[0].value().to_string();
+        let is_edit = self.screen == Screen::EditProfile;
+        let indices = self.get_filtered_profiles_indices();
+        let edit_index = indices.get(self.selected_profile).copied();
+
+        // Start from the existing profile when editing so fields not shown
+        // in the form (color, data cap, ...) are preserved
+        let base = match (is_edit, edit_index) {
+            (true, Some(idx)) => self.config.profiles[idx].clone(),
+            _ => VpnProfile {
+                source: Some(crate::config::SOURCE_MANUAL.to_string()),
+                ..Default::default()
+            },
+        };
+
+        let mut profile = VpnProfile {
+            name,
+            gateway_address: self.add_profile_data[1].value().to_string(),
+            cert_path: if self.add_profile_data[3].is_empty() {
+                None
+            } else {
+                Some(self.add_profile_data[3].value().to_string())
+            },
+            username: if self.add_profile_data[4].is_empty() {
+                None
+            } else {
+                Some(self.add_profile_data[4].value().to_string())
+            },
+            aliases: if self.add_profile_data[5].is_empty() {
+                None
+            } else {
+                Some(self.add_profile_data[5].value().to_string())
+            },
+            protocol: self.form_protocol.clone(),
+            port: None,
+            ..base
+        };
+        profile.set_tags(crate::config::parse_tags(self.add_profile_data[2].value()));
+        profile.normalize_gateway();
+
+        // Keep the form open, pointing at the first bad field, until fixed
+        if let Err(errors) = profile.validate() {
+            self.input_field = form_field_index(errors[0].field);
+            if self.screen == Screen::EditProfile && self.input_field == 0 {
+                self.input_field = 1;
+            }
+            self.set_status_message(match errors.len() {
+                1 => errors[0].message.clone(),
+                n => format!("{} fields need fixing", n),
+            });
+            self.form_errors = errors;
+            return Ok(());
+        }
+        self.form_errors.clear();
+
+        if is_edit {
+            if let Some(actual_index) = edit_index {
+                let old_name = self.config.profiles[actual_index].name.clone();
+                if old_name != profile.name {
+                    self.vpn_manager
+                        .rename_connection(&old_name, &profile.name)
+                        .await;
+                }
+                self.config.profiles[actual_index] = profile;
+            }
+        } else {
+            self.config.profiles.push(profile);
+        }
+
+        self.save_config();
+        self.screen = Screen::Main;
+        self.input_mode = InputMode::Normal;
+        self.set_status_message(
+            if is_edit {
+                "Profile updated"
+            } else {
+                "Profile added"
+            }
+            .to_string(),
+        );
+        Ok(())
+    }
+
+    /// Append a copy of the selected profile under an unused "(copy)" name
+    /// and open it in the edit form. Aliases aren't copied since they must
+    /// stay unique; the copy starts without history.
+    fn clone_selected_profile(&mut self) {
+        let indices = self.get_filtered_profiles_indices();
+        let Some(&actual_index) = indices.get(self.selected_profile) else {
+            return;
+        };
+        let original = self.config.profiles[actual_index].clone();
+        let copy_name = self.config.copy_name(&original.name);
+        self.log(
+            LogLevel::Info,
+            format!("Cloned {} as {}", original.name, copy_name),
+        );
+        self.config.profiles.push(VpnProfile {
+            name: copy_name.clone(),
+            aliases: None,
+            last_connected: None,
+            source: Some(crate::config::SOURCE_MANUAL.to_string()),
+            ..original
+        });
+        self.save_config();
+
+        // Select the copy, clearing a search it doesn't match
+        let copy_index = self.config.profiles.len() - 1;
+        if !self.get_filtered_profiles_indices().contains(&copy_index) {
+            self.search_query.clear();
+        }
+        let indices = self.get_filtered_profiles_indices();
+        let Some(position) = indices.iter().position(|&i| i == copy_index) else {
+            self.set_status_message(format!("Cloned as {} (hidden by filters)", copy_name));
+            return;
+        };
+        self.selected_profile = position;
+        self.table_state.select(Some(self.selected_profile));
+
+        self.screen = Screen::EditProfile;
+        self.input_mode = InputMode::Editing;
+        self.load_profile_to_edit();
+        self.input_field = 1; // Start from Gateway Address, as when editing
+        self.set_status_message(format!("Cloned as {}", copy_name));
+    }
+
+    async fn delete_selected_profile(&mut self) -> Result<()> {
+        let indices = self.get_filtered_profiles_indices();
+        if !indices.is_empty() && self.selected_profile < indices.len() {
+            let actual_index = indices[self.selected_profile];
+            let profile_name = self.config.profiles[actual_index].name.clone();
+            self.config.profiles.remove(actual_index);
+            self.save_config();
+            self.vpn_manager.remove_connection(&profile_name).await;
+            if let Err(e) = self.vpn_manager.delete_password(&profile_name).await {
+                self.log(LogLevel::Warning, e.to_string());
+            }
+            if self.watched_profile() == Some(profile_name.as_str()) {
+                self.unwatch();
+            }
+            self.refresh_from_manager().await?;
+
+            // Reset selection if needed
+            let new_len = self.get_filtered_profiles_indices().len();
+            if self.selected_profile >= new_len && self.selected_profile > 0 {
+                self.selected_profile = new_len.saturating_sub(1);
+            }
+            if new_len == 0 {
+                self.selected_profile = 0;
+            }
+            self.table_state.select(Some(self.selected_profile));
+
+            self.set_status_message(format!("Deleted profile: {}", profile_name));
+        }
+        Ok(())
+    }
+
+    fn load_profile_to_edit(&mut self) {
+        let indices = self.get_filtered_profiles_indices();
+        if let Some(&actual_index) = indices.get(self.selected_profile)
+            && let Some(profile) = self.config.profiles.get(actual_index)
+        {
+            self.add_profile_data[0].set(profile.name.clone());
+            self.add_profile_data[1].set(profile.gateway_display());
+            self.add_profile_data[2].set(profile.tags.join(", "));
+            self.add_profile_data[3].set(profile.cert_path.clone().unwrap_or_default());
+            self.add_profile_data[4].set(profile.username.clone().unwrap_or_default());
+            self.add_profile_data[5].set(profile.aliases.clone().unwrap_or_default());
+            self.form_protocol = profile.protocol.clone();
+            self.form_errors.clear();
+            self.input_field = 0;
+        }
+    }
+
+    /// Validation error shown on form field `index`, if any
+    pub fn form_error(&self, index: usize) -> Option<&ValidationError> {
+        self.form_errors
+            .iter()
+            .find(|e| form_field_index(e.field) == index)
+    }
+
+    pub fn get_filtered_profiles_indices(&self) -> Vec<usize> {
+        let connections = self
+            .connections
+            .iter()
+            .map(|c| (c.profile_name.clone(), c.clone()))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        let query = self.search_query.value().to_lowercase();
+        let mut indices: Vec<usize> = self
+            .config
+            .profiles
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| p.enabled || self.show_disabled)
+            .filter(|(_, p)| p.favorite || !self.favorites_only)
+            .filter(|(_, p)| {
+                // "source:<text>" searches where profiles were imported from
+                if let Some(source) = query.strip_prefix("source:") {
+                    return p
+                        .source
+                        .as_deref()
+                        .is_some_and(|s| s.to_lowercase().contains(source.trim()));
+                }
+                query.is_empty() || crate::config::search_score(p, &query).is_some()
+            })
+            .filter(|(_, p)| {
+                self.category_filter.is_empty()
+                    || p.tags.iter().any(|t| self.category_filter.contains(t))
+            })
+            .filter(|(_, p)| {
+                let status = connections
+                    .get(&p.name)
+                    .map(|c| c.status.clone())
+                    .unwrap_or(VpnStatus::Disconnected);
+                self.status_filter.matches(&status)
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        // Apply sorting
+
+        // While searching, the best matches come first instead of the column sort
+        let scores: std::collections::HashMap<usize, u32> = match self.search_match_query() {
+            Some(query) => indices
+                .iter()
+                .filter_map(|&i| {
+                    Some((
+                        i,
+                        crate::config::search_score(&self.config.profiles[i], query)?,
+                    ))
+                })
+                .collect(),
+            None => Default::default(),
+        };
+        let score = |i: usize| scores.get(&i).copied();
+        let watched = self.watched_profile();
+        indices.sort_by(|&a, &b| {
+            let w_a = watched == Some(self.config.profiles[a].name.as_str());
+            let w_b = watched == Some(self.config.profiles[b].name.as_str());
+            let (p_a, p_b) = (&self.config.profiles[a], &self.config.profiles[b]);
+            // The watched profile is pinned first regardless of sort, then
+            // favorites come before the rest
+            w_b.cmp(&w_a)
+                .then_with(|| p_b.favorite.cmp(&p_a.favorite))
+                .then_with(|| score(b).cmp(&score(a)))
+                .then_with(|| self.compare_profiles(a, b, &connections))
+        });
+
+        indices
+    }
+
+    /// The search query when it is a plain text search, i.e. one that ranks
+    /// and highlights matches (not empty and not a `source:` filter)
+    pub fn search_match_query(&self) -> Option<&str> {
+        let query = self.search_query.value().trim();
+        (!query.is_empty() && !query.to_lowercase().starts_with("source:")).then_some(query)
+    }
+
+    fn compare_profiles(
+        &self,
+        a: usize,
+        b: usize,
+        connections: &std::collections::HashMap<String, VpnConnection>,
+    ) -> std::cmp::Ordering {
+        let p_a = &self.config.profiles[a];
+        let p_b = &self.config.profiles[b];
+
+        let display = self.config.settings.display_primary;
+        let res = match self.sort_column {
+            SortColumn::Name => display
+                .primary(p_a)
+                .to_lowercase()
+                .cmp(&display.primary(p_b).to_lowercase()),
+            SortColumn::Category => p_a
+                .tags_display()
+                .to_lowercase()
+                .cmp(&p_b.tags_display().to_lowercase()),
+            SortColumn::Status => {
+                let s_a = connections
+                    .get(&p_a.name)
+                    .map(|c| c.status.as_str())
+                    .unwrap_or_else(|| "Disconnected".to_string());
+                let s_b = connections
+                    .get(&p_b.name)
+                    .map(|c| c.status.as_str())
+                    .unwrap_or_else(|| "Disconnected".to_string());
+                s_a.cmp(&s_b)
+            }
+            SortColumn::Duration => {
+                let since = |p: &VpnProfile| connections.get(&p.name)?.connected_since;
+                return compare_present_first(since(p_a), since(p_b), self.sort_direction);
+            }
+            SortColumn::Ip => {
+                let ip = |p: &VpnProfile| {
+                    connections
+                        .get(&p.name)?
+                        .ip_address
+                        .as_deref()?
+                        .parse::<std::net::IpAddr>()
+                        .ok()
+                };
+                return compare_present_first(ip(p_a), ip(p_b), self.sort_direction);
+            }
+            // Indices follow the config order, which has no reverse
+            SortColumn::Manual => return a.cmp(&b),
+        };
+
+        if self.sort_direction == SortDirection::Asc {
+            res
+        } else {
+            res.reverse()
+        }
+    }
+
+    pub fn watched_profile(&self) -> Option<&str> {
+        self.watched.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// Poll one profile every second until unwatched
+    fn watch(&mut self, name: String) {
+        self.unwatch();
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+        let vpn_manager = self.vpn_manager.clone();
+        let profile_name = name.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                if vpn_manager.refresh_one(&profile_name).await.is_ok()
+                    && event_tx.send(AppEvent::VpnStatusUpdated).await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+        self.watched = Some((name, handle));
+    }
+
+    fn unwatch(&mut self) {
+        if let Some((_, handle)) = self.watched.take() {
+            handle.abort();
+        }
+    }
+
+    fn cycle_sort(&mut self) {
+        if self.sort_direction == SortDirection::Asc && self.sort_column != SortColumn::Manual {
+            self.sort_direction = SortDirection::Desc;
+        } else {
+            self.sort_column = self.sort_column.next();
+            self.sort_direction = SortDirection::Asc;
+        }
+        self.save_ui_state();
+        self.set_status_message(format!(
+            "Sorting by {:?} ({:?})",
+            self.sort_column, self.sort_direction
+        ));
+    }
+
+    async fn refresh_from_manager(&mut self) -> Result<()> {
+        let connections = self.vpn_manager.get_all_connections().await;
+        let previous = std::mem::replace(&mut self.connections, connections);
+        self.detect_drops(&previous);
+        self.detect_stalled_connects(&previous);
+        self.record_last_connected(&previous);
+        self.spawn_dns_leak_checks(&previous);
+        self.check_data_caps();
+        self.record_throughput();
+        self.report_missing_tool();
+        self.reapply_kill_switch(&previous).await;
+        Ok(())
+    }
+
+    /// Sample the traffic counters of connected profiles; others lose their
+    /// history so a new session starts from scratch
+    fn record_throughput(&mut self) {
+        let now = std::time::Instant::now();
+        let connected: Vec<&VpnConnection> = self
+            .connections
+            .iter()
+            .filter(|c| c.status == VpnStatus::Connected)
+            .collect();
+        self.throughput
+            .retain(|name, _| connected.iter().any(|c| &c.profile_name == name));
+        for conn in connected {
+            self.throughput
+                .entry(conn.profile_name.clone())
+                .or_default()
+                .record(now, conn.bytes_sent, conn.bytes_received);
+        }
+    }
+
+    /// Statuses are Unknown while the platform VPN tool is missing; say
+    /// which tool and how to get it instead of leaving the list blank
+    fn report_missing_tool(&mut self) {
+        let notice = self.connections.iter().find_map(|c| match &c.status {
+            VpnStatus::Unknown(reason) => Some(reason.clone()),
+            _ => None,
+        });
+        if notice != self.missing_tool_notice
+            && let Some(message) = &notice
+        {
+            self.log(LogLevel::Warning, message.clone());
+            self.set_status_message(message.clone());
+        }
+        self.missing_tool_notice = notice;
+    }
+
+    /// Persist the time of each profile that just reached Connected. Failed
+    /// attempts never get here, so they keep the previous timestamp.
+    fn record_last_connected(&mut self, previous: &[VpnConnection]) {
+        let newly_connected: Vec<(String, chrono::DateTime<chrono::Local>)> = self
+            .connections
+            .iter()
+            .filter(|c| c.status == VpnStatus::Connected)
+            .filter(|c| {
+                !previous.iter().any(|old| {
+                    old.profile_name == c.profile_name && old.status == VpnStatus::Connected
+                })
+            })
+            .map(|c| {
+                let at = c.connected_since.unwrap_or_else(chrono::Local::now);
+                (c.profile_name.clone(), at)
+            })
+            .collect();
+        let mut changed = false;
+        for (name, at) in newly_connected {
+            changed |= self.config.mark_connected(&name, at);
+        }
+        if changed {
+            self.save_config();
+        }
+    }
+
+    /// With `check_dns_leak`, check each profile that just connected for DNS
+    /// or traffic bypassing its tunnel and warn about what does
+    fn spawn_dns_leak_checks(&self, previous: &[VpnConnection]) {
+        if !self.config.settings.check_dns_leak {
+            return;
+        }
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+        let newly_connected: Vec<String> = self
+            .connections
+            .iter()
+            .filter(|c| c.status == VpnStatus::Connected)
+            .filter(|c| {
+                !previous.iter().any(|old| {
+                    old.profile_name == c.profile_name && old.status == VpnStatus::Connected
+                })
+            })
+            .map(|c| c.profile_name.clone())
+            .collect();
+        if newly_connected.is_empty() {
+            return;
+        }
+
+        let vpn_manager = self.vpn_manager.clone();
+        tokio::spawn(async move {
+            for name in newly_connected {
+                for warning in vpn_manager.check_dns_leak(&name).await {
+                    let message = format!("{}: {}", name, warning);
+                    let _ = event_tx
+                        .send(AppEvent::Notification(LogLevel::Warning, message.clone()))
+                        .await;
+                    let _ = event_tx.send(AppEvent::SetStatusWarning(message)).await;
+                }
+            }
+        });
+    }
+
+    /// Warn once per session when a connected profile passes its data cap,
+    /// disconnecting it if the settings ask for that
+    fn check_data_caps(&mut self) {
+        let mut over_cap = Vec::new();
+        for conn in &self.connections {
+            if conn.status != VpnStatus::Connected {
+                self.data_cap_alerted.remove(&conn.profile_name);
+                continue;
+            }
+            let Some(cap_mb) = self
+                .config
+                .profiles
+                .iter()
+                .find(|p| p.name == conn.profile_name)
+                .and_then(|p| p.data_cap_mb)
+            else {
+                continue;
+            };
+            let used = conn.bytes_sent.saturating_add(conn.bytes_received);
+            if used >= cap_mb.saturating_mul(1024 * 1024)
+                && !self.data_cap_alerted.contains(&conn.profile_name)
+            {
+                over_cap.push((conn.profile_name.clone(), used / (1024 * 1024), cap_mb));
+            }
+        }
+
+        for (name, used_mb, cap_mb) in over_cap {
+            self.data_cap_alerted.insert(name.clone());
+            let msg = format!(
+                "{} passed its data cap ({} MB of {} MB)",
+                name, used_mb, cap_mb
+            );
+            self.log(LogLevel::Warning, format!("ALERT: {}", msg));
+            self.alert_toast = Some((msg, chrono::Local::now()));
+
+            if self.config.settings.disconnect_on_data_cap {
+                self.log(
+                    LogLevel::Warning,
+                    format!("Disconnecting {} (data cap reached)", name),
+                );
+                self.expected_disconnects.insert(name.clone());
+                let vpn_manager = self.vpn_manager.clone();
+                let event_tx = self.event_tx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = vpn_manager.disconnect(&name).await
+                        && let Some(tx) = &event_tx
+                    {
+                        let _ = tx
+                            .send(AppEvent::Notification(
+                                LogLevel::Error,
+                                format!("Error disconnecting {}: {}", name, e),
+                            ))
+                            .await;
+                    }
+                    if let Some(tx) = event_tx {
+                        let _ = tx.send(AppEvent::VpnStatusUpdated).await;
+                    }
+                });
+            }
+        }
+    }
+
+    /// Alert on tunnels that went from Connected to Disconnected without
+    /// the user asking for it, and reconnect the ones remipn brought up when
+    /// auto-reconnect is on
+    fn detect_drops(&mut self, previous: &[VpnConnection]) {
+        let dropped: Vec<(String, bool)> = previous
+            .iter()
+            .filter(|old| old.status == VpnStatus::Connected)
+            .filter(|old| {
+                self.connections.iter().any(|c| {
+                    c.profile_name == old.profile_name && c.status == VpnStatus::Disconnected
+                })
+            })
+            .map(|old| (old.profile_name.clone(), old.owned_by_remipn))
+            .collect();
 
-                        if attempt >= max_retries {
-                            let _ = event_tx
-                                .send(AppEvent::SetStatusMessage(format!(
-                                    "Failed to connect to {} after {} attempts",
-                                    profile_name,
-                                    max_retries + 1
-                                )))
-                                .await;
-                            let _ = event_tx
-                                .send(AppEvent::Notification(format!(
-                                    "Failed to connect to {} after {} attempts",
-                                    profile_name,
-                                    max_retries + 1
-                                )))
-                                .await;
-                            break;
-                        }
+        for (name, owned) in dropped {
+            if self.expected_disconnects.remove(&name) {
+                continue;
+            }
+            self.vpn_manager.record_history(
+                &name,
+                ConnectAction::Drop,
+                Outcome::Error("connection dropped".to_string()),
+            );
+            if self.auto_reconnect && owned && !self.safe_mode {
+                self.spawn_reconnect(&name);
+            }
+            if !self.config.settings.alert_on_drop {
+                continue;
+            }
+            use std::io::Write;
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(b"\x07");
+            let _ = stdout.flush();
+            self.alert_flash = true;
+            self.log(
+                LogLevel::Error,
+                format!("ALERT: connection to {} dropped", name),
+            );
+            self.alert_toast = Some((
+                format!("Connection to {} dropped", name),
+                chrono::Local::now(),
+            ));
+        }
+    }
 
-                        attempt += 1;
-                        let _ = vpn_manager
-                            .set_status(
-                                &profile_name,
-                                VpnStatus::Retrying(attempt, max_retries + 1),
-                            )
+    /// Reconnect a dropped profile after `reconnect_delay_seconds`, showing
+    /// it as Retrying while waiting. Gives up after a few attempts, or as soon
+    /// as the profile is up again or the user has taken over.
+    fn spawn_reconnect(&mut self, name: &str) {
+        const MAX_ATTEMPTS: u32 = 3;
+
+        let Some(profile) = self
+            .config
+            .profiles
+            .iter()
+            .find(|p| p.name == name && p.enabled)
+            .cloned()
+        else {
+            return;
+        };
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+        let delay = std::time::Duration::from_secs(self.config.settings.reconnect_delay_seconds);
+        self.log(
+            LogLevel::Warning,
+            format!(
+                "{} dropped; reconnecting in {}s",
+                profile.name,
+                delay.as_secs()
+            ),
+        );
+
+        let vpn_manager = self.vpn_manager.clone();
+        tokio::spawn(async move {
+            for attempt in 1..=MAX_ATTEMPTS {
+                let waiting = VpnStatus::Retrying(attempt, MAX_ATTEMPTS);
+                vpn_manager.set_status(&profile.name, waiting.clone()).await;
+                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                tokio::time::sleep(delay).await;
+
+                // The user (or the system) may have taken over meanwhile
+                if vpn_manager.get_status(&profile.name).await != waiting {
+                    return;
+                }
+                let _ = event_tx
+                    .send(AppEvent::Notification(
+                        LogLevel::Info,
+                        format!(
+                            "Reconnecting {} (attempt {}/{})",
+                            profile.name, attempt, MAX_ATTEMPTS
+                        ),
+                    ))
+                    .await;
+                let result = vpn_manager.connect(&profile).await;
+                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                match result {
+                    Ok(()) => {
+                        let _ = event_tx
+                            .send(AppEvent::Notification(
+                                LogLevel::Success,
+                                format!("Reconnected {}", profile.name),
+                            ))
                             .await;
+                        return;
+                    }
+                    Err(e) => {
                         let _ = event_tx
-                            .send(AppEvent::Notification(format!(
-                                "Retrying connection to {}...",
-                                profile_name
-                            )))
+                            .send(AppEvent::Notification(
+                                LogLevel::Error,
+                                format!("Reconnect of {} failed: {}", profile.name, e),
+                            ))
                             .await;
-                        sleep(Duration::from_millis(500)).await;
                     }
                 }
             }
-            let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
         });
+    }
 
-        Ok(())
+    /// Log attempts the status watchdog gave up on. Attempts started from the
+    /// TUI report their own timeout, so only orphaned ones are logged here.
+    fn detect_stalled_connects(&mut self, previous: &[VpnConnection]) {
+        if self.operation.is_some() {
+            return;
+        }
+        let stalled: Vec<String> = previous
+            .iter()
+            .filter(|old| old.status.is_in_progress())
+            .filter(|old| {
+                self.connections
+                    .iter()
+                    .any(|c| c.profile_name == old.profile_name && c.status == VpnStatus::TimedOut)
+            })
+            .map(|old| old.profile_name.clone())
+            .collect();
+        for name in stalled {
+            self.log(
+                LogLevel::Warning,
+                format!(
+                    "{} was stuck connecting for over {}s; marked as timed out",
+                    name, self.config.settings.connect_timeout_seconds
+                ),
+            );
+        }
     }
 
-    fn save_new_profile(&mut self) -> Result<()> {
-        let name = self.add_profile_data[0].clone();
-        if name.is_empty() {
-            self.set_status_message("Name cannot be empty".to_string());
-            return Ok(());
+    async fn refresh_status(&mut self) -> Result<()> {
+        // The periodic refresh is already querying; its results will do
+        if !self.refreshing.swap(true, Ordering::SeqCst) {
+            let result = self
+                .vpn_manager
+                .refresh_all_status(&self.config.profiles)
+                .await;
+            self.refreshing.store(false, Ordering::SeqCst);
+            result?;
         }
+        self.refresh_from_manager().await
+    }
 
-        let profile = VpnProfile {
-            name,
-            gateway_address: self.add_profile_data[1].clone(),
-            category: self.add_profile_data[2].clone(),
-            cert_path: if self.add_profile_data[3].is_empty() {
-                None
-            } else {
-                Some(self.add_profile_data[3].clone())
-            },
-            username: if self.add_profile_data[4].is_empty() {
-                None
-            } else {
-                Some(self.add_profile_data[4].clone())
-            },
-            aliases: if self.add_profile_data[5].is_empty() {
-                None
-            } else {
-                Some(self.add_profile_data[5].clone())
-            },
-            protocol: "IKEv2".to_string(),
-            auto_connect: false,
+    /// Refresh the status every `status_check_interval_seconds` on a
+    /// background task, so slow `nmcli`/`scutil` queries never stall input
+    /// or drawing. Each round checks the profiles as last saved; the interval
+    /// follows the settings screen. A round is skipped while another refresh
+    /// is still running.
+    pub fn spawn_status_refresh(&self) {
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
         };
+        tokio::spawn(status_refresh_loop(
+            self.vpn_manager.clone(),
+            self.refreshing.clone(),
+            self.refresh_interval.subscribe(),
+            self.profiles_tx.subscribe(),
+            event_tx,
+        ));
+    }
 
-        let is_edit = self.screen == Screen::EditProfile;
-        if is_edit {
-            let indices = self.get_filtered_profiles_indices();
-            if self.selected_profile < indices.len() {
-                let actual_index = indices[self.selected_profile];
-                self.config.profiles[actual_index] = profile;
-            }
-        } else {
-            self.config.profiles.push(profile);
+    pub async fn update(&mut self) -> Result<()> {
+        if self
+            .operation
+            .as_ref()
+            .is_some_and(|op| op.handle.is_finished())
+        {
+            self.operation = None;
         }
 
-        self.config.save()?;
-        self.screen = Screen::Main;
-        self.input_mode = InputMode::Normal;
-        self.set_status_message(
-            if is_edit {
-                "Profile updated"
-            } else {
-                "Profile added"
-            }
-            .to_string(),
-        );
+        let now = std::time::Instant::now();
+        let settings = &self.config.settings;
+        if !self.safe_mode
+            && settings.verify_connections
+            && now.duration_since(self.last_verify).as_secs() >= settings.verify_interval_seconds
+        {
+            self.last_verify = now;
+            self.spawn_connection_verification();
+        }
         Ok(())
     }
 
-    fn delete_selected_profile(&mut self) -> Result<()> {
-        let indices = self.get_filtered_profiles_indices();
-        if !indices.is_empty() && self.selected_profile < indices.len() {
-            let actual_index = indices[self.selected_profile];
-            let profile_name = self.config.profiles[actual_index].name.clone();
-            self.config.profiles.remove(actual_index);
-            self.config.save()?;
+    /// Verify connected profiles in the background, cycling newly degraded
+    /// ones when auto-reconnect is enabled. Tunnels remipn didn't bring up
+    /// are only reported, never cycled. Degraded profiles are checked again
+    /// so they return to connected once the problem clears.
+    fn spawn_connection_verification(&self) {
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+        let connected: Vec<(VpnProfile, bool, bool)> = self
+            .config
+            .profiles
+            .iter()
+            .filter_map(|p| {
+                let conn = self.connections.iter().find(|c| {
+                    c.profile_name == p.name
+                        && matches!(c.status, VpnStatus::Connected | VpnStatus::Degraded(_))
+                })?;
+                let degraded = matches!(conn.status, VpnStatus::Degraded(_));
+                // Disabled profiles are never cycled automatically
+                Some((p.clone(), conn.owned_by_remipn && p.enabled, degraded))
+            })
+            .collect();
+        if connected.is_empty() {
+            return;
+        }
 
-            // Reset selection if needed
-            let new_len = self.get_filtered_profiles_indices().len();
-            if self.selected_profile >= new_len && self.selected_profile > 0 {
-                self.selected_profile = new_len.saturating_sub(1);
+        let vpn_manager = self.vpn_manager.clone();
+        let auto_reconnect = self.auto_reconnect;
+        tokio::spawn(async move {
+            for (profile, owned, degraded) in connected {
+                let reason = vpn_manager.verify_connection(&profile).await;
+                if degraded {
+                    // Already reported (and cycled, if allowed) when it degraded
+                    if reason.is_none()
+                        && vpn_manager.get_status(&profile.name).await == VpnStatus::Connected
+                    {
+                        let _ = event_tx
+                            .send(AppEvent::Notification(
+                                LogLevel::Info,
+                                format!("{} recovered", profile.name),
+                            ))
+                            .await;
+                        let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                    }
+                    continue;
+                }
+                let Some(reason) = reason else {
+                    continue;
+                };
+                let _ = event_tx
+                    .send(AppEvent::Notification(
+                        LogLevel::Warning,
+                        format!("{} degraded: {}", profile.name, reason),
+                    ))
+                    .await;
+                let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+
+                if auto_reconnect && !owned {
+                    let _ = event_tx
+                        .send(AppEvent::Notification(
+                            LogLevel::Info,
+                            format!(
+                                "{} was not connected by remipn; leaving it to its owner",
+                                profile.name
+                            ),
+                        ))
+                        .await;
+                } else if auto_reconnect {
+                    let _ = event_tx
+                        .send(AppEvent::Notification(
+                            LogLevel::Info,
+                            format!("Cycling degraded connection {}...", profile.name),
+                        ))
+                        .await;
+                    let _ = vpn_manager.disconnect(&profile.name).await;
+                    if let Err(e) = vpn_manager.connect(&profile).await {
+                        let _ = event_tx
+                            .send(AppEvent::Notification(
+                                LogLevel::Error,
+                                format!("Reconnect of {} failed: {}", profile.name, e),
+                            ))
+                            .await;
+                    }
+                    let _ = event_tx.send(AppEvent::VpnStatusUpdated).await;
+                }
             }
-            if new_len == 0 {
-                self.selected_profile = 0;
+        });
+    }
+
+    /// Probe every listed profile's gateway in the background and report
+    /// the results in the logs
+    fn spawn_reachability_sweep(&mut self) {
+        let Some(event_tx) = self.event_tx.clone() else {
+            return;
+        };
+        let profiles: Vec<VpnProfile> = self
+            .get_filtered_profiles_indices()
+            .into_iter()
+            .map(|i| self.config.profiles[i].clone())
+            .collect();
+        self.set_status_message(format!("Probing {} gateway(s)...", profiles.len()));
+
+        let vpn_manager = self.vpn_manager.clone();
+        tokio::spawn(async move {
+            let results = vpn_manager.check_all_reachable(&profiles, 8).await;
+            let reachable = results.iter().filter(|(_, rtt)| rtt.is_some()).count();
+            for (name, rtt) in &results {
+                let (level, line) = match rtt {
+                    Some(ms) => (LogLevel::Info, format!("{}: reachable ({} ms)", name, ms)),
+                    None => (LogLevel::Warning, format!("{}: unreachable", name)),
+                };
+                let _ = event_tx.send(AppEvent::Notification(level, line)).await;
             }
-            self.table_state.select(Some(self.selected_profile));
+            let _ = event_tx
+                .send(AppEvent::SetStatusMessage(format!(
+                    "{}/{} gateways reachable (see logs)",
+                    reachable,
+                    results.len()
+                )))
+                .await;
+        });
+    }
 
-            self.set_status_message(format!("Deleted profile: {}", profile_name));
+    fn set_status_message(&mut self, msg: String) {
+        self.status_message = Some((msg, chrono::Local::now()));
+        self.status_is_warning = false;
+    }
+
+    pub fn log(&mut self, level: LogLevel, message: String) {
+        self.logs.push(LogEntry {
+            level,
+            message,
+            timestamp: chrono::Local::now(),
+        });
+        let max = self.config.settings.max_log_lines.max(1);
+        if self.logs.len() > max {
+            self.logs.drain(..self.logs.len() - max);
         }
-        Ok(())
     }
 
-    fn load_profile_to_edit(&mut self) {
-        let indices = self.get_filtered_profiles_indices();
-        if let Some(&actual_index) = indices.get(self.selected_profile)
-            && let Some(profile) = self.config.profiles.get(actual_index)
-        {
-            self.add_profile_data[0] = profile.name.clone();
-            self.add_profile_data[1] = profile.gateway_address.clone();
-            self.add_profile_data[2] = profile.category.clone();
-            self.add_profile_data[3] = profile.cert_path.clone().unwrap_or_default();
-            self.add_profile_data[4] = profile.username.clone().unwrap_or_default();
-            self.add_profile_data[5] = profile.aliases.clone().unwrap_or_default();
-            self.input_field = 0;
+    pub fn get_connections(&self) -> Vec<VpnConnection> {
+        self.connections.clone()
+    }
+}
+
+/// Order two optional sort keys in `direction`, always putting a missing key
+/// (a profile that isn't connected) after present ones
+fn compare_present_first<T: Ord>(
+    a: Option<T>,
+    b: Option<T>,
+    direction: SortDirection,
+) -> std::cmp::Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if direction == SortDirection::Asc => a.cmp(&b),
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
+
+/// Index in the add/edit form of the input for `field`
+fn form_field_index(field: ProfileField) -> usize {
+    match field {
+        ProfileField::Name => 0,
+        ProfileField::Gateway => 1,
+        ProfileField::CertPath => 3,
+        ProfileField::Protocol => PROTOCOL_FIELD,
+    }
+}
+
+/// Body of [`App::spawn_status_refresh`]: refresh on every interval tick
+/// until the app stops listening
+async fn status_refresh_loop(
+    vpn_manager: VpnManager,
+    refreshing: Arc<AtomicBool>,
+    mut interval_rx: tokio::sync::watch::Receiver<u64>,
+    profiles_rx: tokio::sync::watch::Receiver<Vec<VpnProfile>>,
+    event_tx: tokio::sync::mpsc::Sender<AppEvent>,
+) {
+    loop {
+        let interval = (*interval_rx.borrow_and_update()).max(MIN_INTERVAL_SECONDS);
+        let wait = std::time::Duration::from_secs(interval);
+        match tokio::time::timeout(wait, interval_rx.changed()).await {
+            // Changed on the settings screen: wait the new interval
+            Ok(Ok(())) => continue,
+            Ok(Err(_)) => break,
+            Err(_) => {}
+        }
+        let vpn_manager = vpn_manager.clone().with_latency_interval(wait);
+        if refreshing.swap(true, Ordering::SeqCst) {
+            log::debug!("Status refresh skipped, the previous one is still running");
+            continue;
+        }
+
+        let profiles = profiles_rx.borrow().clone();
+        let started = std::time::Instant::now();
+        let result = vpn_manager.refresh_all_status(&profiles).await;
+        refreshing.store(false, Ordering::SeqCst);
+        let took = started.elapsed();
+        if took.as_secs() >= interval {
+            log::warn!(
+                "Status refresh took {:.1}s, longer than the {}s interval",
+                took.as_secs_f64(),
+                interval
+            );
+        }
+        if let Err(e) = result {
+            log::warn!("Status refresh failed: {}", e);
+        }
+        if event_tx.send(AppEvent::VpnStatusUpdated).await.is_err() {
+            break;
         }
     }
+}
 
-    pub fn get_filtered_profiles_indices(&self) -> Vec<usize> {
-        let mut indices: Vec<usize> = if self.search_query.is_empty() {
-            (0..self.config.profiles.len()).collect()
-        } else {
-            let query = self.search_query.to_lowercase();
-            self.config
-                .profiles
-                .iter()
-                .enumerate()
-                .filter(|(_, p)| {
-                    p.name.to_lowercase().contains(&query)
-                        || p.category.to_lowercase().contains(&query)
-                        || p.aliases.iter().any(|a| a.to_lowercase().contains(&query))
-                })
-                .map(|(i, _)| i)
-                .collect()
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // Apply sorting
-        let connections = self
-            .connections
-            .iter()
-            .map(|c| (c.profile_name.clone(), c.clone()))
-            .collect::<std::collections::HashMap<_, _>>();
+    #[test]
+    fn log_levels_map_to_directives() {
+        for level in LOG_LEVELS {
+            assert_eq!(log_directive(level), Some(format!("remipn={}", level)));
+        }
+        assert_eq!(log_directive("DEBUG").as_deref(), Some("remipn=debug"));
+        assert_eq!(log_directive("  Warn\n").as_deref(), Some("remipn=warn"));
+    }
 
-        indices.sort_by(|&a, &b| {
-            let p_a = &self.config.profiles[a];
-            let p_b = &self.config.profiles[b];
-
-            let res = match self.sort_column {
-                SortColumn::Name => p_a.name.to_lowercase().cmp(&p_b.name.to_lowercase()),
-                SortColumn::Category => p_a
-                    .category
-                    .to_lowercase()
-                    .cmp(&p_b.category.to_lowercase()),
-                SortColumn::Status => {
-                    let s_a = connections
-                        .get(&p_a.name)
-                        .map(|c| c.status.as_str())
-                        .unwrap_or_else(|| "Disconnected".to_string());
-                    let s_b = connections
-                        .get(&p_b.name)
-                        .map(|c| c.status.as_str())
-                        .unwrap_or_else(|| "Disconnected".to_string());
-                    s_a.cmp(&s_b)
-                }
-            };
+    #[test]
+    fn unknown_log_levels_fall_back_to_info() {
+        for level in ["", "verbose", "warning", "info debug"] {
+            assert_eq!(log_directive(level), None, "{:?}", level);
+        }
+        // What callers use instead
+        assert_eq!(
+            log_directive(DEFAULT_LOG_LEVEL).as_deref(),
+            Some("remipn=info")
+        );
+    }
 
-            if self.sort_direction == SortDirection::Asc {
-                res
-            } else {
-                res.reverse()
-            }
-        });
+    /// Time until the refresh loop reports its next round
+    async fn next_round(events: &mut tokio::sync::mpsc::Receiver<AppEvent>) -> std::time::Duration {
+        let started = tokio::time::Instant::now();
+        let event = events.recv().await;
+        assert!(matches!(event, Some(AppEvent::VpnStatusUpdated)));
+        started.elapsed()
+    }
 
-        indices
+    /// Rounds fire one interval apart, and an interval changed mid-wait
+    /// takes effect from the change instead of after the old one runs out
+    #[tokio::test(start_paused = true)]
+    async fn refresh_rounds_follow_the_interval() {
+        use std::time::Duration;
+
+        let (interval_tx, interval_rx) = tokio::sync::watch::channel(10);
+        let (_profiles_tx, profiles_rx) = tokio::sync::watch::channel(Vec::new());
+        let (event_tx, mut events) = tokio::sync::mpsc::channel(8);
+        tokio::spawn(status_refresh_loop(
+            VpnManager::mocked(crate::vpn::MockRunner::new()),
+            Arc::new(AtomicBool::new(false)),
+            interval_rx,
+            profiles_rx,
+            event_tx,
+        ));
+        let about = |took: Duration, secs: u64| {
+            took >= Duration::from_secs(secs) && took < Duration::from_secs(secs + 1)
+        };
+
+        let took = next_round(&mut events).await;
+        assert!(about(took, 10), "first round after {:?}", took);
+        let took = next_round(&mut events).await;
+        assert!(about(took, 10), "second round after {:?}", took);
+
+        interval_tx.send_replace(30);
+        let took = next_round(&mut events).await;
+        assert!(about(took, 30), "round after {:?} at 30s", took);
+
+        // Shortened 5s into a 30s wait: the next round is 10s after the change
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        interval_tx.send_replace(10);
+        let took = next_round(&mut events).await;
+        assert!(about(took, 10), "round after {:?} once shortened", took);
     }
 
-    fn cycle_sort(&mut self) {
-        match self.sort_column {
-            SortColumn::Name => {
-                if self.sort_direction == SortDirection::Asc {
-                    self.sort_direction = SortDirection::Desc;
-                } else {
-                    self.sort_column = SortColumn::Category;
-                    self.sort_direction = SortDirection::Asc;
-                }
-            }
-            SortColumn::Category => {
-                if self.sort_direction == SortDirection::Asc {
-                    self.sort_direction = SortDirection::Desc;
-                } else {
-                    self.sort_column = SortColumn::Status;
-                    self.sort_direction = SortDirection::Asc;
-                }
-            }
-            SortColumn::Status => {
-                if self.sort_direction == SortDirection::Asc {
-                    self.sort_direction = SortDirection::Desc;
-                } else {
-                    self.sort_column = SortColumn::Name;
-                    self.sort_direction = SortDirection::Asc;
-                }
-            }
+    fn profile(name: &str) -> VpnProfile {
+        VpnProfile {
+            name: name.to_string(),
+            gateway_address: "vpn.example.com".to_string(),
+            ..Default::default()
         }
-        self.set_status_message(format!(
-            "Sorting by {:?} ({:?})",
-            self.sort_column, self.sort_direction
+    }
+
+    /// An app over `profiles` that saves its config to a file of its own,
+    /// returned with it
+    fn app_with(profiles: Vec<VpnProfile>) -> (App, std::path::PathBuf) {
+        static NEXT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "remipn-app-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
         ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let mut config = Config::new_empty();
+        config.profiles = profiles;
+        let app = App::from_parts(
+            config,
+            VpnManager::mocked(crate::vpn::MockRunner::new()),
+            ConfigWriter::spawn_at(path.clone()),
+        );
+        (app, path)
     }
 
-    async fn refresh_from_manager(&mut self) -> Result<()> {
-        self.connections = self.vpn_manager.get_all_connections().await;
-        Ok(())
+    /// The config `app` saved, read back
+    async fn reloaded(app: &App, path: &std::path::Path) -> Config {
+        app.flush_config().await;
+        Config::load_from(path).unwrap()
     }
 
-    async fn refresh_status(&mut self) -> Result<()> {
-        self.vpn_manager
-            .refresh_all_status(&self.config.profiles)
-            .await?;
-        self.refresh_from_manager().await
+    fn at(rfc3339: &str) -> chrono::DateTime<chrono::Local> {
+        chrono::DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&chrono::Local)
     }
 
-    pub async fn update(&mut self) -> Result<()> {
-        // Periodic status update
-        let now = std::time::Instant::now();
-        if now.duration_since(self.last_update).as_secs() >= 5 {
-            let _ = self.refresh_status().await;
-            self.last_update = now;
+    #[tokio::test]
+    async fn last_connected_survives_save_and_reload() {
+        let earlier = at("2026-03-01T08:00:00+01:00");
+        let mut home = profile("Home");
+        home.last_connected = Some(earlier);
+        let (mut app, path) = app_with(vec![profile("Office"), home]);
+
+        let connected = at("2026-03-02T09:30:15.250+01:00");
+        let mut office = VpnConnection::new("Office", VpnStatus::Connected);
+        office.connected_since = Some(connected);
+        let previous = [
+            VpnConnection::new("Office", VpnStatus::Connecting),
+            VpnConnection::new("Home", VpnStatus::Connecting),
+        ];
+        // Home's attempt failed
+        app.connections = vec![office, VpnConnection::new("Home", VpnStatus::TimedOut)];
+        app.record_last_connected(&previous);
+
+        let config = reloaded(&app, &path).await;
+        assert_eq!(config.profiles[0].last_connected, Some(connected));
+        assert_eq!(config.profiles[1].last_connected, Some(earlier));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn profiles_that_are_down_sort_last_both_ways() {
+        let names = ["Alpha", "Bravo", "Charlie", "Delta"];
+        let (mut app, path) = app_with(Vec::from(names.map(profile)));
+        let up = |name: &str, since: &str, ip: &str| {
+            let mut conn = VpnConnection::new(name, VpnStatus::Connected);
+            conn.connected_since = Some(at(since));
+            conn.ip_address = Some(ip.to_string());
+            (name.to_string(), conn)
+        };
+        let connections: HashMap<String, VpnConnection> = [
+            up("Alpha", "2026-03-02T10:00:00Z", "10.8.0.9"),
+            up("Charlie", "2026-03-02T09:00:00Z", "10.8.0.10"),
+            up("Delta", "2026-03-02T11:00:00Z", "fd00::2"),
+            (
+                "Bravo".to_string(),
+                VpnConnection::new("Bravo", VpnStatus::Disconnected),
+            ),
+        ]
+        .into();
+        let order = |app: &App| {
+            let mut indices: Vec<usize> = (0..names.len()).collect();
+            indices.sort_by(|&a, &b| app.compare_profiles(a, b, &connections));
+            indices.into_iter().map(|i| names[i]).collect::<Vec<_>>()
+        };
+
+        for column in [SortColumn::Duration, SortColumn::Ip] {
+            app.sort_column = column;
+            app.sort_direction = SortDirection::Asc;
+            assert_eq!(
+                order(&app),
+                ["Charlie", "Alpha", "Delta", "Bravo"],
+                "{:?}",
+                column
+            );
+            app.sort_direction = SortDirection::Desc;
+            assert_eq!(
+                order(&app),
+                ["Delta", "Alpha", "Charlie", "Bravo"],
+                "{:?}",
+                column
+            );
         }
-        Ok(())
+
+        // Manual is the config order whichever way it is toggled
+        app.sort_column = SortColumn::Manual;
+        for direction in [SortDirection::Asc, SortDirection::Desc] {
+            app.sort_direction = direction;
+            assert_eq!(order(&app), names);
+        }
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
     }
 
-    fn set_status_message(&mut self, msg: String) {
-        self.status_message = Some((msg, chrono::Local::now()));
+    #[test]
+    fn missing_values_compare_after_present_ones() {
+        use std::cmp::Ordering::{Equal, Greater, Less};
+        for direction in [SortDirection::Asc, SortDirection::Desc] {
+            assert_eq!(compare_present_first(Some(1), None, direction), Less);
+            assert_eq!(compare_present_first(None, Some(1), direction), Greater);
+            assert_eq!(compare_present_first::<u32>(None, None, direction), Equal);
+        }
+        assert_eq!(
+            compare_present_first(Some(1), Some(2), SortDirection::Asc),
+            Less
+        );
+        assert_eq!(
+            compare_present_first(Some(1), Some(2), SortDirection::Desc),
+            Greater
+        );
     }
 
-    pub fn add_log(&mut self, msg: String) {
-        let timestamp = chrono::Local::now().format("%H:%M:%S");
-        self.logs.push(format!("[{}] {}", timestamp, msg));
-        if self.logs.len() > 100 {
-            self.logs.remove(0);
+    #[tokio::test]
+    async fn sort_order_survives_save_and_reload() {
+        let (mut app, path) = app_with(vec![profile("Office")]);
+        // Name asc -> Name desc -> Category asc -> Category desc
+        for _ in 0..3 {
+            app.cycle_sort();
         }
+        assert_eq!(
+            (app.sort_column, app.sort_direction),
+            (SortColumn::Category, SortDirection::Desc)
+        );
+
+        let config = reloaded(&app, &path).await;
+        let restored = App::from_parts(
+            config,
+            VpnManager::mocked(crate::vpn::MockRunner::new()),
+            ConfigWriter::spawn_at(path.clone()),
+        );
+        assert_eq!(restored.sort_column, SortColumn::Category);
+        assert_eq!(restored.sort_direction, SortDirection::Desc);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
     }
 
-    pub fn get_connections(&self) -> Vec<VpnConnection> {
-        self.connections.clone()
+    fn names(profiles: &[VpnProfile]) -> Vec<&str> {
+        profiles.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn reordering_persists_and_waits_for_operations() {
+        let (mut app, path) =
+            app_with(vec![profile("Alpha"), profile("Bravo"), profile("Charlie")]);
+        let alt = |code| KeyEvent::new(code, KeyModifiers::ALT);
+
+        app.handle_key(alt(KeyCode::Down)).await.unwrap();
+        assert_eq!(names(&app.config.profiles), ["Bravo", "Alpha", "Charlie"]);
+        assert_eq!(app.selected_profile, 1);
+
+        // A connect is running: reordering waits, moving the selection doesn't
+        app.operation = Some(PendingOperation {
+            label: "Connecting to Alpha".to_string(),
+            started: std::time::Instant::now(),
+            handle: tokio::spawn(std::future::pending::<()>()),
+        });
+        app.handle_key(alt(KeyCode::Down)).await.unwrap();
+        assert_eq!(names(&app.config.profiles), ["Bravo", "Alpha", "Charlie"]);
+        app.handle_key(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE))
+            .await
+            .unwrap();
+        assert_eq!(app.selected_profile, 2);
+        if let Some(op) = app.operation.take() {
+            op.handle.abort();
+        }
+
+        let config = reloaded(&app, &path).await;
+        assert_eq!(names(&config.profiles), ["Bravo", "Alpha", "Charlie"]);
+        assert_eq!(config.ui.sort_column, SortColumn::Manual);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn favorites_survive_save_and_reload() {
+        let (mut app, path) = app_with(vec![profile("Alpha"), profile("Bravo")]);
+        app.selected_profile = 1;
+        app.handle_key(KeyEvent::new(KeyCode::Char('*'), KeyModifiers::NONE))
+            .await
+            .unwrap();
+        // Bravo moved to the top and stayed selected
+        assert_eq!(app.get_filtered_profiles_indices(), [1, 0]);
+        assert_eq!(app.selected_profile, 0);
+
+        let config = reloaded(&app, &path).await;
+        let favorites: Vec<bool> = config.profiles.iter().map(|p| p.favorite).collect();
+        assert_eq!(favorites, [false, true]);
+        let restored = App::from_parts(
+            config,
+            VpnManager::mocked(crate::vpn::MockRunner::new()),
+            ConfigWriter::spawn_at(path.clone()),
+        );
+        assert_eq!(restored.get_filtered_profiles_indices(), [1, 0]);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn batch_connect_brings_up_one_profile_without_multiple_connections() {
+        let tagged = |name: &str| {
+            let mut p = profile(name);
+            p.set_tags(vec!["emea".to_string()]);
+            p
+        };
+        let (mut app, path) = app_with(vec![tagged("Berlin"), tagged("Madrid"), tagged("Paris")]);
+        let (event_tx, _events) = tokio::sync::mpsc::channel(64);
+        app.event_tx = Some(event_tx);
+        app.category_filter.insert("emea".to_string());
+
+        app.batch_filtered(true);
+        let op = app.operation.take().expect("no batch started");
+        op.handle.abort();
+        assert_eq!(op.label, "Connecting 1 profile(s) tagged emea");
+        assert!(
+            app.logs
+                .iter()
+                .any(|l| l.level == LogLevel::Warning && l.message.contains("skipping 2")),
+            "{:?}",
+            app.logs.iter().map(|l| &l.message).collect::<Vec<_>>()
+        );
+
+        // One of them is already up: connecting another would close it
+        app.connections = vec![VpnConnection::new("Madrid", VpnStatus::Connected)];
+        app.batch_filtered(true);
+        assert!(app.operation.is_none());
+        let (message, _) = app.status_message.clone().unwrap();
+        assert!(
+            message.starts_with("Madrid is already connected"),
+            "{}",
+            message
+        );
+
+        // Disconnecting several is always fine
+        app.batch_filtered(false);
+        let op = app.operation.take().expect("no batch started");
+        op.handle.abort();
+        assert_eq!(op.label, "Disconnecting 1 profile(s) tagged emea");
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
     }
 }
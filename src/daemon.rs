@@ -0,0 +1,259 @@
+use crate::config::{Config, VpnProfile};
+use crate::output::StatusView;
+use crate::vpn::{VpnConnection, VpnManager};
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::os::unix::fs::PermissionsExt;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+/// A single line-delimited JSON command accepted on the control socket,
+/// e.g. `{"command": "connect", "name": "prod"}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum ControlRequest {
+    Connect { name: String },
+    Disconnect { name: Option<String> },
+    Status { name: Option<String> },
+    List,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connections: Vec<ConnectionInfo>,
+    /// The same connections joined with their profiles, in the shape the
+    /// CLI prints them without a daemon
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub statuses: Vec<StatusView>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    pub name: String,
+    pub status: String,
+    pub ip: Option<String>,
+    pub since: Option<String>,
+}
+
+impl From<&VpnConnection> for ConnectionInfo {
+    fn from(conn: &VpnConnection) -> Self {
+        Self {
+            name: conn.profile_name.clone(),
+            status: conn.status.as_str(),
+            ip: conn.ip_address.clone(),
+            since: conn.connected_since.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+impl ControlResponse {
+    fn ok(connections: Vec<ConnectionInfo>, statuses: Vec<StatusView>) -> Self {
+        Self {
+            ok: true,
+            error: None,
+            connections,
+            statuses,
+        }
+    }
+
+    fn error(msg: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(msg.into()),
+            connections: Vec::new(),
+            statuses: Vec::new(),
+        }
+    }
+}
+
+/// Run the daemon: serve control requests on the Unix socket and keep the
+/// shared connection map refreshed in the background
+pub async fn run() -> Result<()> {
+    let socket_path = Config::control_socket_path()?;
+    match UnixStream::connect(&socket_path).await {
+        Ok(_) => {
+            return Err(anyhow!(
+                "A remipn daemon is already listening on {}",
+                socket_path.display()
+            ));
+        }
+        // Nobody answers: a stale socket from a previous run, which would
+        // make bind fail
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            std::fs::remove_file(&socket_path)?;
+        }
+        Err(_) => {}
+    }
+
+    let listener = UnixListener::bind(&socket_path)?;
+    std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600))?;
+    log::info!("remipn daemon listening on {}", socket_path.display());
+
+    let settings = Config::load().map(|cfg| cfg.settings).unwrap_or_default();
+    let mgr = VpnManager::new()
+        .with_connect_timeout(std::time::Duration::from_secs(
+            settings.connect_timeout_seconds,
+        ))
+        .with_status_debounce(settings.status_debounce_refreshes)
+        .with_latency_interval(std::time::Duration::from_secs(
+            settings.status_check_interval_seconds,
+        ))
+        .with_history_limit(settings.history_max_lines)
+        .with_multiple_connections(settings.allow_multiple_connections)
+        .with_hook_policy(
+            std::time::Duration::from_secs(settings.hook_timeout_seconds),
+            settings.abort_on_hook_failure,
+        );
+
+    let refresh_mgr = mgr.clone();
+    tokio::spawn(async move {
+        loop {
+            let interval = match Config::load() {
+                Ok(cfg) => {
+                    let _ = refresh_mgr.refresh_all_status(&cfg.profiles).await;
+                    cfg.settings.status_check_interval_seconds.max(1)
+                }
+                Err(_) => 5,
+            };
+            tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+        }
+    });
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let mgr = mgr.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_client(stream, mgr).await {
+                log::warn!("Control client error: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_client(stream: UnixStream, mgr: VpnManager) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => handle_request(request, &mgr).await,
+            Err(e) => ControlResponse::error(format!("Invalid request: {}", e)),
+        };
+        let mut payload = serde_json::to_string(&response)?;
+        payload.push('\n');
+        writer.write_all(payload.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+async fn handle_request(request: ControlRequest, mgr: &VpnManager) -> ControlResponse {
+    let cfg = match Config::load() {
+        Ok(cfg) => cfg,
+        Err(e) => return ControlResponse::error(format!("Failed to load config: {}", e)),
+    };
+
+    match request {
+        ControlRequest::Connect { name } => {
+            let Some(profile) = cfg.find_profile(&name) else {
+                return ControlResponse::error(format!("Profile '{}' not found", name));
+            };
+            match mgr.connect(profile).await {
+                Ok(_) => connections_for(mgr, &cfg.profiles, Some(&profile.name)).await,
+                Err(e) => ControlResponse::error(e.to_string()),
+            }
+        }
+        ControlRequest::Disconnect { name: Some(name) } => {
+            let target = cfg
+                .find_profile(&name)
+                .map(|p| p.name.clone())
+                .unwrap_or(name);
+            match mgr.disconnect(&target).await {
+                Ok(_) => connections_for(mgr, &cfg.profiles, Some(&target)).await,
+                Err(e) => ControlResponse::error(e.to_string()),
+            }
+        }
+        ControlRequest::Disconnect { name: None } => {
+            for p in &cfg.profiles {
+                let _ = mgr.disconnect(&p.name).await;
+            }
+            connections_for(mgr, &cfg.profiles, None).await
+        }
+        ControlRequest::Status { name } => {
+            if let Err(e) = mgr.refresh_all_status(&cfg.profiles).await {
+                return ControlResponse::error(e.to_string());
+            }
+            let target = name.map(|n| cfg.find_profile(&n).map(|p| p.name.clone()).unwrap_or(n));
+            connections_for(mgr, &cfg.profiles, target.as_deref()).await
+        }
+        ControlRequest::List => {
+            if let Err(e) = mgr.refresh_all_status(&cfg.profiles).await {
+                return ControlResponse::error(e.to_string());
+            }
+            connections_for(mgr, &cfg.profiles, None).await
+        }
+    }
+}
+
+async fn connections_for(
+    mgr: &VpnManager,
+    profiles: &[VpnProfile],
+    name: Option<&str>,
+) -> ControlResponse {
+    let all = mgr.get_all_connections().await;
+    let connections = all
+        .iter()
+        .filter(|c| name.is_none_or(|n| c.profile_name == n))
+        .map(ConnectionInfo::from)
+        .collect();
+    let statuses = profiles
+        .iter()
+        .filter(|p| name.is_none_or(|n| p.name == n))
+        .map(|p| StatusView::new(p, all.iter().find(|c| c.profile_name == p.name)))
+        .collect();
+    ControlResponse::ok(connections, statuses)
+}
+
+/// How long a client waits to reach the daemon and hand over a request
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a client waits for the reply; a disconnect may run hooks first
+const REPLY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Send a request to a running daemon, returning `None` when no daemon is
+/// listening. A daemon that stops answering or replies with something that
+/// isn't a response is an error, so the caller doesn't act a second time.
+pub async fn send_request(request: &ControlRequest) -> Result<Option<ControlResponse>> {
+    let socket_path = Config::control_socket_path()?;
+    let stream = match tokio::time::timeout(SEND_TIMEOUT, UnixStream::connect(&socket_path)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(_)) => return Ok(None),
+        Err(_) => return Err(anyhow!("The remipn daemon didn't accept the connection")),
+    };
+    let (reader, mut writer) = stream.into_split();
+
+    let mut payload = serde_json::to_string(request)?;
+    payload.push('\n');
+    tokio::time::timeout(SEND_TIMEOUT, writer.write_all(payload.as_bytes()))
+        .await
+        .map_err(|_| anyhow!("Timed out sending the request to the remipn daemon"))??;
+
+    let mut lines = BufReader::new(reader).lines();
+    let line = tokio::time::timeout(REPLY_TIMEOUT, lines.next_line())
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "The remipn daemon didn't reply within {}s",
+                REPLY_TIMEOUT.as_secs()
+            )
+        })??
+        .ok_or_else(|| anyhow!("The remipn daemon closed the connection without replying"))?;
+    serde_json::from_str(&line).map_err(|e| anyhow!("Invalid reply from the remipn daemon: {}", e))
+}
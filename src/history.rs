@@ -0,0 +1,127 @@
+//! Connection history, appended to `~/.config/remipn/history.jsonl` with one
+//! JSON object per line so it can be audited later, by `remipn history`, the
+//! TUI's history screen or plain `jq`.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Lines kept in the history file unless `history_max_lines` says otherwise
+pub const DEFAULT_MAX_LINES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectAction {
+    Connect,
+    Disconnect,
+    /// The tunnel went down without being disconnected
+    Drop,
+}
+
+impl std::fmt::Display for ConnectAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectAction::Connect => write!(f, "connect"),
+            ConnectAction::Disconnect => write!(f, "disconnect"),
+            ConnectAction::Drop => write!(f, "drop"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Ok,
+    Error(String),
+}
+
+impl Outcome {
+    pub fn from_result<T>(result: &Result<T>) -> Self {
+        match result {
+            Ok(_) => Outcome::Ok,
+            Err(e) => Outcome::Error(e.to_string()),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Outcome::Ok)
+    }
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Ok => write!(f, "ok"),
+            Outcome::Error(e) => write!(f, "error: {}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionEvent {
+    pub profile: String,
+    pub action: ConnectAction,
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub outcome: Outcome,
+}
+
+impl ConnectionEvent {
+    pub fn new(profile: &str, action: ConnectAction, outcome: Outcome) -> Self {
+        Self {
+            profile: profile.to_string(),
+            action,
+            timestamp: chrono::Local::now(),
+            outcome,
+        }
+    }
+}
+
+pub fn history_path() -> Result<PathBuf> {
+    let dir = dirs::home_dir()
+        .ok_or_else(|| anyhow!("Could not find home directory"))?
+        .join(".config/remipn/");
+    if !dir.exists() {
+        fs::create_dir_all(&dir)?;
+    }
+    Ok(dir.join("history.jsonl"))
+}
+
+/// Append an event to the history file at `path`, then cut it down to its
+/// newest `max_lines` lines (0 keeps everything)
+pub fn append(path: &Path, event: &ConnectionEvent, max_lines: usize) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(event)?)?;
+    drop(file);
+
+    if max_lines > 0 {
+        let contents = fs::read_to_string(path)?;
+        let lines: Vec<&str> = contents.lines().collect();
+        if lines.len() > max_lines {
+            let kept = lines[lines.len() - max_lines..].join("\n");
+            fs::write(path, kept + "\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// The newest `limit` events, optionally only those of one profile, newest
+/// first. Lines that don't parse are skipped.
+pub fn load(profile: Option<&str>, limit: usize) -> Result<Vec<ConnectionEvent>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .rev()
+        .filter_map(|line| serde_json::from_str::<ConnectionEvent>(line).ok())
+        .filter(|event| profile.is_none_or(|p| event.profile == p))
+        .take(limit)
+        .collect())
+}
@@ -1,5 +1,13 @@
 pub mod app;
+pub mod clipboard;
 pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod history;
+pub mod input;
+pub mod output;
+pub mod report;
+pub mod secrets;
 pub mod ui;
 pub mod vpn;
 
@@ -0,0 +1,176 @@
+use crate::config::VpnProfile;
+use crate::vpn::{VpnConnection, VpnStatus};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Output format shared by every CLI subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Colored tables and messages
+    #[default]
+    Human,
+    Json,
+    Yaml,
+}
+
+impl OutputFormat {
+    pub fn is_human(self) -> bool {
+        self == OutputFormat::Human
+    }
+}
+
+/// Serialize a view for a machine-readable format. Returns `None` for the
+/// human format, which each command renders itself.
+pub fn render<T: Serialize>(format: OutputFormat, value: &T) -> Result<Option<String>> {
+    Ok(match format {
+        OutputFormat::Human => None,
+        OutputFormat::Json => Some(serde_json::to_string_pretty(value)?),
+        OutputFormat::Yaml => Some(serde_yaml::to_string(value)?),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileView {
+    pub name: String,
+    pub alias: Option<String>,
+    pub category: String,
+    pub tags: Vec<String>,
+    pub gateway: String,
+    pub port: Option<u16>,
+    pub protocol: String,
+    pub source: Option<String>,
+    pub last_connected: Option<String>,
+}
+
+impl From<&VpnProfile> for ProfileView {
+    fn from(p: &VpnProfile) -> Self {
+        Self {
+            name: p.name.clone(),
+            alias: p.aliases.clone(),
+            category: p.category.clone(),
+            tags: p.tags.clone(),
+            gateway: p.gateway_host(),
+            port: p.gateway_port(),
+            protocol: p.protocol.to_string(),
+            source: p.source.clone(),
+            last_connected: p.last_connected.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionView {
+    pub status: String,
+    /// Error or degradation reason, when the status carries one
+    pub detail: Option<String>,
+    pub ip: Option<String>,
+    pub since: Option<String>,
+    /// Whether remipn brought the tunnel up, as opposed to finding it running
+    pub owned: bool,
+    /// Gateway round-trip time while connected
+    pub latency_ms: Option<u32>,
+    /// Egress IP seen by the public-IP endpoint after connecting
+    pub public_ip: Option<String>,
+}
+
+impl ConnectionView {
+    pub fn new(conn: Option<&VpnConnection>) -> Self {
+        let status = conn
+            .map(|c| c.status.clone())
+            .unwrap_or(VpnStatus::Disconnected);
+        Self {
+            status: status.key().to_string(),
+            detail: status.detail().map(|d| d.to_string()),
+            ip: conn.and_then(|c| c.ip_address.clone()),
+            since: conn.and_then(|c| c.connected_since).map(|t| t.to_rfc3339()),
+            owned: conn.is_some_and(|c| c.owned_by_remipn),
+            latency_ms: conn.and_then(|c| c.latency_ms),
+            public_ip: conn.and_then(|c| c.public_ip.clone()),
+        }
+    }
+}
+
+/// Outcome of a single `doctor` check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckView {
+    pub check: String,
+    pub ok: bool,
+    pub detail: Option<String>,
+}
+
+/// Result of probing one profile's gateway
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReachabilityView {
+    pub name: String,
+    pub gateway: String,
+    pub reachable: bool,
+    pub rtt_ms: Option<u32>,
+}
+
+/// A profile together with its live connection state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusView {
+    #[serde(flatten)]
+    pub profile: ProfileView,
+    #[serde(flatten)]
+    pub connection: ConnectionView,
+}
+
+impl StatusView {
+    pub fn new(profile: &VpnProfile, conn: Option<&VpnConnection>) -> Self {
+        Self {
+            profile: ProfileView::from(profile),
+            connection: ConnectionView::new(conn),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_status_views_read_back() {
+        let profile = |name: &str| VpnProfile {
+            name: name.to_string(),
+            aliases: Some(name.to_lowercase()),
+            gateway_address: "vpn.example.com".to_string(),
+            ..Default::default()
+        };
+        let mut up = VpnConnection::new("Office", VpnStatus::Connected);
+        up.ip_address = Some("10.8.0.2".to_string());
+        up.connected_since = Some(chrono::Local::now());
+        up.latency_ms = Some(23);
+        let views = vec![
+            StatusView::new(&profile("Office"), Some(&up)),
+            StatusView::new(&profile("Home"), None),
+        ];
+
+        let json = render(OutputFormat::Json, &views).unwrap().unwrap();
+        assert!(!json.contains('\u{1b}'), "ANSI codes in {}", json);
+
+        // Every field is there, flattened into one object per profile
+        let values: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        for value in &values {
+            for field in [
+                "name",
+                "alias",
+                "category",
+                "status",
+                "ip",
+                "since",
+                "latency_ms",
+            ] {
+                assert!(value.get(field).is_some(), "no {} in {}", field, value);
+            }
+        }
+        assert_eq!(values[0]["status"], "connected");
+        assert_eq!(values[0]["ip"], "10.8.0.2");
+        assert_eq!(values[0]["latency_ms"], 23);
+        assert_eq!(values[1]["status"], "disconnected");
+        assert!(values[1]["since"].is_null());
+
+        let read: Vec<StatusView> = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, views);
+    }
+}
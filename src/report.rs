@@ -0,0 +1,125 @@
+use crate::config::Config;
+use crate::output::CheckView;
+use crate::vpn::VpnManager;
+use anyhow::Result;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Placeholder written over personal values in a bundled config
+const REDACTED: &str = "<redacted>";
+
+/// Config problems and platform tool availability, as reported by `doctor`
+pub async fn doctor_checks(mgr: &VpnManager) -> Vec<CheckView> {
+    let mut checks = Vec::new();
+    match Config::load() {
+        Ok(cfg) => {
+            checks.push(CheckView {
+                check: "config".to_string(),
+                ok: cfg.load_warnings.is_empty(),
+                detail: Some(if cfg.load_warnings.is_empty() {
+                    format!("{} profile(s)", cfg.profiles.len())
+                } else {
+                    cfg.load_warnings.join("; ")
+                }),
+            });
+        }
+        Err(e) => checks.push(CheckView {
+            check: "config".to_string(),
+            ok: false,
+            detail: Some(e.to_string()),
+        }),
+    }
+
+    for (program, problem) in mgr.check_tools().await {
+        checks.push(CheckView {
+            check: program.to_string(),
+            ok: problem.is_none(),
+            detail: problem,
+        });
+    }
+    checks
+}
+
+/// The config as TOML with usernames, certificate/keychain references and
+/// hook commands replaced, so it can be attached to a public bug report
+pub fn redacted_config(cfg: &Config) -> Result<String> {
+    let mut cfg = cfg.clone();
+    for p in &mut cfg.profiles {
+        if p.username.is_some() {
+            p.username = Some(REDACTED.to_string());
+        }
+        if p.cert_path.is_some() {
+            p.cert_path = Some(REDACTED.to_string());
+        }
+        // Hooks tend to carry passwords and tokens inline
+        for hook in [
+            &mut p.pre_connect_cmd,
+            &mut p.post_connect_cmd,
+            &mut p.pre_disconnect_cmd,
+            &mut p.post_disconnect_cmd,
+        ] {
+            if hook.is_some() {
+                *hook = Some(REDACTED.to_string());
+            }
+        }
+    }
+    Ok(toml::to_string_pretty(&cfg)?)
+}
+
+/// Timestamped bundle file name, e.g. `remipn-report-20240131-142501.zip`
+pub fn default_bundle_name() -> PathBuf {
+    PathBuf::from(format!(
+        "remipn-report-{}.zip",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ))
+}
+
+/// Write a zip with the redacted config, `doctor` results, OS/tool details
+/// and, when given, recent log lines
+pub async fn write_bundle(path: &Path, logs: &[String]) -> Result<()> {
+    let mgr = VpnManager::new();
+    let checks = doctor_checks(&mgr).await;
+
+    let config = match Config::load() {
+        Ok(cfg) => redacted_config(&cfg)?,
+        Err(e) => format!("# config failed to load: {}\n", e),
+    };
+
+    let mut system = format!(
+        "remipn {}\nos: {} ({})\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    for check in &checks {
+        system.push_str(&format!(
+            "{}: {}\n",
+            check.check,
+            if check.ok {
+                "ok"
+            } else {
+                check.detail.as_deref().unwrap_or("failed")
+            }
+        ));
+    }
+
+    let mut entries = vec![
+        ("config.toml", config),
+        ("doctor.json", serde_json::to_string_pretty(&checks)?),
+        ("system.txt", system),
+    ];
+    if !logs.is_empty() {
+        entries.push(("logs.txt", logs.join("\n") + "\n"));
+    }
+
+    let file = std::fs::File::create(path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, contents) in entries {
+        zip.start_file(name, options)?;
+        zip.write_all(contents.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
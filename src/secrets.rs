@@ -0,0 +1,56 @@
+//! Profile passwords, kept in the OS credential store (macOS Keychain,
+//! Secret Service on Linux, Credential Manager on Windows) under the service
+//! name `remipn` and the profile name. They never go into `config.toml` or
+//! the logs.
+
+use anyhow::{Result, anyhow};
+
+const SERVICE: &str = "remipn";
+
+pub trait SecretStore: std::fmt::Debug + Send + Sync {
+    fn store(&self, profile: &str, secret: &str) -> Result<()>;
+    /// The saved secret, or None when the profile has none
+    fn retrieve(&self, profile: &str) -> Result<Option<String>>;
+    /// Forget the profile's secret; succeeds when there was none
+    fn delete(&self, profile: &str) -> Result<()>;
+}
+
+/// The platform's credential store, through the `keyring` crate
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KeyringStore;
+
+fn entry(profile: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, profile)
+        .map_err(|e| anyhow!("Credential store unavailable: {}", e))
+}
+
+impl SecretStore for KeyringStore {
+    fn store(&self, profile: &str, secret: &str) -> Result<()> {
+        entry(profile)?
+            .set_password(secret)
+            .map_err(|e| anyhow!("Could not save the password for {}: {}", profile, e))
+    }
+
+    fn retrieve(&self, profile: &str) -> Result<Option<String>> {
+        match entry(profile)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!(
+                "Could not read the password for {}: {}",
+                profile,
+                e
+            )),
+        }
+    }
+
+    fn delete(&self, profile: &str) -> Result<()> {
+        match entry(profile)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow!(
+                "Could not delete the password for {}: {}",
+                profile,
+                e
+            )),
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::app::{App, Screen};
+use crate::app::{App, Screen, SettingsField};
+use crate::input::TextInput;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -7,7 +8,18 @@ use ratatui::{
     widgets::{Block, Borders, Cell, List, ListItem, Paragraph, Row, Table, Wrap},
 };
 
+/// Smallest terminal the layouts fit in: the main screen stacks a 3-row title,
+/// at least 10 rows of list and a 3-row status bar
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 16;
+
 pub fn draw(f: &mut Frame, app: &App) {
+    let size = f.size();
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        draw_too_small(f);
+        return;
+    }
+
     match app.screen {
         Screen::Main => draw_main_screen(f, app),
         Screen::AddProfile => draw_add_profile_screen(f, app),
@@ -16,9 +28,118 @@ pub fn draw(f: &mut Frame, app: &App) {
         Screen::FileBrowser => draw_file_browser_screen(f, app),
         Screen::Help => draw_help_screen(f),
         Screen::DeleteConfirmation => draw_delete_confirmation(f, app),
+        Screen::ConnectConfirmation => draw_main_screen(f, app),
+        Screen::KillSwitchConfirm => draw_main_screen(f, app),
         Screen::Search => draw_main_screen(f, app), // Search is rendered as part of the main or overlay
         Screen::AliasModal => draw_main_screen(f, app),
+        Screen::ColorModal => draw_main_screen(f, app),
+        Screen::GatewayModal => draw_main_screen(f, app),
+        Screen::PasswordModal => draw_main_screen(f, app),
+        Screen::ConnectWizard => draw_main_screen(f, app),
+        Screen::SequenceModal => draw_main_screen(f, app),
+        Screen::ProfileDetails => draw_main_screen(f, app),
+        Screen::History => draw_history_screen(f, app),
+        Screen::Logs => draw_logs_screen(f, app),
+        Screen::FilterByCategory => draw_main_screen(f, app),
+        Screen::Settings => draw_settings_screen(f, app),
+    }
+
+    draw_operation_overlay(f, app);
+
+    if app.alert_flash {
+        let flash = Block::default().style(Style::default().bg(Color::Red));
+        f.render_widget(flash, f.size());
+    }
+    draw_alert_toast(f, app);
+}
+
+fn draw_operation_overlay(f: &mut Frame, app: &App) {
+    let Some(op) = &app.operation else {
+        return;
+    };
+
+    let spinner = ['|', '/', '-', '\\'];
+    let elapsed = op.started.elapsed();
+    let frame = spinner[(elapsed.as_millis() / 200) as usize % spinner.len()];
+
+    let area = centered_rect(50, 20, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Working ")
+        .border_style(Style::default().fg(Color::Yellow));
+    let body = Paragraph::new(vec![
+        Line::from(""),
+        Line::from(format!("{} {}... {}s", frame, op.label, elapsed.as_secs())),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Other keys are ignored until this finishes",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ])
+    .alignment(Alignment::Center)
+    .block(block);
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(body, area);
+
+    let help_area = Rect {
+        x: area.x,
+        y: area.y + area.height - 1,
+        width: area.width,
+        height: 1,
+    };
+    let help_text = Paragraph::new(" [Esc] Cancel ")
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help_text, help_area);
+}
+
+fn draw_too_small(f: &mut Frame) {
+    let size = f.size();
+    let msg = format!(
+        "Terminal too small (need ≥ {}x{}, have {}x{})",
+        MIN_WIDTH, MIN_HEIGHT, size.width, size.height
+    );
+    let para = Paragraph::new(msg)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::Yellow))
+        .wrap(Wrap { trim: true });
+    f.render_widget(para, size);
+}
+
+/// Toast shown for a few seconds after an alert (tunnel drop, data cap)
+fn draw_alert_toast(f: &mut Frame, app: &App) {
+    let Some((msg, timestamp)) = &app.alert_toast else {
+        return;
+    };
+    if chrono::Local::now()
+        .signed_duration_since(*timestamp)
+        .num_seconds()
+        >= 10
+    {
+        return;
     }
+
+    let size = f.size();
+    let width = (msg.chars().count() as u16 + 6).min(size.width);
+    let area = Rect::new(
+        size.width.saturating_sub(width),
+        0,
+        width,
+        3.min(size.height),
+    );
+    let toast = Paragraph::new(format!("⚠ {}", msg))
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .fg(Color::White)
+                .bg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(Borders::ALL));
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(toast, area);
 }
 
 fn draw_main_screen(f: &mut Frame, app: &App) {
@@ -31,19 +152,26 @@ fn draw_main_screen(f: &mut Frame, app: &App) {
         ])
         .split(f.size());
 
-    // Title
-    let title = Paragraph::new("RemiPN")
-        .style(
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        )
+    // Title with per-status counts; the active status filter is highlighted.
+    // An installed kill switch turns it into a red banner.
+    let mut title_block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded);
+    if let Some(profile) = &app.kill_switch {
+        title_block = title_block
+            .border_style(Style::default().fg(Color::Red))
+            .title(Span::styled(
+                format!(" KILL SWITCH ACTIVE: {} (K to remove) ", profile),
+                Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .title_alignment(Alignment::Center);
+    }
+    let title = Paragraph::new(title_line(app))
         .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(ratatui::widgets::BorderType::Rounded),
-        );
+        .block(title_block);
     f.render_widget(title, chunks[0]);
 
     // Main content area
@@ -79,6 +207,94 @@ fn draw_main_screen(f: &mut Frame, app: &App) {
     if app.screen == Screen::AliasModal {
         draw_alias_modal(f, app);
     }
+
+    // Gateway overlay
+    if app.screen == Screen::GatewayModal {
+        draw_gateway_modal(f, app);
+    }
+
+    // Password overlay
+    if app.screen == Screen::PasswordModal {
+        draw_password_modal(f, app);
+    }
+
+    // Connect confirmation overlay
+    if app.screen == Screen::ConnectConfirmation {
+        draw_connect_confirmation(f, app);
+    }
+
+    // Kill switch confirmation overlay
+    if app.screen == Screen::KillSwitchConfirm {
+        draw_kill_switch_confirmation(f, app);
+    }
+
+    // Color picker overlay
+    if app.screen == Screen::ColorModal {
+        draw_color_modal(f, app);
+    }
+
+    // Missing-service setup overlay
+    if app.screen == Screen::ConnectWizard {
+        draw_wizard_modal(f, app);
+    }
+
+    // Sequence picker overlay
+    if app.screen == Screen::SequenceModal {
+        draw_sequence_modal(f, app);
+    }
+
+    // Tag filter overlay
+    if app.screen == Screen::FilterByCategory {
+        draw_category_filter(f, app);
+    }
+
+    // Selected profile details overlay
+    if app.screen == Screen::ProfileDetails {
+        draw_profile_details(f, app);
+    }
+}
+
+fn title_line(app: &App) -> Line<'static> {
+    use crate::app::StatusFilter;
+
+    let connections = app.get_connections();
+    let count = |filter: StatusFilter| {
+        app.config
+            .profiles
+            .iter()
+            .filter(|p| {
+                let status = connections
+                    .iter()
+                    .find(|c| c.profile_name == p.name)
+                    .map(|c| c.status.clone())
+                    .unwrap_or(crate::vpn::VpnStatus::Disconnected);
+                filter.matches(&status)
+            })
+            .count()
+    };
+
+    let counter = |filter: StatusFilter, glyph: &str, color: Color| {
+        let mut style = Style::default().fg(color);
+        if app.status_filter == filter {
+            style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        }
+        Span::styled(
+            format!("  {} {} {}", glyph, count(filter), filter.label()),
+            style,
+        )
+    };
+
+    Line::from(vec![
+        Span::styled(
+            "RemiPN",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        counter(StatusFilter::Connected, "●", Color::Green),
+        counter(StatusFilter::Disconnected, "○", Color::Gray),
+        counter(StatusFilter::Error, "✗", Color::Red),
+    ])
 }
 
 fn draw_vpn_list(f: &mut Frame, app: &App, area: Rect) {
@@ -89,6 +305,11 @@ fn draw_vpn_list(f: &mut Frame, app: &App, area: Rect) {
         .collect();
 
     let filtered_indices = app.get_filtered_profiles_indices();
+    if filtered_indices.is_empty() {
+        draw_empty_state(f, app, area);
+        return;
+    }
+
     let rows: Vec<Row> = filtered_indices
         .iter()
         .map(|&idx| {
@@ -99,88 +320,118 @@ fn draw_vpn_list(f: &mut Frame, app: &App, area: Rect) {
                 .unwrap_or(crate::vpn::VpnStatus::Disconnected);
 
             let status_color = status.color();
-            let status_text = status.as_str();
+            // Mark who brought the tunnel up: remipn (●) or something else (○)
+            let status_text = match (&status, conn) {
+                (
+                    crate::vpn::VpnStatus::Connected | crate::vpn::VpnStatus::Degraded(_),
+                    Some(c),
+                ) => {
+                    let marker = if c.owned_by_remipn { "●" } else { "○" };
+                    format!("{} {}", marker, status.as_str())
+                }
+                _ => status.as_str(),
+            };
 
             let connected_time = conn
                 .and_then(|c| c.connected_since)
-                .map(|t| {
-                    let duration = chrono::Local::now().signed_duration_since(t);
-                    format!("{}m", duration.num_minutes())
-                })
+                .map(|t| app.config.settings.duration_display.format(t))
                 .unwrap_or_else(|| "-".to_string());
 
+            let last_ip = conn
+                .filter(|c| c.ip_address.is_none() && app.config.settings.show_last_ip)
+                .and_then(|c| c.last_ip.as_deref());
             let ip_addr = conn
                 .and_then(|c| c.ip_address.clone())
                 .unwrap_or_else(|| "-".to_string());
+            let latency = conn
+                .map(|c| c.latency_display())
+                .unwrap_or_else(|| "-".to_string());
+            let traffic = conn
+                .map(|c| c.traffic_display())
+                .unwrap_or_else(|| "-".to_string());
+
+            let display = app.config.settings.display_primary;
+            let secondary = display.secondary(profile).unwrap_or("-").to_string();
+
+            let name_style = if profile.enabled {
+                profile
+                    .accent_color()
+                    .map(|c| Style::default().fg(c))
+                    .unwrap_or_default()
+            } else {
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::CROSSED_OUT)
+            };
 
-            let alias = profile.aliases.clone().unwrap_or_else(|| "-".to_string());
+            let mut name_spans = highlight_matches(
+                display.primary(profile),
+                app.search_match_query(),
+                name_style,
+            );
+            if profile.favorite {
+                name_spans.insert(0, Span::styled("★ ", Style::default().fg(Color::Yellow)));
+            }
+            if app.watched_profile() == Some(profile.name.as_str()) {
+                name_spans.insert(0, Span::styled("◉ ", name_style));
+            }
 
             Row::new(vec![
-                Cell::from(profile.name.clone()),
-                Cell::from(alias),
-                Cell::from(profile.category.clone()),
+                Cell::from(Line::from(name_spans)),
+                Cell::from(secondary),
+                Cell::from(profile.tags_display()),
                 Cell::from(Span::styled(status_text, Style::default().fg(status_color))),
                 Cell::from(connected_time),
-                Cell::from(ip_addr),
+                match last_ip {
+                    Some(ip) => Cell::from(Span::styled(
+                        format!("(was {})", ip),
+                        Style::default().fg(Color::DarkGray),
+                    )),
+                    None => Cell::from(ip_addr),
+                },
+                Cell::from(latency),
+                Cell::from(traffic),
             ])
         })
         .collect();
+    let alias_first = app.config.settings.display_primary == crate::config::DisplayPrimary::Alias;
     let header_name = format!(
-        "Profile {}",
-        if app.sort_column == crate::app::SortColumn::Name {
-            if app.sort_direction == crate::app::SortDirection::Asc {
-                "▲"
-            } else {
-                "▼"
-            }
-        } else {
-            ""
-        }
-    );
-    let header_category = format!(
-        "Category {}",
-        if app.sort_column == crate::app::SortColumn::Category {
-            if app.sort_direction == crate::app::SortDirection::Asc {
-                "▲"
-            } else {
-                "▼"
-            }
-        } else {
-            ""
-        }
+        "{} {}",
+        if alias_first { "Alias" } else { "Profile" },
+        sort_arrow(app, crate::app::SortColumn::Name)
     );
-    let header_status = format!(
-        "Status {}",
-        if app.sort_column == crate::app::SortColumn::Status {
-            if app.sort_direction == crate::app::SortDirection::Asc {
-                "▲"
-            } else {
-                "▼"
-            }
-        } else {
-            ""
-        }
+    let header_category = format!("Tags {}", sort_arrow(app, crate::app::SortColumn::Category));
+    let header_status = format!("Status {}", sort_arrow(app, crate::app::SortColumn::Status));
+    let header_duration = format!(
+        "{} {}",
+        app.config.settings.duration_display.header(),
+        sort_arrow(app, crate::app::SortColumn::Duration)
     );
+    let header_ip = format!("IP Address {}", sort_arrow(app, crate::app::SortColumn::Ip));
 
     let table = Table::new(
         rows,
         [
-            Constraint::Min(25),    // Profile Name
-            Constraint::Length(15), // Alias
-            Constraint::Length(15), // Category
+            Constraint::Min(25),    // Primary identifier (name or alias)
+            Constraint::Length(15), // Secondary identifier
+            Constraint::Length(15), // Tags
             Constraint::Length(15), // Status
-            Constraint::Length(10), // Duration
+            Constraint::Length(app.config.settings.duration_display.width()), // Duration
             Constraint::Min(20),    // IP Address
+            Constraint::Length(8),  // Latency
+            Constraint::Length(24), // Traffic
         ],
     )
     .header(
         Row::new(vec![
             header_name,
-            "Alias".to_string(),
+            if alias_first { "Profile" } else { "Alias" }.to_string(),
             header_category,
             header_status,
-            "Duration".to_string(),
-            "IP Address".to_string(),
+            header_duration,
+            header_ip,
+            "Ping".to_string(),
+            "Traffic".to_string(),
         ])
         .style(
             Style::default()
@@ -198,11 +449,117 @@ fn draw_vpn_list(f: &mut Frame, app: &App, area: Rect) {
         Block::default()
             .borders(Borders::ALL)
             .border_type(ratatui::widgets::BorderType::Rounded)
-            .title(" VPN Connections (↑/↓: select, Enter: toggle, /: search, s: sort, i: import) "),
+            .title(list_title(app)),
     )
     .column_spacing(1);
 
-    f.render_stateful_widget(table, area, &mut app.table_state.clone());
+    let mut state = app.table_state.clone();
+    f.render_stateful_widget(table, area, &mut state);
+    app.table_area.set(area);
+    app.table_offset.set(state.offset());
+}
+
+/// `text` in `style`, with the characters matching the search `query`
+/// underlined in bold yellow
+fn highlight_matches(text: &str, query: Option<&str>, style: Style) -> Vec<Span<'static>> {
+    let positions = query
+        .map(|q| crate::config::match_positions(text, q))
+        .unwrap_or_default();
+    if positions.is_empty() {
+        return vec![Span::styled(text.to_string(), style)];
+    }
+    let highlight = style
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.contains(&i);
+        if matched != run_matched && !run.is_empty() {
+            let run_style = if run_matched { highlight } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), run_style));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    let run_style = if run_matched { highlight } else { style };
+    spans.push(Span::styled(run, run_style));
+    spans
+}
+
+/// "▲"/"▼" after the header of the column the list is sorted by
+fn sort_arrow(app: &App, column: crate::app::SortColumn) -> &'static str {
+    match (app.sort_column == column, app.sort_direction) {
+        (false, _) => "",
+        (true, crate::app::SortDirection::Asc) => "▲",
+        (true, crate::app::SortDirection::Desc) => "▼",
+    }
+}
+
+fn list_title(app: &App) -> String {
+    let mut filter = if app.status_filter == crate::app::StatusFilter::All {
+        String::new()
+    } else {
+        format!("[{}] ", app.status_filter.label())
+    };
+    if app.show_disabled {
+        filter.push_str("[+disabled] ");
+    }
+    if app.favorites_only {
+        filter.push_str("[favorites] ");
+    }
+    if app.sort_column == crate::app::SortColumn::Manual {
+        filter.push_str("[manual order] ");
+    }
+    format!(
+        " VPN Connections {}(↑/↓: select, Enter: toggle, /: search, s: sort, S: status filter, i: import) ",
+        filter
+    )
+}
+
+fn draw_empty_state(f: &mut Frame, app: &App, area: Rect) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(ratatui::widgets::BorderType::Rounded)
+        .title(list_title(app));
+
+    let text = if app.config.profiles.is_empty() {
+        vec![
+            Line::from(Span::styled(
+                "No profiles yet",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            Line::from("Press 'n' to add, 'i' to import, 'I' to auto-import"),
+        ]
+    } else {
+        vec![
+            Line::from(Span::styled(
+                "No profiles match the current search or filter",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            Line::from("Press '/' to refine the search or 'S' to change the status filter"),
+        ]
+    };
+
+    // Vertically center the message inside the block
+    let inner = block.inner(area);
+    let padding = inner.height.saturating_sub(text.len() as u16) / 2;
+    let mut lines = vec![Line::from(""); padding as usize];
+    lines.extend(text);
+
+    let para = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::Gray))
+        .block(block);
+
+    f.render_widget(para, area);
 }
 
 fn draw_logs_panel(f: &mut Frame, app: &App, area: Rect) {
@@ -211,39 +568,97 @@ fn draw_logs_panel(f: &mut Frame, app: &App, area: Rect) {
         .iter()
         .rev()
         .take(area.height as usize - 2)
-        .map(|log| {
-            let style = if log.contains("Error") || log.contains("✗") {
-                Style::default().fg(Color::Red)
-            } else if log.contains("✓") {
-                Style::default().fg(Color::Green)
-            } else {
-                Style::default().fg(Color::Gray)
-            };
-            ListItem::new(log.as_str()).style(style)
-        })
+        .map(|entry| ListItem::new(entry.to_string()).style(log_style(entry.level)))
         .collect();
 
     let logs_list = List::new(logs).block(
         Block::default()
             .borders(Borders::ALL)
             .border_type(ratatui::widgets::BorderType::Rounded)
-            .title(" Logs (l: toggle) "),
+            .title(" Logs (l: toggle, L: all) "),
     );
 
     f.render_widget(logs_list, area);
 }
 
+fn log_style(level: crate::app::LogLevel) -> Style {
+    use crate::app::LogLevel;
+    match level {
+        LogLevel::Error => Style::default().fg(Color::Red),
+        LogLevel::Warning => Style::default().fg(Color::Yellow),
+        LogLevel::Success => Style::default().fg(Color::Green),
+        LogLevel::Info => Style::default().fg(Color::Gray),
+    }
+}
+
+/// Every buffered log line, newest first, narrowed by the level filter and
+/// the `/` search, whose matches are highlighted
+fn draw_logs_screen(f: &mut Frame, app: &App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(f.size());
+
+    let query = app.logs_query.value().trim();
+    let lines = app.visible_logs();
+    let items: Vec<ListItem> = lines
+        .iter()
+        .map(|entry| {
+            let style = log_style(entry.level);
+            let mut spans = vec![Span::styled(
+                format!("[{}] ", entry.timestamp.format("%H:%M:%S")),
+                style,
+            )];
+            spans.extend(highlight_matches(
+                &entry.message,
+                (!query.is_empty()).then_some(query),
+                style,
+            ));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let title = format!(
+        " Logs: {} of {} lines, {} (e: errors, s: successes, a: all, /: search, Esc: close) ",
+        lines.len(),
+        app.logs.len(),
+        app.log_filter.label()
+    );
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(ratatui::widgets::BorderType::Rounded)
+                .title(title),
+        )
+        .highlight_style(Style::default().bg(Color::DarkGray));
+    let mut state = ratatui::widgets::ListState::default().with_selected(Some(app.logs_selected));
+    f.render_stateful_widget(list, chunks[0], &mut state);
+
+    let mut spans = vec![Span::raw("Search: ")];
+    if app.logs_searching {
+        spans.extend(input_spans(&app.logs_query, true));
+    } else {
+        spans.push(Span::raw(app.logs_query.value().to_string()));
+    }
+    let style = if app.logs_searching {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default().fg(Color::Gray)
+    };
+    let search = Paragraph::new(Line::from(spans))
+        .style(style)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(search, chunks[1]);
+}
+
 fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
     let now = chrono::Local::now();
-    let status_text = if let Some((msg, timestamp)) = &app.status_message {
-        let age = now.signed_duration_since(*timestamp).num_seconds();
-        if age < 10 {
-            msg.clone()
-        } else {
-            "Ready".to_string()
+    let (status_text, is_warning) = match &app.status_message {
+        Some((msg, timestamp)) if now.signed_duration_since(*timestamp).num_seconds() < 10 => {
+            (msg.clone(), app.status_is_warning)
         }
-    } else {
-        "Ready".to_string()
+        _ => ("Ready".to_string(), false),
     };
 
     let connected_count = app
@@ -255,12 +670,34 @@ fn draw_status_bar(f: &mut Frame, app: &App, area: Rect) {
 
     let auto_reconnect = if app.auto_reconnect { "ON" } else { "OFF" };
 
+    let filter = app
+        .category_filter_label()
+        .map(|tags| format!(" | Filter: {}", tags))
+        .unwrap_or_default();
     let status_line = format!(
-        " {} | Connected: {}/{} | Auto-Reconnect: {} | s: sort, q: quit, h: help ",
-        status_text, connected_count, total_count, auto_reconnect
+        " | Connected: {}/{} | Auto-Reconnect: {}{} | s: sort, q: quit, h: help ",
+        connected_count, total_count, auto_reconnect, filter
     );
 
-    let status = Paragraph::new(status_line)
+    let mut spans = Vec::new();
+    if app.safe_mode {
+        spans.push(Span::styled(
+            " SAFE MODE ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    let status_style = if is_warning {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    spans.push(Span::styled(format!(" {}", status_text), status_style));
+    spans.push(Span::raw(status_line));
+
+    let status = Paragraph::new(Line::from(spans))
         .style(Style::default().fg(Color::White))
         .block(
             Block::default()
@@ -289,9 +726,16 @@ fn draw_add_profile_screen(f: &mut Frame, app: &App) {
         .split(f.size());
 
     let title_text = if app.screen == Screen::EditProfile {
-        "Edit VPN Profile"
+        let indices = app.get_filtered_profiles_indices();
+        match indices
+            .get(app.selected_profile)
+            .and_then(|&i| app.config.profiles[i].source.as_deref())
+        {
+            Some(source) => format!("Edit VPN Profile (source: {})", source),
+            None => "Edit VPN Profile".to_string(),
+        }
     } else {
-        "Add New VPN Profile"
+        "Add New VPN Profile".to_string()
     };
     let title = Paragraph::new(title_text)
         .style(
@@ -306,7 +750,7 @@ fn draw_add_profile_screen(f: &mut Frame, app: &App) {
     let fields = [
         ("Profile Name", 0),
         ("Gateway Address", 1),
-        ("Category (e.g. dev, uat, prod)", 2),
+        ("Tags, comma-separated (e.g. prod, emea)", 2),
         ("Certificate Path (optional)", 3),
         ("Username (optional)", 4),
         ("Aliases (comma-separated)", 5),
@@ -328,25 +772,152 @@ fn draw_add_profile_screen(f: &mut Frame, app: &App) {
         };
 
         let value = &app.add_profile_data[*field_idx];
-        let cursor = if is_selected { "_" } else { "" };
         let input = if is_edit && is_name_field {
-            format!("{}: {} (static)", label, value)
+            Line::from(format!("{}: {} (static)", label, value.value()))
         } else {
-            format!("{}: {}{}", label, value, cursor)
+            let mut spans = vec![Span::raw(format!("{}: ", label))];
+            spans.extend(input_spans(value, is_selected));
+            Line::from(spans)
         };
 
         let para = Paragraph::new(input)
             .style(style)
-            .block(Block::default().borders(Borders::ALL));
+            .block(form_field_block(app, *field_idx));
 
         f.render_widget(para, chunks[i + 1]);
     }
 
+    // Protocol picker: cycles through the known protocols
+    let is_selected = app.input_field == crate::app::PROTOCOL_FIELD;
+    let mut spans = vec![Span::raw("Protocol: ")];
+    if is_selected {
+        spans.push(Span::raw("◀ "));
+    }
+    spans.push(Span::raw(app.form_protocol.to_string()));
+    if is_selected {
+        spans.push(Span::raw(" ▶"));
+    }
+    if !app.form_protocol.is_known() {
+        spans.push(Span::styled(" (unknown)", Style::default().fg(Color::Red)));
+    }
+    let style = if is_selected {
+        Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+    let para = Paragraph::new(Line::from(spans))
+        .style(style)
+        .block(form_field_block(app, crate::app::PROTOCOL_FIELD));
+    f.render_widget(para, chunks[7]);
+
+    let help = Paragraph::new(
+        "Tab: next field | Shift+Tab: prev field | ←/→: protocol | Ctrl+Z/Ctrl+Y: undo/redo | Enter: save | Esc: cancel",
+    )
+    .style(Style::default().fg(Color::Gray))
+    .alignment(Alignment::Center);
+    f.render_widget(help, chunks[8]);
+}
+
+/// Border of a form input: red, with the problem as its title, when the
+/// last save found the field invalid
+fn form_field_block(app: &App, field_idx: usize) -> Block<'static> {
+    let block = Block::default().borders(Borders::ALL);
+    match app.form_error(field_idx) {
+        Some(error) => block
+            .border_style(Style::default().fg(Color::Red))
+            .title(Span::styled(
+                format!(" {} ", error.message),
+                Style::default().fg(Color::Red),
+            )),
+        None => block,
+    }
+}
+
+fn draw_settings_screen(f: &mut Frame, app: &App) {
+    let Some(form) = &app.settings_form else {
+        return;
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(SettingsField::ALL.len() as u16 + 2),
+            Constraint::Length(1),
+        ])
+        .split(f.size());
+
+    let title = Paragraph::new("Settings")
+        .style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(title, chunks[0]);
+
+    let toggle = |on: bool| if on { "[x]" } else { "[ ]" };
+    let mut items = Vec::new();
+    for (i, field) in SettingsField::ALL.into_iter().enumerate() {
+        let is_selected = form.field == i;
+        let mut spans = vec![Span::raw(format!("{:<42}", field.label()))];
+        match field {
+            SettingsField::ReconnectDelay => {
+                spans.extend(input_spans(&form.reconnect_delay, is_selected))
+            }
+            SettingsField::StatusCheckInterval => {
+                spans.extend(input_spans(&form.status_check_interval, is_selected))
+            }
+            SettingsField::LogLevel => {
+                let level = crate::app::LOG_LEVELS[form.log_level];
+                spans.push(Span::raw(if is_selected {
+                    format!("◀ {} ▶", level)
+                } else {
+                    level.to_string()
+                }));
+            }
+            SettingsField::AutoReconnect => spans.push(Span::raw(toggle(form.auto_reconnect))),
+            SettingsField::MultipleConnections => {
+                spans.push(Span::raw(toggle(form.allow_multiple_connections)))
+            }
+            SettingsField::KillSwitch => spans.push(Span::raw(toggle(form.kill_switch))),
+            SettingsField::KillSwitchDryRun => {
+                spans.push(Span::raw(toggle(form.kill_switch_dry_run)))
+            }
+            SettingsField::BatchKeepGoing => spans.push(Span::raw(toggle(form.batch_keep_going))),
+            SettingsField::ConfirmBeforeConnect => {
+                spans.push(Span::raw(toggle(form.confirm_before_connect)))
+            }
+            SettingsField::CheckDnsLeak => spans.push(Span::raw(toggle(form.check_dns_leak))),
+        }
+        if let Some((error_field, error)) = &form.error
+            && *error_field == field
+        {
+            spans.push(Span::styled(
+                format!("  {}", error),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        let style = if is_selected {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        items.push(ListItem::new(Line::from(spans)).style(style));
+    }
+    let list = List::new(items).block(Block::default().borders(Borders::ALL));
+    f.render_widget(list, chunks[1]);
+
     let help =
-        Paragraph::new("Tab: next field | Shift+Tab: prev field | Enter: save | Esc: cancel")
+        Paragraph::new("Tab/↑/↓: move | Space/←/→: change | Enter: save and apply | Esc: cancel")
             .style(Style::default().fg(Color::Gray))
             .alignment(Alignment::Center);
-    f.render_widget(help, chunks[7]);
+    f.render_widget(help, chunks[2]);
 }
 
 fn draw_edit_profile_screen(f: &mut Frame, app: &App) {
@@ -373,7 +944,7 @@ fn draw_import_xml_screen(f: &mut Frame, app: &App) {
         .alignment(Alignment::Center);
     f.render_widget(title, chunks[0]);
 
-    let input = Paragraph::new(format!("{}_", app.input_buffer))
+    let input = Paragraph::new(Line::from(input_spans(&app.input_buffer, true)))
         .style(Style::default().fg(Color::Yellow))
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(input, chunks[1]);
@@ -435,26 +1006,71 @@ fn draw_file_browser_screen(f: &mut Frame, app: &App) {
     f.render_widget(help, chunks[2]);
 }
 
-fn draw_help_screen(f: &mut Frame) {
-    let help_text = vec![
-        Line::from(vec![Span::styled(
-            "RemiPN - Help",
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )]),
-        Line::from(""),
-        Line::from(vec![Span::styled(
-            "Navigation:",
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        )]),
+fn draw_history_screen(f: &mut Frame, app: &App) {
+    let items: Vec<ListItem> = app
+        .history
+        .iter()
+        .map(|event| {
+            let style = if event.outcome.is_ok() {
+                Style::default().fg(Color::Gray)
+            } else {
+                Style::default().fg(Color::Red)
+            };
+            ListItem::new(format!(
+                "{}  {:<10}  {}  {}",
+                event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                event.action,
+                event.profile,
+                event.outcome
+            ))
+            .style(style)
+        })
+        .collect();
+
+    let title = if items.is_empty() {
+        " Connection history: no events yet (Esc: close) ".to_string()
+    } else {
+        format!(
+            " Connection history: {} events, newest first (Esc: close) ",
+            items.len()
+        )
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(ratatui::widgets::BorderType::Rounded)
+                .title(title),
+        )
+        .highlight_style(Style::default().bg(Color::DarkGray));
+
+    let mut state =
+        ratatui::widgets::ListState::default().with_selected(Some(app.history_selected));
+    f.render_stateful_widget(list, f.size(), &mut state);
+}
+
+fn draw_help_screen(f: &mut Frame) {
+    let help_text = vec![
+        Line::from(vec![Span::styled(
+            "RemiPN - Help",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(""),
+        Line::from(vec![Span::styled(
+            "Navigation:",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )]),
         Line::from("  ↑/k         - Move selection up"),
         Line::from("  ↓/j         - Move selection down"),
         Line::from("  PgUp        - Page up (10 items)"),
         Line::from("  PgDn        - Page down (10 items)"),
-        Line::from("  s           - Cycle sort column/direction"),
+        Line::from("  s           - Cycle sort (name, tags, status, duration, IP)"),
+        Line::from("  S           - Cycle status filter"),
+        Line::from("  A           - Show name or alias first"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "Actions:",
@@ -464,7 +1080,9 @@ fn draw_help_screen(f: &mut Frame) {
         )]),
         Line::from("  Enter/Space - Connect/Disconnect selected VPN"),
         Line::from("  r           - Refresh VPN status"),
-        Line::from("  R           - Toggle auto-reconnect"),
+        Line::from("  Ctrl+R      - Refresh only the selected profile"),
+        Line::from("  P           - Probe all listed gateways (results in logs)"),
+        Line::from("  R           - Toggle auto-reconnect (● tunnels only)"),
         Line::from(""),
         Line::from(vec![Span::styled(
             "Profile Management:",
@@ -474,9 +1092,31 @@ fn draw_help_screen(f: &mut Frame) {
         )]),
         Line::from("  n           - Add new profile"),
         Line::from("  e           - Edit selected profile"),
+        Line::from("  c           - Clone selected profile and edit the copy"),
+        Line::from("  v           - Show profile details (incl. last connected)"),
         Line::from("  a           - Quick alias edit"),
+        Line::from("  g           - Quick gateway edit"),
+        Line::from("  p           - Set password (kept in the OS keychain)"),
+        Line::from("  y           - Copy profile as TOML to clipboard"),
+        Line::from("  O           - Disconnect all other VPNs, keep this one"),
+        Line::from("  K           - Kill switch: block traffic outside this tunnel / remove"),
+        Line::from("  Alt+↑/↓     - Move profile up/down (manual order)"),
+        Line::from("  *           - Toggle favorite (listed first)"),
+        Line::from("  F           - Show favorites only / all profiles"),
+        Line::from("  C / D       - Connect / disconnect every profile of the tag filter"),
+        Line::from(
+            "                (C connects only the first unless multiple connections are on)",
+        ),
+        Line::from("  ,           - Settings"),
+        Line::from("  M           - Run a sequence from the config"),
+        Line::from("  B           - Write a bug-report bundle (path shown in status)"),
+        Line::from("  T           - Show/hide last IP of disconnected profiles"),
+        Line::from("  w           - Watch profile (pin to top, poll every second)"),
+        Line::from("  o           - Pick accent color"),
         Line::from("  x           - Delete selected profile"),
-        Line::from("  /           - Search profiles"),
+        Line::from("  d           - Enable/disable selected profile"),
+        Line::from("  u           - Show/hide disabled profiles"),
+        Line::from("  /           - Search profiles (Up/Down: history)"),
         Line::from("  i           - Import profiles from XML"),
         Line::from("  I           - Auto-import from standard locations"),
         Line::from(""),
@@ -487,8 +1127,22 @@ fn draw_help_screen(f: &mut Frame) {
                 .add_modifier(Modifier::BOLD),
         )]),
         Line::from("  l           - Toggle logs panel"),
+        Line::from("  L           - All logs: scroll, / search, e/s/a errors/successes/all"),
+        Line::from("  H           - Connection history"),
+        Line::from("  f           - Filter by tag"),
+        Line::from("  V           - Cycle log level (error → trace) for this session"),
+        Line::from("  ● / ○       - Connected by remipn / found already running"),
         Line::from("  h/F1        - Show this help"),
         Line::from(""),
+        Line::from(vec![Span::styled(
+            "Connect retries/timeout:",
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from("  CLI flag (--retries, --no-retry, --timeout) > profile's max_retries /"),
+        Line::from("  connect_timeout_seconds > [settings] max_retries / connect_timeout_seconds"),
+        Line::from(""),
         Line::from(vec![Span::styled(
             "Exit:",
             Style::default()
@@ -518,6 +1172,42 @@ fn draw_help_screen(f: &mut Frame) {
     f.render_widget(help_para, area);
 }
 
+/// Render a text input, drawing the cursor at its actual position
+fn input_spans(input: &TextInput, show_cursor: bool) -> Vec<Span<'_>> {
+    if !show_cursor {
+        return vec![Span::raw(input.value())];
+    }
+
+    let (before, after) = input.split_at_cursor();
+    let mut rest = after.chars();
+    let under_cursor = rest
+        .next()
+        .map(|c| c.to_string())
+        .unwrap_or_else(|| " ".to_string());
+    vec![
+        Span::raw(before),
+        Span::styled(
+            under_cursor,
+            Style::default().add_modifier(Modifier::REVERSED),
+        ),
+        Span::raw(rest.as_str()),
+    ]
+}
+
+/// Like `input_spans`, with every character shown as a bullet
+fn masked_input_spans(input: &TextInput) -> Vec<Span<'static>> {
+    let (before, after) = input.split_at_cursor();
+    let after_len = after.chars().count();
+    vec![
+        Span::raw("•".repeat(before.chars().count())),
+        Span::styled(
+            if after_len > 0 { "•" } else { " " },
+            Style::default().add_modifier(Modifier::REVERSED),
+        ),
+        Span::raw("•".repeat(after_len.saturating_sub(1))),
+    ]
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -584,14 +1274,124 @@ fn draw_delete_confirmation(f: &mut Frame, app: &App) {
     f.render_widget(para, area);
 }
 
+fn draw_connect_confirmation(f: &mut Frame, app: &App) {
+    let indices = app.get_filtered_profiles_indices();
+    let (profile_name, gateway) = match indices.get(app.selected_profile) {
+        Some(&idx) => {
+            let profile = &app.config.profiles[idx];
+            (profile.name.clone(), profile.gateway_address.clone())
+        }
+        None => ("None".to_string(), String::new()),
+    };
+
+    let area = centered_rect(50, 25, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Confirm Connect ")
+        .border_style(Style::default().fg(Color::Yellow));
+
+    let key_style = Style::default().add_modifier(Modifier::BOLD);
+    let text = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::raw("Connect to "),
+            Span::styled(profile_name, Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("?"),
+        ]),
+        Line::from(format!("Gateway: {}", gateway)),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("y", key_style.fg(Color::Green)),
+            Span::raw(": Yes, "),
+            Span::styled("n", key_style.fg(Color::Red)),
+            Span::raw(": No, "),
+            Span::styled("a", key_style.fg(Color::Yellow)),
+            Span::raw(": Yes, don't ask again"),
+        ]),
+    ];
+
+    let para = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(block);
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(para, area);
+}
+
+fn draw_kill_switch_confirmation(f: &mut Frame, app: &App) {
+    let indices = app.get_filtered_profiles_indices();
+    let profile_name = if let Some(&idx) = indices.get(app.selected_profile) {
+        app.config.profiles[idx].name.clone()
+    } else {
+        "None".to_string()
+    };
+
+    let area = centered_rect(60, 30, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" Enable Kill Switch ")
+        .border_style(Style::default().fg(Color::Red));
+
+    let text = vec![
+        Line::from(""),
+        Line::from(vec![
+            Span::raw("Block all traffic that doesn't go through "),
+            Span::styled(profile_name, Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw("?"),
+        ]),
+        Line::from("If the tunnel drops, the network stays blocked until it reconnects"),
+        Line::from("or you remove the kill switch with K."),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(
+                "y",
+                Style::default()
+                    .fg(Color::Green)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": Yes, "),
+            Span::styled(
+                "n",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(": No"),
+        ]),
+    ];
+
+    let para = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(block);
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(para, area);
+}
+
 fn draw_search_bar(f: &mut Frame, app: &App) {
     let area = centered_rect(50, 15, f.size());
-    let block = Block::default()
+    let mut block = Block::default()
         .borders(Borders::ALL)
         .title(" Search (Name or Category) ")
         .border_style(Style::default().fg(Color::Yellow));
+    if let Some(pos) = app.search_history_pos {
+        let position = format!(
+            " history {}/{} ",
+            pos + 1,
+            app.config.settings.search_history.len()
+        );
+        block = block.title(
+            ratatui::widgets::block::Title::from(Span::styled(
+                position,
+                Style::default().fg(Color::DarkGray),
+            ))
+            .alignment(Alignment::Right),
+        );
+    }
 
-    let input = Paragraph::new(format!("/{}", app.search_query))
+    let mut spans = vec![Span::raw("/")];
+    spans.extend(input_spans(&app.search_query, true));
+    let input = Paragraph::new(Line::from(spans))
         .block(block)
         .style(Style::default().fg(Color::Yellow));
 
@@ -613,7 +1413,41 @@ fn draw_alias_modal(f: &mut Frame, app: &App) {
         .title(format!(" Alias for {} ", profile_name))
         .border_style(Style::default().fg(Color::Cyan));
 
-    let input = Paragraph::new(app.alias_input.clone())
+    let input = Paragraph::new(Line::from(input_spans(&app.alias_input, true)))
+        .block(block)
+        .style(Style::default().fg(Color::Cyan));
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(input, area);
+
+    // Help text at bottom of modal
+    let help_area = Rect {
+        x: area.x,
+        y: area.y + area.height - 1,
+        width: area.width,
+        height: 1,
+    };
+    let help_text = Paragraph::new(" [Enter] Save  [Esc] Cancel ")
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help_text, help_area);
+}
+
+fn draw_gateway_modal(f: &mut Frame, app: &App) {
+    let indices = app.get_filtered_profiles_indices();
+    let profile_name = if let Some(&idx) = indices.get(app.selected_profile) {
+        app.config.profiles[idx].name.clone()
+    } else {
+        "None".to_string()
+    };
+
+    let area = centered_rect(50, 20, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Gateway for {} ", profile_name))
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let input = Paragraph::new(Line::from(input_spans(&app.gateway_input, true)))
         .block(block)
         .style(Style::default().fg(Color::Cyan));
 
@@ -632,3 +1466,357 @@ fn draw_alias_modal(f: &mut Frame, app: &App) {
         .style(Style::default().fg(Color::DarkGray));
     f.render_widget(help_text, help_area);
 }
+
+fn draw_password_modal(f: &mut Frame, app: &App) {
+    let indices = app.get_filtered_profiles_indices();
+    let profile_name = if let Some(&idx) = indices.get(app.selected_profile) {
+        app.config.profiles[idx].name.clone()
+    } else {
+        "None".to_string()
+    };
+
+    let area = centered_rect(50, 20, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Password for {} ", profile_name))
+        .border_style(Style::default().fg(Color::Cyan));
+
+    let input = Paragraph::new(Line::from(masked_input_spans(&app.password_input)))
+        .block(block)
+        .style(Style::default().fg(Color::Cyan));
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(input, area);
+
+    let help_area = Rect {
+        x: area.x,
+        y: area.y + area.height - 1,
+        width: area.width,
+        height: 1,
+    };
+    let help_text = Paragraph::new(" [Enter] Save (empty: remove)  [Esc] Cancel ")
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help_text, help_area);
+}
+
+fn draw_wizard_modal(f: &mut Frame, app: &App) {
+    use crate::app::WizardStep;
+
+    let Some(wizard) = &app.wizard else {
+        return;
+    };
+
+    let (step, lines, help) = match wizard.step {
+        WizardStep::Explain => (
+            1,
+            vec![
+                Line::from(format!(
+                    "'{}' has no system VPN service yet.",
+                    wizard.profile_name
+                )),
+                Line::from(""),
+                Line::from("The Azure VPN Client registers the service when it"),
+                Line::from("imports the profile's .azvpn/.xml file."),
+            ],
+            " [Enter] Continue  [Esc] Cancel ",
+        ),
+        WizardStep::ChooseFile => (
+            2,
+            vec![
+                Line::from("Profile file to open in the Azure VPN Client:"),
+                Line::from(""),
+                Line::from(input_spans(&wizard.file_input, true)),
+            ],
+            " [Enter] Open  [Tab] Already imported  [Esc] Cancel ",
+        ),
+        WizardStep::Recheck => (
+            3,
+            vec![
+                Line::from("Finish the import in the Azure VPN Client,"),
+                Line::from("keeping the profile name unchanged."),
+            ],
+            " [Enter] Check again  [Esc] Cancel ",
+        ),
+    };
+    let area = centered_rect(60, 40, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" Set up {} ({}/3) ", wizard.profile_name, step))
+        .border_style(Style::default().fg(Color::Magenta));
+
+    let body = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(body, area);
+
+    let help_area = Rect {
+        x: area.x,
+        y: area.y + area.height - 1,
+        width: area.width,
+        height: 1,
+    };
+    let help_text = Paragraph::new(help)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help_text, help_area);
+}
+
+fn draw_sequence_modal(f: &mut Frame, app: &App) {
+    let area = centered_rect(50, 50, f.size());
+    let items: Vec<ListItem> = app
+        .config
+        .sequences
+        .iter()
+        .map(|seq| {
+            let steps: Vec<String> = seq.steps.iter().map(|s| s.to_string()).collect();
+            ListItem::new(vec![
+                Line::from(Span::styled(
+                    seq.name.clone(),
+                    Style::default().add_modifier(Modifier::BOLD),
+                )),
+                Line::from(Span::styled(
+                    format!("  {}", steps.join(", ")),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ])
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Run sequence (Enter: run, Esc: cancel) ")
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    let mut state = ratatui::widgets::ListState::default().with_selected(Some(app.sequence_choice));
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_profile_details(f: &mut Frame, app: &App) {
+    let indices = app.get_filtered_profiles_indices();
+    let Some(profile) = indices
+        .get(app.selected_profile)
+        .map(|&idx| &app.config.profiles[idx])
+    else {
+        return;
+    };
+    let connections = app.get_connections();
+    let conn = connections.iter().find(|c| c.profile_name == profile.name);
+    let status = conn
+        .map(|c| c.status.clone())
+        .unwrap_or(crate::vpn::VpnStatus::Disconnected);
+
+    let field = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(
+                format!("{:<16}", label),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::raw(value),
+        ])
+    };
+    let mut lines = vec![
+        field(
+            "Alias",
+            profile.aliases.clone().unwrap_or_else(|| "-".into()),
+        ),
+        field("Tags", profile.tags_display()),
+        field("Gateway", profile.gateway_display()),
+        field("Protocol", profile.protocol.to_string()),
+        field(
+            "Source",
+            profile.source.clone().unwrap_or_else(|| "-".into()),
+        ),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled(
+                format!("{:<16}", "Status"),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(status.as_str(), Style::default().fg(status.color())),
+        ]),
+        field(
+            "IP",
+            conn.and_then(|c| c.ip_address.clone())
+                .unwrap_or_else(|| "-".into()),
+        ),
+        field(
+            "Connected",
+            conn.and_then(|c| c.connected_since)
+                .map(|t| crate::config::DurationDisplay::Both.format(t))
+                .unwrap_or_else(|| "-".into()),
+        ),
+        field(
+            "Ping",
+            conn.map(|c| c.latency_display())
+                .unwrap_or_else(|| "-".into()),
+        ),
+        field(
+            "Traffic",
+            conn.map(|c| c.traffic_display())
+                .unwrap_or_else(|| "-".into()),
+        ),
+        field("Last connected", profile.last_connected_display()),
+    ];
+
+    let throughput = app
+        .throughput
+        .get(&profile.name)
+        .filter(|t| !t.samples.is_empty());
+    if let Some(throughput) = throughput {
+        let rate = |bytes: u64| format!("{}/s", crate::vpn::format_bytes(bytes));
+        let (current, peak) = (throughput.current(), throughput.peak());
+        lines.push(field(
+            "Rate",
+            format!("{} ↑ / {} ↓", rate(current.sent), rate(current.received)),
+        ));
+        lines.push(field(
+            "Peak",
+            format!("{} ↑ / {} ↓", rate(peak.sent), rate(peak.received)),
+        ));
+    }
+
+    let area = centered_rect(60, if throughput.is_some() { 80 } else { 60 }, f.size());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} (Esc: close) ", profile.name))
+        .border_style(Style::default().fg(Color::Cyan));
+    let inner = block.inner(area);
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_widget(block, area);
+
+    let Some(throughput) = throughput else {
+        f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
+        return;
+    };
+
+    // Send and receive rate history under the fields, newest on the right
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(lines.len() as u16),
+            Constraint::Length(4),
+            Constraint::Length(4),
+        ])
+        .split(inner);
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), chunks[0]);
+
+    for (chunk, label, color, data) in [
+        (
+            chunks[1],
+            "Sent",
+            Color::Magenta,
+            throughput
+                .samples
+                .iter()
+                .map(|s| s.sent)
+                .collect::<Vec<u64>>(),
+        ),
+        (
+            chunks[2],
+            "Received",
+            Color::Green,
+            throughput.samples.iter().map(|s| s.received).collect(),
+        ),
+    ] {
+        // Keep the newest samples when the history is wider than the chart
+        let width = chunk.width as usize;
+        let data = &data[data.len().saturating_sub(width)..];
+        let sparkline = ratatui::widgets::Sparkline::default()
+            .block(
+                Block::default()
+                    .borders(Borders::TOP)
+                    .title(format!(" {} ", label)),
+            )
+            .data(data)
+            .style(Style::default().fg(color));
+        f.render_widget(sparkline, chunk);
+    }
+}
+
+fn draw_category_filter(f: &mut Frame, app: &App) {
+    let area = centered_rect(40, 60, f.size());
+    let items: Vec<ListItem> = app
+        .tag_counts()
+        .into_iter()
+        .map(|(tag, total, connected)| {
+            let checked = if app.category_filter.contains(&tag) {
+                "[x]"
+            } else {
+                "[ ]"
+            };
+            ListItem::new(format!(
+                "{} {} ({}/{} connected)",
+                checked, tag, connected, total
+            ))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Filter by tag (Space: toggle, f: done, Esc: show all) ")
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    let mut state = ratatui::widgets::ListState::default().with_selected(Some(app.filter_choice));
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_color_modal(f: &mut Frame, app: &App) {
+    let indices = app.get_filtered_profiles_indices();
+    let profile_name = if let Some(&idx) = indices.get(app.selected_profile) {
+        app.config.profiles[idx].name.clone()
+    } else {
+        "None".to_string()
+    };
+
+    let area = centered_rect(30, 50, f.size());
+    let items: Vec<ListItem> = crate::app::PROFILE_COLORS
+        .iter()
+        .map(|name| {
+            let style = crate::config::parse_color(name)
+                .map(|c| Style::default().fg(c))
+                .unwrap_or_default();
+            ListItem::new(name.to_string()).style(style)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Color for {} ", profile_name))
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("> ");
+
+    let mut state = ratatui::widgets::ListState::default().with_selected(Some(app.color_choice));
+    f.render_widget(ratatui::widgets::Clear, area);
+    f.render_stateful_widget(list, area, &mut state);
+}
@@ -1,10 +1,24 @@
-use crate::config::VpnProfile;
+use crate::config::{ProfileMatch, Protocol, SequenceStep, VpnProfile};
+use crate::history::{self, ConnectAction, ConnectionEvent, Outcome};
+use crate::secrets::{KeyringStore, SecretStore};
 use anyhow::{Result, anyhow};
-use async_process::Command;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+mod dns;
+mod killswitch;
+#[cfg(target_os = "linux")]
+mod linux;
+mod openconnect;
+mod runner;
+#[cfg(unix)]
+mod wireguard;
+
+#[cfg(test)]
+pub use runner::MockRunner;
+pub use runner::{CommandRunner, RunFuture, SystemRunner};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum VpnStatus {
     Connected,
@@ -12,9 +26,80 @@ pub enum VpnStatus {
     Retrying(u32, u32),
     Disconnected,
     Disconnecting,
+    /// No matching system VPN service is registered for the profile
+    NotConfigured,
+    /// Reported connected by the OS but failing verification
+    Degraded(String),
+    /// The last connect attempt gave up waiting for the system to confirm
+    TimedOut,
+    /// The system tool needed to query the state could not be run
+    Unknown(String),
     Error(String),
 }
 
+/// Commands run around disconnecting a profile
+#[derive(Debug, Clone, Default)]
+struct DisconnectHooks {
+    pre: Option<String>,
+    post: Option<String>,
+}
+
+/// A platform tool (`rasdial`, `nmcli`, ...) could not be spawned because it
+/// is not installed or not on `PATH`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolMissing {
+    pub program: &'static str,
+}
+
+impl std::fmt::Display for ToolMissing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hint = match self.program {
+            "rasdial" => "ensure RAS (Remote Access Service) is installed",
+            "powershell" => "ensure Windows PowerShell is installed",
+            "nmcli" => "install NetworkManager (nmcli)",
+            "scutil" => "it ships with macOS, so check PATH",
+            _ => "ensure it is installed and on PATH",
+        };
+        write!(f, "{} not found; {}", self.program, hint)
+    }
+}
+
+impl std::error::Error for ToolMissing {}
+
+/// `~/.config/remipn/run/`, for state other remipn processes must see
+/// (openconnect pid files, the kill switch marker)
+fn default_run_dir() -> Result<std::path::PathBuf> {
+    Ok(dirs::home_dir()
+        .ok_or_else(|| anyhow!("Could not find home directory"))?
+        .join(".config/remipn/run/"))
+}
+
+/// Map a spawn error of `program`, turning "not found" into [`ToolMissing`]
+fn spawn_error(program: &'static str) -> impl FnOnce(std::io::Error) -> anyhow::Error {
+    move |e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            ToolMissing { program }.into()
+        } else {
+            anyhow!("Failed to run {}: {}", program, e)
+        }
+    }
+}
+
+/// Platform tools each command path depends on, with arguments that make
+/// them exit immediately
+#[cfg(target_os = "windows")]
+const REQUIRED_TOOLS: &[(&str, &[&str])] = &[
+    ("powershell", &["-NoProfile", "-Command", "exit"]),
+    ("rasdial", &[]),
+];
+/// Linux reports its detected backend instead of a fixed tool list
+#[cfg(target_os = "linux")]
+const REQUIRED_TOOLS: &[(&str, &[&str])] = &[];
+#[cfg(target_os = "macos")]
+const REQUIRED_TOOLS: &[(&str, &[&str])] = &[("scutil", &["--nc", "list"])];
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+const REQUIRED_TOOLS: &[(&str, &[&str])] = &[];
+
 impl VpnStatus {
     pub fn as_str(&self) -> String {
         match self {
@@ -23,21 +108,189 @@ impl VpnStatus {
             VpnStatus::Retrying(a, m) => format!("Retry {}/{}...", a, m),
             VpnStatus::Disconnected => "Disconnected".to_string(),
             VpnStatus::Disconnecting => "Disconnecting...".to_string(),
+            VpnStatus::NotConfigured => "Not configured".to_string(),
+            VpnStatus::Degraded(_) => "Degraded".to_string(),
+            VpnStatus::TimedOut => "Timed out".to_string(),
+            VpnStatus::Unknown(_) => "Unknown".to_string(),
             VpnStatus::Error(_) => "Error".to_string(),
         }
     }
 
+    /// Stable lowercase identifier for machine-readable output
+    pub fn key(&self) -> &'static str {
+        match self {
+            VpnStatus::Connected => "connected",
+            VpnStatus::Connecting => "connecting",
+            VpnStatus::Retrying(_, _) => "retrying",
+            VpnStatus::Disconnected => "disconnected",
+            VpnStatus::Disconnecting => "disconnecting",
+            VpnStatus::NotConfigured => "not_configured",
+            VpnStatus::Degraded(_) => "degraded",
+            VpnStatus::TimedOut => "timed_out",
+            VpnStatus::Unknown(_) => "unknown",
+            VpnStatus::Error(_) => "error",
+        }
+    }
+
+    /// A connect attempt that hasn't settled yet
+    pub fn is_in_progress(&self) -> bool {
+        matches!(self, VpnStatus::Connecting | VpnStatus::Retrying(..))
+    }
+
+    /// Reason attached to error-like statuses
+    pub fn detail(&self) -> Option<&str> {
+        match self {
+            VpnStatus::Degraded(reason) | VpnStatus::Unknown(reason) | VpnStatus::Error(reason) => {
+                Some(reason)
+            }
+            _ => None,
+        }
+    }
+
     pub fn color(&self) -> ratatui::style::Color {
         match self {
             VpnStatus::Connected => ratatui::style::Color::Green,
             VpnStatus::Connecting | VpnStatus::Retrying(_, _) => ratatui::style::Color::Yellow,
             VpnStatus::Disconnected => ratatui::style::Color::Gray,
             VpnStatus::Disconnecting => ratatui::style::Color::Yellow,
+            VpnStatus::NotConfigured => ratatui::style::Color::Magenta,
+            VpnStatus::Degraded(_) => ratatui::style::Color::LightRed,
+            VpnStatus::TimedOut => ratatui::style::Color::Rgb(255, 165, 0),
+            VpnStatus::Unknown(_) => ratatui::style::Color::DarkGray,
             VpnStatus::Error(_) => ratatui::style::Color::Red,
         }
     }
 }
 
+/// Default for `Settings::connect_timeout_seconds`
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 90;
+
+/// Default for `Settings::public_ip_endpoint`; answers a plain GET with the
+/// caller's IP as text
+pub const DEFAULT_PUBLIC_IP_ENDPOINT: &str = "https://api.ipify.org";
+
+/// `curl --max-time` of a public-IP lookup, short so a connect isn't held up
+const PUBLIC_IP_TIMEOUT_SECS: &str = "5";
+
+/// Per-profile system queries a status refresh runs at once
+const REFRESH_PARALLELISM: usize = 8;
+
+/// Short id tying together the log lines of one connect/disconnect operation
+pub fn operation_id() -> String {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:04x}", (nanos ^ n.wrapping_mul(0x9e37)) & 0xffff)
+}
+
+/// Remediation steps shown when a profile has no registered system VPN service
+pub fn not_configured_help(profile_name: &str) -> String {
+    format!(
+        "No system VPN service found for '{}'.\n- If this is an Azure profile, import the .azvpn/.xml file into the 'Azure VPN Client' App (e.g.: open -a 'Azure VPN Client' /path/to/profile.azvpn).\n- Alternatively, open Azure VPN Client and create/import a profile with the same name.\n- Then try again from remipn.",
+        profile_name
+    )
+}
+
+/// Attribute active tunnels that no profile claims by name to a profile by
+/// gateway, for systems that report tunnels under the gateway host rather
+/// than the profile name.
+///
+/// Precedence: a name match always wins and such tunnels are never
+/// re-attributed here. An unclaimed tunnel whose name is a profile's gateway
+/// host goes to that profile; when several profiles share the gateway, it
+/// goes to the one remipn connected, then to the one already shown as up.
+/// If that still leaves more than one candidate the tunnel is left
+/// unattributed rather than guessed.
+fn correlate_by_gateway<'a>(
+    profiles: &[VpnProfile],
+    connections: &HashMap<String, VpnConnection>,
+    active: &'a [(String, Option<String>)],
+) -> HashMap<String, &'a (String, Option<String>)> {
+    let mut matched = HashMap::new();
+    for entry in active {
+        if profiles.iter().any(|p| p.name == entry.0) {
+            continue;
+        }
+        let candidates: Vec<&VpnProfile> = profiles
+            .iter()
+            .filter(|p| p.gateway_host().eq_ignore_ascii_case(&entry.0))
+            .filter(|p| !active.iter().any(|(name, _)| name == &p.name))
+            .collect();
+
+        let pick = |pred: &dyn Fn(&VpnConnection) -> bool| {
+            let hits: Vec<&&VpnProfile> = candidates
+                .iter()
+                .filter(|p| connections.get(&p.name).is_some_and(pred))
+                .collect();
+            (hits.len() == 1).then(|| hits[0].name.clone())
+        };
+
+        let chosen = if candidates.len() == 1 {
+            Some(candidates[0].name.clone())
+        } else {
+            pick(&|c| c.owned_by_remipn).or_else(|| {
+                pick(&|c| {
+                    matches!(
+                        c.status,
+                        VpnStatus::Connected
+                            | VpnStatus::Connecting
+                            | VpnStatus::Degraded(_)
+                            | VpnStatus::Retrying(..)
+                    )
+                })
+            })
+        };
+
+        match chosen {
+            Some(name) => {
+                matched.entry(name).or_insert(entry);
+            }
+            None if candidates.len() > 1 => {
+                log::debug!(
+                    "Active tunnel {} matches the gateway of {} profiles; not attributing it",
+                    entry.0,
+                    candidates.len()
+                );
+            }
+            None => {}
+        }
+    }
+    matched
+}
+
+/// Hand a profile file to the Azure VPN Client so it registers the system service
+pub async fn open_in_azure_client(path: &std::path::Path) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        let output = async_process::Command::new("open")
+            .arg("-a")
+            .arg("Azure VPN Client")
+            .arg(path)
+            .output()
+            .await
+            .map_err(spawn_error("open"))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "open failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = path;
+        Err(anyhow!(
+            "Opening profiles in the Azure VPN Client is only supported on macOS"
+        ))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct VpnConnection {
     pub profile_name: String,
@@ -46,26 +299,435 @@ pub struct VpnConnection {
     pub ip_address: Option<String>,
     pub bytes_sent: u64,
     pub bytes_received: u64,
+    /// First IP seen for the current session, used to detect silent changes
+    pub verified_ip: Option<String>,
+    /// Most recent IP, kept after disconnecting until the next connect
+    pub last_ip: Option<String>,
+    /// Brought up by `VpnManager::connect` rather than found already running
+    pub owned_by_remipn: bool,
+    /// When the current Connecting/Retrying phase began
+    pub connecting_since: Option<std::time::Instant>,
+    /// Up/down state from the latest system query, before debouncing
+    pub raw_status: VpnStatus,
+    /// Consecutive refreshes the system has disagreed with `status`
+    pending_refreshes: u32,
+    /// Egress IP the public-IP endpoint saw once the tunnel was stable
+    pub public_ip: Option<String>,
+    /// Round-trip time to the gateway from the latest ping while connected
+    pub latency_ms: Option<u32>,
+    /// When the latest ping was started
+    latency_checked: Option<std::time::Instant>,
+}
+
+/// Byte count in the largest unit that keeps it above 1, e.g. "1.2 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+impl VpnConnection {
+    /// Latency as "23 ms", or "-" when unknown or the gateway didn't answer
+    pub fn latency_display(&self) -> String {
+        self.latency_ms
+            .map_or_else(|| "-".to_string(), |ms| format!("{} ms", ms))
+    }
+
+    /// Session traffic as "1.2 MB ↑ / 4.5 MB ↓", or "-" while none is known
+    pub fn traffic_display(&self) -> String {
+        if self.bytes_sent == 0 && self.bytes_received == 0 {
+            return "-".to_string();
+        }
+        format!(
+            "{} ↑ / {} ↓",
+            format_bytes(self.bytes_sent),
+            format_bytes(self.bytes_received)
+        )
+    }
+
+    pub fn new(profile_name: &str, status: VpnStatus) -> Self {
+        Self {
+            profile_name: profile_name.to_string(),
+            connected_since: None,
+            ip_address: None,
+            bytes_sent: 0,
+            bytes_received: 0,
+            verified_ip: None,
+            last_ip: None,
+            owned_by_remipn: false,
+            connecting_since: status.is_in_progress().then(std::time::Instant::now),
+            raw_status: status.clone(),
+            pending_refreshes: 0,
+            latency_ms: None,
+            latency_checked: None,
+            public_ip: None,
+            status,
+        }
+    }
+
+    /// Whether a refresh observing the tunnel `observed_up` should leave the
+    /// shown status alone, because a settled up/down state only flips once the
+    /// system has reported the opposite for `required` consecutive refreshes
+    fn hold_flap(&mut self, observed_up: bool, required: u32) -> bool {
+        let shown_up = matches!(self.status, VpnStatus::Connected | VpnStatus::Degraded(_));
+        let shown_down = matches!(
+            self.status,
+            VpnStatus::Disconnected | VpnStatus::NotConfigured
+        );
+        if !((shown_up && !observed_up) || (shown_down && observed_up)) {
+            self.pending_refreshes = 0;
+            return false;
+        }
+        self.pending_refreshes += 1;
+        if self.pending_refreshes < required {
+            return true;
+        }
+        self.pending_refreshes = 0;
+        false
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct VpnManager {
     connections: Arc<RwLock<HashMap<String, VpnConnection>>>,
+    /// How long a connect may stay unconfirmed before the refresh gives up on it
+    connect_timeout: std::time::Duration,
+    /// Consecutive refreshes a Connected/Disconnected flip must persist for
+    status_debounce: u32,
+    /// Minimum time between gateway pings of a connected profile
+    latency_interval: std::time::Duration,
+    /// Lines kept in the connection history file
+    history_max_lines: usize,
+    /// Connection history file; `None` for [`history::history_path`]
+    history_file: Option<std::path::PathBuf>,
+    /// wg-quick config files of WireGuard profiles, by profile name
+    wireguard_configs: Arc<RwLock<HashMap<String, String>>>,
+    /// Hook commands of the profiles, by name, since disconnecting only
+    /// gets a name
+    disconnect_hooks: Arc<RwLock<HashMap<String, DisconnectHooks>>>,
+    /// How long a hook command may run
+    hook_timeout: std::time::Duration,
+    /// Whether a failing pre-connect hook stops the connect
+    abort_on_hook_failure: bool,
+    /// Log the kill switch rules instead of installing them
+    kill_switch_dry_run: bool,
+    /// Leave other tunnels up when connecting
+    allow_multiple: bool,
+    /// URL answering with the caller's public IP; None skips the check
+    public_ip_endpoint: Option<String>,
+    /// Set once a missing status tool has been logged, so refreshes don't
+    /// repeat it
+    missing_tool_logged: Arc<std::sync::atomic::AtomicBool>,
+    /// Executes the platform tools
+    runner: Arc<dyn CommandRunner>,
+    /// Where openconnect pid files and the kill switch marker are kept;
+    /// `None` for [`default_run_dir`]
+    run_dir: Option<std::path::PathBuf>,
+    /// Holds the profiles' passwords
+    secrets: Arc<dyn SecretStore>,
+    /// Detected on first use and shared by all clones
+    #[cfg(target_os = "linux")]
+    linux_backend: Arc<tokio::sync::OnceCell<linux::Backend>>,
 }
 
 impl VpnManager {
     pub fn new() -> Self {
         Self {
             connections: Arc::new(RwLock::new(HashMap::new())),
+            connect_timeout: std::time::Duration::from_secs(DEFAULT_CONNECT_TIMEOUT_SECS),
+            status_debounce: 1,
+            latency_interval: std::time::Duration::from_secs(5),
+            history_max_lines: crate::history::DEFAULT_MAX_LINES,
+            history_file: None,
+            wireguard_configs: Arc::new(RwLock::new(HashMap::new())),
+            disconnect_hooks: Arc::new(RwLock::new(HashMap::new())),
+            hook_timeout: std::time::Duration::from_secs(30),
+            abort_on_hook_failure: true,
+            kill_switch_dry_run: false,
+            allow_multiple: false,
+            public_ip_endpoint: Some(DEFAULT_PUBLIC_IP_ENDPOINT.to_string()),
+            missing_tool_logged: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            runner: Arc::new(SystemRunner),
+            run_dir: None,
+            secrets: Arc::new(KeyringStore),
+            #[cfg(target_os = "linux")]
+            linux_backend: Arc::new(tokio::sync::OnceCell::new()),
         }
     }
 
-    /// Connect to an Azure VPN using the profile configuration
-    pub async fn connect(&self, profile: &VpnProfile) -> Result<()> {
-        // Disconnect all other VPNs first (Single connection requirement)
+    pub fn with_connect_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Require a status flip to be seen on `refreshes` consecutive refreshes
+    /// before showing it; 1 shows every change immediately
+    pub fn with_status_debounce(mut self, refreshes: u32) -> Self {
+        self.status_debounce = refreshes.max(1);
+        self
+    }
+
+    /// Ping connected gateways at most once per `interval`
+    pub fn with_latency_interval(mut self, interval: std::time::Duration) -> Self {
+        self.latency_interval = interval;
+        self
+    }
+
+    /// Run platform tools through `runner` instead of spawning them directly
+    pub fn with_runner(mut self, runner: impl CommandRunner + 'static) -> Self {
+        self.runner = Arc::new(runner);
+        self
+    }
+
+    /// Record the connection history in `path` instead of
+    /// `~/.config/remipn/history.jsonl`
+    pub fn with_history_file(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.history_file = Some(path.into());
+        self
+    }
+
+    /// Keep openconnect pid files and the kill switch marker in `dir`
+    /// instead of `~/.config/remipn/run/`
+    pub fn with_run_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.run_dir = Some(dir.into());
+        self
+    }
+
+    /// The run dir, created on first use
+    fn run_dir(&self) -> Result<std::path::PathBuf> {
+        let dir = match &self.run_dir {
+            Some(dir) => dir.clone(),
+            None => default_run_dir()?,
+        };
+        if !dir.exists() {
+            std::fs::create_dir_all(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// When a tunnel found up by a status refresh came up. openconnect's pid
+    /// file is written as it connects; other tools don't say, so `recorded`
+    /// (the profile's last recorded connect) or else now is used.
+    fn found_up_since(
+        &self,
+        profile_name: &str,
+        recorded: Option<chrono::DateTime<chrono::Local>>,
+    ) -> chrono::DateTime<chrono::Local> {
+        self.run_dir()
+            .ok()
+            .and_then(|dir| openconnect::started_at(&dir, profile_name))
+            .or(recorded)
+            .unwrap_or_else(chrono::Local::now)
+    }
+
+    /// Keep profile passwords in `store` instead of the OS credential store
+    pub fn with_secret_store(mut self, store: impl SecretStore + 'static) -> Self {
+        self.secrets = Arc::new(store);
+        self
+    }
+
+    /// Keep at most this many events in the connection history (0: no limit)
+    pub fn with_history_limit(mut self, max_lines: usize) -> Self {
+        self.history_max_lines = max_lines;
+        self
+    }
+
+    /// Give profile hooks `timeout` to finish; with `abort_on_failure` a
+    /// failing pre-connect hook fails the connect instead of being logged
+    pub fn with_hook_policy(
+        mut self,
+        timeout: std::time::Duration,
+        abort_on_failure: bool,
+    ) -> Self {
+        self.hook_timeout = timeout;
+        self.abort_on_hook_failure = abort_on_failure;
+        self
+    }
+
+    /// Let several tunnels be up at once instead of disconnecting the others
+    /// on connect, e.g. split tunnels to two gateways
+    pub fn with_multiple_connections(mut self, allow: bool) -> Self {
+        self.allow_multiple = allow;
+        self
+    }
+
+    pub fn allows_multiple_connections(&self) -> bool {
+        self.allow_multiple
+    }
+
+    /// Only log the firewall rules the kill switch would install
+    pub fn with_kill_switch_dry_run(mut self, dry_run: bool) -> Self {
+        self.kill_switch_dry_run = dry_run;
+        self
+    }
+
+    /// Block all traffic that doesn't go through `profile`'s tunnel (or to
+    /// its gateway, so it can reconnect). Replaces rules installed for
+    /// another profile; they stay until [`Self::disable_kill_switch`].
+    pub async fn enable_kill_switch(&self, profile: &VpnProfile) -> Result<()> {
+        let interface = self
+            .tunnel_interface(&profile.name)
+            .await
+            .ok_or_else(|| anyhow!("Can't find the network interface of {}", profile.name))?;
+        killswitch::enable(
+            self.runner.as_ref(),
+            &self.run_dir()?,
+            &profile.name,
+            &interface,
+            &profile.gateway_host(),
+            self.kill_switch_dry_run,
+        )
+        .await
+    }
+
+    /// Remove the kill switch rules, letting traffic flow normally again
+    pub async fn disable_kill_switch(&self) -> Result<()> {
+        killswitch::disable(self.runner.as_ref(), &self.run_dir()?).await
+    }
+
+    /// Profile the kill switch rules currently protect, as recorded by
+    /// whichever remipn process installed them
+    pub fn kill_switch_profile(&self) -> Option<String> {
+        killswitch::active_profile(&self.run_dir().ok()?)
+    }
+
+    /// Check that DNS queries and internet traffic of a connected profile go
+    /// through its tunnel, returning a warning for each that doesn't. Empty
+    /// when everything is routed through the tunnel or it can't be told.
+    pub async fn check_dns_leak(&self, profile_name: &str) -> Vec<String> {
+        let Some(interface) = self.tunnel_interface(profile_name).await else {
+            log::debug!(
+                "DNS leak check skipped: no interface found for {}",
+                profile_name
+            );
+            return Vec::new();
+        };
+        dns::leak_warnings(self.runner.as_ref(), &interface).await
+    }
+
+    /// Interface the tunnel of a connected profile runs over
+    async fn tunnel_interface(&self, profile_name: &str) -> Option<String> {
+        #[cfg(unix)]
+        if let Some(config) = self.wireguard_configs.read().await.get(profile_name) {
+            return Some(wireguard::interface(config));
+        }
+
+        #[cfg(target_os = "linux")]
+        return linux::interface_for(self.runner.as_ref(), profile_name).await;
+
+        // scutil doesn't name the interface; find the utun holding the
+        // tunnel's address
+        #[cfg(target_os = "macos")]
+        {
+            let ip = self
+                .connections
+                .read()
+                .await
+                .get(profile_name)
+                .and_then(|c| c.ip_address.clone())?;
+            let output = self.runner.run("ifconfig", &[]).await.ok()?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let mut current_interface = None;
+            for line in stdout.lines() {
+                if !line.starts_with('\t') {
+                    current_interface = line.split(':').next();
+                } else if line.split_whitespace().nth(1) == Some(ip.as_str()) {
+                    return current_interface.map(str::to_string);
+                }
+            }
+            None
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        {
+            let _ = profile_name;
+            None
+        }
+    }
+
+    /// Look up the public IP at `endpoint`, or never when None
+    pub fn with_public_ip_endpoint(mut self, endpoint: Option<String>) -> Self {
+        self.public_ip_endpoint = endpoint;
+        self
+    }
+
+    /// The IP traffic leaves through, as seen by the public-IP endpoint.
+    /// None when the check is disabled, `curl` is missing, the endpoint
+    /// doesn't answer within a few seconds or answers with something that
+    /// isn't an IP address.
+    pub async fn detect_public_ip(&self) -> Option<String> {
+        let endpoint = self.public_ip_endpoint.as_deref()?;
+        let output = self
+            .runner
+            .run(
+                "curl",
+                &["-fsS", "--max-time", PUBLIC_IP_TIMEOUT_SECS, endpoint],
+            )
+            .await
+            .inspect_err(|e| log::debug!("Could not run curl for the public IP: {}", e))
+            .ok()?;
+        if !output.status.success() {
+            log::debug!(
+                "Public IP lookup at {} failed: {}",
+                endpoint,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return None;
+        }
+        let ip = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        ip.parse::<std::net::IpAddr>().is_ok().then_some(ip)
+    }
+
+    /// Detect the public IP and keep it on the profile's connection
+    pub async fn refresh_public_ip(&self, profile_name: &str) -> Option<String> {
+        let ip = self.detect_public_ip().await;
+        if let Some(conn) = self.connections.write().await.get_mut(profile_name) {
+            conn.public_ip = ip.clone();
+        }
+        ip
+    }
+
+    /// Append a connect/disconnect/drop to the connection history, logging
+    /// rather than failing when the file can't be written
+    pub fn record_history(&self, profile_name: &str, action: ConnectAction, outcome: Outcome) {
+        let event = ConnectionEvent::new(profile_name, action, outcome);
+        let path = match &self.history_file {
+            Some(path) => Ok(path.clone()),
+            None => history::history_path(),
+        };
+        if let Err(e) = path.and_then(|path| history::append(&path, &event, self.history_max_lines))
+        {
+            log::warn!("Could not write connection history: {}", e);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn linux_backend(&self) -> linux::Backend {
+        *self
+            .linux_backend
+            .get_or_init(|| async {
+                let backend = linux::Backend::detect(self.runner.as_ref()).await;
+                log::info!("Using {} VPN backend", backend.label());
+                backend
+            })
+            .await
+    }
+
+    /// Disconnect every active VPN except `keep`, waiting for each to go
+    /// down. Returns the names of the tunnels closed.
+    pub async fn disconnect_others(&self, keep: &str) -> Result<Vec<String>> {
         let active_vpns = self.get_active_vpns().await?;
+        let mut closed = Vec::new();
         for (name, _) in active_vpns {
-            if name != profile.name {
+            if name != keep {
                 let _ = self.disconnect(&name).await;
 
                 // Wait for it to be effectively disconnected
@@ -75,7 +737,10 @@ impl VpnManager {
 
                 for _ in 0..40 {
                     let sys_status = self.get_system_status(&name).await;
-                    if matches!(sys_status, VpnStatus::Disconnected) {
+                    if matches!(
+                        sys_status,
+                        VpnStatus::Disconnected | VpnStatus::NotConfigured
+                    ) {
                         disconnected = true;
                         break;
                     }
@@ -83,31 +748,81 @@ impl VpnManager {
                 }
                 if !disconnected {
                     return Err(anyhow!(
-                        "Failed to disconnect previous VPN: {}. Current state still not Disconnected.",
+                        "Failed to disconnect other VPN: {}. Current state still not Disconnected.",
                         name
                     ));
                 }
+                closed.push(name);
+            }
+        }
+        Ok(closed)
+    }
+
+    /// Close tunnels that came up next to `keep` while it was connecting,
+    /// unless several may be up at once. Returns the names of the tunnels
+    /// closed; unlike [`Self::disconnect_others`] it doesn't wait for them.
+    pub async fn enforce_exclusive(&self, keep: &str) -> Vec<String> {
+        if self.allow_multiple {
+            return Vec::new();
+        }
+        let Ok(active) = self.get_active_vpns().await else {
+            return Vec::new();
+        };
+        let mut closed = Vec::new();
+        for (name, _) in active {
+            if name != keep {
+                let _ = self.disconnect(&name).await;
+                closed.push(name);
             }
         }
+        closed
+    }
+
+    /// Connect to an Azure VPN using the profile configuration
+    pub async fn connect(&self, profile: &VpnProfile) -> Result<()> {
+        // Disconnect all other VPNs first, unless several may be up at once
+        if !self.allow_multiple {
+            self.disconnect_others(&profile.name).await?;
+        }
 
         let mut connections = self.connections.write().await;
 
         // Update status to connecting
         connections.insert(
             profile.name.clone(),
-            VpnConnection {
-                profile_name: profile.name.clone(),
-                status: VpnStatus::Connecting,
-                connected_since: None,
-                ip_address: None,
-                bytes_sent: 0,
-                bytes_received: 0,
-            },
+            VpnConnection::new(&profile.name, VpnStatus::Connecting),
         );
         drop(connections);
 
+        let run_hooks = profile.hooks_allowed();
+        if profile.has_hooks() && !run_hooks {
+            log::warn!(
+                "Skipping the hooks of imported profile {}; set trust_hooks = true to run them",
+                profile.name
+            );
+        }
+        self.track_hooks(std::slice::from_ref(profile)).await;
+
+        let mut result = Ok(());
+        if run_hooks && let Some(cmd) = &profile.pre_connect_cmd {
+            result = self.run_hook(&profile.name, "pre-connect", cmd).await;
+            if let Err(e) = &result
+                && !self.abort_on_hook_failure
+            {
+                log::warn!("{}", e);
+                result = Ok(());
+            }
+        }
+
         // Execute Azure VPN connection command
-        let result = self.execute_vpn_connect(profile).await;
+        if result.is_ok() {
+            result = self.execute_vpn_connect(profile).await;
+        }
+        self.record_history(
+            &profile.name,
+            ConnectAction::Connect,
+            Outcome::from_result(&result),
+        );
 
         let mut connections = self.connections.write().await;
         match result {
@@ -115,6 +830,8 @@ impl VpnManager {
                 if let Some(conn) = connections.get_mut(&profile.name) {
                     conn.status = VpnStatus::Connected;
                     conn.connected_since = Some(chrono::Local::now());
+                    conn.connecting_since = None;
+                    conn.owned_by_remipn = true;
                 }
             }
             Err(e) => {
@@ -124,10 +841,35 @@ impl VpnManager {
                 return Err(e);
             }
         }
+        drop(connections);
+
+        if run_hooks
+            && let Some(cmd) = &profile.post_connect_cmd
+            && let Err(e) = self.run_hook(&profile.name, "post-connect", cmd).await
+        {
+            log::warn!("{}", e);
+        }
 
         Ok(())
     }
 
+    /// Run one step of a sequence, resolving profile names against `profiles`
+    pub async fn run_step(&self, profiles: &[VpnProfile], step: &SequenceStep) -> Result<()> {
+        let resolve = |key: &str| match crate::config::match_profile(profiles, key) {
+            ProfileMatch::Found(p) => Ok(p.clone()),
+            ProfileMatch::Ambiguous(_) => Err(anyhow!("'{}' matches several profiles", key)),
+            ProfileMatch::NotFound => Err(anyhow!("Profile '{}' not found", key)),
+        };
+        match step {
+            SequenceStep::Connect(key) => self.connect(&resolve(key)?).await,
+            SequenceStep::Disconnect(key) => self.disconnect(&resolve(key)?.name).await,
+            SequenceStep::Wait(secs) => {
+                tokio::time::sleep(std::time::Duration::from_secs(*secs)).await;
+                Ok(())
+            }
+        }
+    }
+
     /// Disconnect from a VPN
     pub async fn disconnect(&self, profile_name: &str) -> Result<()> {
         let mut connections = self.connections.write().await;
@@ -137,8 +879,27 @@ impl VpnManager {
         }
         drop(connections);
 
+        // Hook failures never keep a tunnel up
+        let hooks = self
+            .disconnect_hooks
+            .read()
+            .await
+            .get(profile_name)
+            .cloned()
+            .unwrap_or_default();
+        if let Some(cmd) = &hooks.pre
+            && let Err(e) = self.run_hook(profile_name, "pre-disconnect", cmd).await
+        {
+            log::warn!("{}", e);
+        }
+
         // Execute disconnect command
         let result = self.execute_vpn_disconnect(profile_name).await;
+        self.record_history(
+            profile_name,
+            ConnectAction::Disconnect,
+            Outcome::from_result(&result),
+        );
 
         let mut connections = self.connections.write().await;
         match result {
@@ -147,6 +908,9 @@ impl VpnManager {
                     conn.status = VpnStatus::Disconnected;
                     conn.connected_since = None;
                     conn.ip_address = None;
+                    conn.verified_ip = None;
+                    conn.public_ip = None;
+                    conn.owned_by_remipn = false;
                 }
             }
             Err(e) => {
@@ -156,7 +920,54 @@ impl VpnManager {
                 return Err(e);
             }
         }
+        drop(connections);
+
+        if let Some(cmd) = &hooks.post
+            && let Err(e) = self.run_hook(profile_name, "post-disconnect", cmd).await
+        {
+            log::warn!("{}", e);
+        }
+
+        Ok(())
+    }
 
+    /// Run a profile hook through the shell, logging what it prints. Fails
+    /// when the command can't be started, exits non-zero or outlives the
+    /// hook timeout.
+    async fn run_hook(&self, profile_name: &str, stage: &str, command: &str) -> Result<()> {
+        #[cfg(windows)]
+        let (shell, flag) = ("cmd", "/C");
+        #[cfg(not(windows))]
+        let (shell, flag) = ("sh", "-c");
+
+        log::info!("Running {} hook of {}: {}", stage, profile_name, command);
+        let output =
+            tokio::time::timeout(self.hook_timeout, self.runner.run(shell, &[flag, command]))
+                .await
+                .map_err(|_| {
+                    anyhow!(
+                        "{} hook of {} timed out after {}s",
+                        stage,
+                        profile_name,
+                        self.hook_timeout.as_secs()
+                    )
+                })?
+                .map_err(spawn_error(shell))?;
+
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            log::info!("[{} {}] {}", profile_name, stage, line);
+        }
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            log::warn!("[{} {}] {}", profile_name, stage, line);
+        }
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{} hook of {} failed ({})",
+                stage,
+                profile_name,
+                output.status
+            ));
+        }
         Ok(())
     }
 
@@ -169,20 +980,80 @@ impl VpnManager {
             .unwrap_or(VpnStatus::Disconnected)
     }
 
+    /// Remember the disconnect hooks of `profiles` that may run them.
+    /// Refreshing does this too; call it before disconnecting without one.
+    pub async fn track_hooks(&self, profiles: &[VpnProfile]) {
+        let mut hooks = self.disconnect_hooks.write().await;
+        for p in profiles {
+            if p.hooks_allowed()
+                && (p.pre_disconnect_cmd.is_some() || p.post_disconnect_cmd.is_some())
+            {
+                hooks.insert(
+                    p.name.clone(),
+                    DisconnectHooks {
+                        pre: p.pre_disconnect_cmd.clone(),
+                        post: p.post_disconnect_cmd.clone(),
+                    },
+                );
+            } else {
+                hooks.remove(&p.name);
+            }
+        }
+    }
+
+    /// Remember which WireGuard profiles are driven by wg-quick, so they can
+    /// be found by name when disconnecting or checking status
+    async fn track_wireguard(&self, profiles: &[VpnProfile]) {
+        #[cfg(unix)]
+        {
+            let configs = profiles
+                .iter()
+                .filter_map(|p| Some((p.name.clone(), wireguard::config_file(p)?)))
+                .collect();
+            *self.wireguard_configs.write().await = configs;
+        }
+        #[cfg(not(unix))]
+        let _ = profiles;
+    }
+
     /// Get the actual system status of a VPN connection
     pub async fn get_system_status(&self, profile_name: &str) -> VpnStatus {
+        if let Ok(dir) = self.run_dir() {
+            match openconnect::is_running(self.runner.as_ref(), &dir, profile_name).await {
+                Some(true) => return VpnStatus::Connected,
+                Some(false) => return VpnStatus::Disconnected,
+                None => {}
+            }
+        }
+
+        #[cfg(unix)]
+        if let Some(config) = self.wireguard_configs.read().await.get(profile_name) {
+            return wireguard::status(self.runner.as_ref(), &wireguard::interface(config)).await;
+        }
+
         #[cfg(target_os = "macos")]
         {
-            if let Ok(output) = Command::new("scutil")
-                .arg("--nc")
-                .arg("status")
-                .arg(profile_name)
-                .output()
-                .await
+            let result = self
+                .runner
+                .run("scutil", &["--nc", "status", profile_name])
+                .await;
+            if let Err(e) = &result
+                && e.kind() == std::io::ErrorKind::NotFound
             {
+                return VpnStatus::Unknown(ToolMissing { program: "scutil" }.to_string());
+            }
+            if let Ok(output) = result {
                 let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
                 let first_line = stdout.lines().next().unwrap_or("");
-                if first_line.contains("Connected") && !first_line.contains("Disconnected") {
+                // scutil reports unknown service names as "No service" or "Invalid"
+                if stdout.contains("No service")
+                    || stderr.contains("No service")
+                    || stderr.contains("No such service")
+                    || first_line.starts_with("Invalid")
+                {
+                    return VpnStatus::NotConfigured;
+                } else if first_line.contains("Connected") && !first_line.contains("Disconnected") {
                     return VpnStatus::Connected;
                 } else if first_line.contains("Connecting") {
                     return VpnStatus::Connecting;
@@ -194,86 +1065,132 @@ impl VpnManager {
             }
         }
 
-        #[cfg(target_os = "linux")]
+        #[cfg(target_os = "windows")]
         {
-            if let Ok(output) = Command::new("nmcli")
-                .arg("-t")
-                .arg("-f")
-                .arg("NAME,STATE")
-                .arg("connection")
-                .arg("show")
-                .arg("--active")
-                .output()
-                .await
+            if let Ok(active) = self.get_active_vpns().await
+                && active.iter().any(|(name, _)| name == profile_name)
             {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                for line in stdout.lines() {
-                    let parts: Vec<&str> = line.split(':').collect();
-                    if parts.len() >= 2 && parts[0] == profile_name {
-                        let state = parts[1].to_lowercase();
-                        if state.contains("activated") && !state.contains("deactivated") {
-                            return VpnStatus::Connected;
-                        } else if state.contains("activating") {
-                            return VpnStatus::Connecting;
-                        } else if state.contains("deactivating") {
-                            return VpnStatus::Disconnecting;
-                        }
-                    }
-                }
+                return VpnStatus::Connected;
             }
         }
 
+        #[cfg(target_os = "linux")]
+        return self
+            .linux_backend()
+            .await
+            .status(self.runner.as_ref(), profile_name)
+            .await;
+
+        #[cfg(not(target_os = "linux"))]
         VpnStatus::Disconnected
     }
 
     pub async fn set_status(&self, profile_name: &str, status: VpnStatus) {
         let mut connections = self.connections.write().await;
         if let Some(conn) = connections.get_mut(profile_name) {
+            if !status.is_in_progress() {
+                conn.connecting_since = None;
+            } else if !conn.status.is_in_progress() {
+                conn.connecting_since = Some(std::time::Instant::now());
+            }
             conn.status = status;
         } else {
             connections.insert(
                 profile_name.to_string(),
-                VpnConnection {
-                    profile_name: profile_name.to_string(),
-                    status,
-                    connected_since: None,
-                    ip_address: None,
-                    bytes_sent: 0,
-                    bytes_received: 0,
-                },
+                VpnConnection::new(profile_name, status),
             );
         }
     }
 
     /// Refresh status for all connections
     pub async fn refresh_all_status(&self, profiles: &[VpnProfile]) -> Result<()> {
+        self.track_wireguard(profiles).await;
+        self.track_hooks(profiles).await;
+        let wireguard = self.wireguard_configs.read().await.clone();
+
         // Query system for actual VPN status
-        let active_vpns = self.get_active_vpns().await?;
+        let (active_vpns, registered) =
+            tokio::join!(self.get_active_vpns(), self.get_registered_services());
+        // openconnect and wg-quick profiles don't need a system VPN service
+        let registered = registered.map(|mut services| {
+            services.extend(
+                profiles
+                    .iter()
+                    .filter(|p| {
+                        p.protocol == Protocol::AnyConnect || wireguard.contains_key(&p.name)
+                    })
+                    .map(|p| p.name.clone()),
+            );
+            services
+        });
 
         let mut connections = self.connections.write().await;
 
-        // Ensure all profiles are in the map
+        // Drop entries left behind by deleted or renamed profiles
+        connections.retain(|name, _| profiles.iter().any(|p| &p.name == name));
+
+        // Ensure all profiles are in the map. Entries created now have no
+        // in-memory history, e.g. because remipn just started.
+        let mut fresh = std::collections::HashSet::new();
         for p in profiles {
             if !connections.contains_key(&p.name) {
+                fresh.insert(p.name.clone());
                 connections.insert(
                     p.name.clone(),
-                    VpnConnection {
-                        profile_name: p.name.clone(),
-                        status: VpnStatus::Disconnected,
-                        connected_since: None,
-                        ip_address: None,
-                        bytes_sent: 0,
-                        bytes_received: 0,
-                    },
+                    VpnConnection::new(&p.name, VpnStatus::Disconnected),
                 );
             }
         }
 
+        // Without the status tool the real state can't be known; say so
+        // instead of reporting every profile as disconnected
+        let active_vpns = match active_vpns {
+            Ok(active) => active,
+            Err(e) => match e.downcast_ref::<ToolMissing>() {
+                Some(missing) => {
+                    if !self
+                        .missing_tool_logged
+                        .swap(true, std::sync::atomic::Ordering::Relaxed)
+                    {
+                        log::warn!("Can't read VPN status: {}", missing);
+                    }
+                    for conn in connections.values_mut() {
+                        conn.status = VpnStatus::Unknown(missing.to_string());
+                    }
+                    return Ok(());
+                }
+                None => return Err(e),
+            },
+        };
+
+        let by_gateway = correlate_by_gateway(profiles, &connections, &active_vpns);
+
+        let mut connected = Vec::new();
         for (_, conn) in connections.iter_mut() {
-            if let Some(active_info) = active_vpns
+            let active_info = active_vpns
                 .iter()
                 .find(|(name, _)| name == &conn.profile_name)
-            {
+                .or_else(|| by_gateway.get(&conn.profile_name).copied());
+            let is_registered = registered
+                .as_ref()
+                .is_none_or(|services| services.contains(&conn.profile_name));
+
+            conn.raw_status = match (active_info, is_registered) {
+                (Some(_), _) => VpnStatus::Connected,
+                (None, true) => VpnStatus::Disconnected,
+                (None, false) => VpnStatus::NotConfigured,
+            };
+            if conn.hold_flap(active_info.is_some(), self.status_debounce) {
+                log::debug!(
+                    "Holding {} as {} while the system reports {}",
+                    conn.profile_name,
+                    conn.status.as_str(),
+                    conn.raw_status.as_str()
+                );
+                continue;
+            }
+
+            if let Some(active_info) = active_info {
                 log::debug!(
                     "{} {} {} {}",
                     conn.status.as_str(),
@@ -281,19 +1198,475 @@ impl VpnManager {
                     active_info.0,
                     active_info.1.as_deref().unwrap_or("")
                 );
-                if !matches!(conn.status, VpnStatus::Connected) {
+                // A degraded tunnel stays degraded until it is cycled or
+                // passes verification again
+                if !matches!(conn.status, VpnStatus::Connected | VpnStatus::Degraded(_)) {
+                    // A tunnel that was already up when remipn started most
+                    // likely came up at the profile's recorded last connect
+                    let recorded = fresh
+                        .contains(&conn.profile_name)
+                        .then(|| {
+                            profiles
+                                .iter()
+                                .find(|p| p.name == conn.profile_name)
+                                .and_then(|p| p.last_connected)
+                        })
+                        .flatten();
                     conn.status = VpnStatus::Connected;
-                    conn.connected_since = Some(chrono::Local::now());
+                    conn.connected_since
+                        .get_or_insert_with(|| self.found_up_since(&conn.profile_name, recorded));
+                    conn.connecting_since = None;
                 }
                 conn.ip_address = active_info.1.clone();
+                if conn.ip_address.is_some() {
+                    conn.last_ip = conn.ip_address.clone();
+                }
+                if conn.verified_ip.is_none() {
+                    conn.verified_ip = conn.ip_address.clone();
+                }
+                connected.push(conn.profile_name.clone());
+            } else if conn.status == VpnStatus::TimedOut {
+                // Keep the failed attempt visible until a manual refresh or reconnect
+                continue;
+            } else if conn.status.is_in_progress() {
+                // Give an attempt time to come up, but don't let one whose task
+                // died (or whose app was killed) show Connecting forever
+                let started = *conn
+                    .connecting_since
+                    .get_or_insert_with(std::time::Instant::now);
+                if started.elapsed() >= self.connect_timeout {
+                    log::warn!(
+                        "{} was {} for over {}s without the system confirming it; marking timed out",
+                        conn.profile_name,
+                        conn.status.as_str(),
+                        self.connect_timeout.as_secs()
+                    );
+                    conn.status = VpnStatus::TimedOut;
+                    conn.connecting_since = None;
+                }
             } else {
-                conn.status = VpnStatus::Disconnected;
+                conn.status = if is_registered {
+                    VpnStatus::Disconnected
+                } else {
+                    VpnStatus::NotConfigured
+                };
                 conn.connected_since = None;
                 conn.ip_address = None;
+                conn.verified_ip = None;
+                conn.public_ip = None;
+                conn.bytes_sent = 0;
+                conn.bytes_received = 0;
+                conn.latency_ms = None;
+                conn.latency_checked = None;
+                conn.owned_by_remipn = false;
             }
         }
 
-        Ok(())
+        // Ping in the background so an unresponsive gateway doesn't hold up
+        // the refresh; the result lands in the map when it arrives
+        for name in &connected {
+            let Some(conn) = connections.get_mut(name) else {
+                continue;
+            };
+            if conn
+                .latency_checked
+                .is_some_and(|t| t.elapsed() < self.latency_interval)
+            {
+                continue;
+            }
+            let Some(profile) = profiles.iter().find(|p| &p.name == name).cloned() else {
+                continue;
+            };
+            conn.latency_checked = Some(std::time::Instant::now());
+            let mgr = self.clone();
+            tokio::spawn(async move {
+                let latency = mgr.measure_latency(&profile).await;
+                mgr.store_latency(&profile.name, latency).await;
+            });
+        }
+        let targets: Vec<(String, Option<String>)> = connected
+            .into_iter()
+            .map(|name| {
+                let ip = connections.get(&name).and_then(|c| c.ip_address.clone());
+                (name, ip)
+            })
+            .collect();
+        drop(connections);
+
+        // Interface lookups can each spawn a tool; run them side by side so
+        // the refresh doesn't grow with the number of connected profiles
+        let traffic = self.gather_traffic(targets).await;
+        let mut connections = self.connections.write().await;
+        for (name, (sent, received)) in traffic {
+            if let Some(conn) = connections.get_mut(&name) {
+                conn.bytes_sent = sent;
+                conn.bytes_received = received;
+            }
+        }
+        Ok(())
+    }
+
+    /// Session traffic of each `(profile, ip)`, read at most
+    /// [`REFRESH_PARALLELISM`] at a time. Profiles whose counters can't be
+    /// read are left out.
+    async fn gather_traffic(
+        &self,
+        targets: Vec<(String, Option<String>)>,
+    ) -> Vec<(String, (u64, u64))> {
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(REFRESH_PARALLELISM));
+        let mut tasks = tokio::task::JoinSet::new();
+        for (name, ip) in targets {
+            let mgr = self.clone();
+            let semaphore = semaphore.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let traffic = mgr.interface_traffic(&name, ip.as_deref()).await;
+                traffic.map(|traffic| (name, traffic))
+            });
+        }
+
+        let mut results = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            if let Ok(Some(traffic)) = result {
+                results.push(traffic);
+            }
+        }
+        results
+    }
+
+    /// Round-trip time to the profile's gateway in ms, from a single probe
+    pub async fn measure_latency(&self, profile: &VpnProfile) -> Option<u32> {
+        self.probe_gateway(profile).await
+    }
+
+    /// Ping every connected profile's gateway now and wait for the results,
+    /// for one-shot CLI output
+    pub async fn refresh_latency(&self, profiles: &[VpnProfile]) {
+        let mut tasks = tokio::task::JoinSet::new();
+        for profile in profiles {
+            if self.get_status(&profile.name).await != VpnStatus::Connected {
+                continue;
+            }
+            let mgr = self.clone();
+            let profile = profile.clone();
+            tasks.spawn(async move {
+                let latency = mgr.measure_latency(&profile).await;
+                mgr.store_latency(&profile.name, latency).await;
+            });
+        }
+        while tasks.join_next().await.is_some() {}
+    }
+
+    async fn store_latency(&self, profile_name: &str, latency: Option<u32>) {
+        let mut connections = self.connections.write().await;
+        if let Some(conn) = connections.get_mut(profile_name)
+            && conn.status == VpnStatus::Connected
+        {
+            conn.latency_ms = latency;
+            conn.latency_checked = Some(std::time::Instant::now());
+        }
+    }
+
+    /// Update a connected profile's session traffic counters from its tunnel
+    /// interface. The interface is looked up on every call, as reconnecting
+    /// can bring the tunnel back on a different one.
+    pub async fn refresh_traffic_stats(&self, profile_name: &str) {
+        let ip = {
+            let connections = self.connections.read().await;
+            let Some(conn) = connections.get(profile_name) else {
+                return;
+            };
+            conn.ip_address.clone()
+        };
+        let Some((sent, received)) = self.interface_traffic(profile_name, ip.as_deref()).await
+        else {
+            return;
+        };
+        if let Some(conn) = self.connections.write().await.get_mut(profile_name) {
+            conn.bytes_sent = sent;
+            conn.bytes_received = received;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn interface_traffic(&self, profile_name: &str, _ip: Option<&str>) -> Option<(u64, u64)> {
+        let wg_config = self
+            .wireguard_configs
+            .read()
+            .await
+            .get(profile_name)
+            .cloned();
+        let interface = match wg_config {
+            Some(config) => wireguard::interface(&config),
+            None => linux::interface_for(self.runner.as_ref(), profile_name).await?,
+        };
+        linux::interface_traffic(&interface)
+    }
+
+    /// The tunnel's `utun` interface is the one holding its IP; `netstat`
+    /// reports byte counters for it
+    #[cfg(target_os = "macos")]
+    async fn interface_traffic(&self, _profile_name: &str, ip: Option<&str>) -> Option<(u64, u64)> {
+        let interface = self.get_macos_interface(ip?).await?;
+        let output = self
+            .runner
+            .run("netstat", &["-ibn", "-I", &interface])
+            .await
+            .ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // utun rows have no Address column, so count from the end:
+        // ... Ipkts Ierrs Ibytes Opkts Oerrs Obytes Coll
+        let line = stdout.lines().find(|l| l.contains("<Link#"))?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let from_end = |n: usize| {
+            fields
+                .get(fields.len().checked_sub(n)?)?
+                .parse::<u64>()
+                .ok()
+        };
+        Some((from_end(2)?, from_end(5)?))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    async fn interface_traffic(
+        &self,
+        _profile_name: &str,
+        _ip: Option<&str>,
+    ) -> Option<(u64, u64)> {
+        None
+    }
+
+    /// Re-check a single profile against the active VPN list. Used to poll a
+    /// watched profile faster than the full refresh; only connects and drops
+    /// are picked up, other states are left to `refresh_all_status`.
+    pub async fn refresh_one(&self, profile_name: &str) -> Result<()> {
+        let active = self.get_active_vpns().await?;
+        let mut connections = self.connections.write().await;
+        let Some(conn) = connections.get_mut(profile_name) else {
+            return Ok(());
+        };
+
+        let found = active.into_iter().find(|(name, _)| name == profile_name);
+        conn.raw_status = if found.is_some() {
+            VpnStatus::Connected
+        } else {
+            VpnStatus::Disconnected
+        };
+        if conn.hold_flap(found.is_some(), self.status_debounce) {
+            return Ok(());
+        }
+
+        match found {
+            Some((_, ip)) => {
+                if !matches!(conn.status, VpnStatus::Connected | VpnStatus::Degraded(_)) {
+                    conn.status = VpnStatus::Connected;
+                    conn.connected_since
+                        .get_or_insert_with(|| self.found_up_since(&conn.profile_name, None));
+                }
+                if ip.is_some() {
+                    conn.last_ip = ip.clone();
+                }
+                conn.ip_address = ip;
+            }
+            None if conn.status == VpnStatus::Connected => {
+                conn.status = VpnStatus::Disconnected;
+                conn.connected_since = None;
+                conn.ip_address = None;
+                conn.verified_ip = None;
+                conn.public_ip = None;
+                conn.owned_by_remipn = false;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Query the system for one profile and update only its entry, instead
+    /// of refreshing every profile
+    pub async fn refresh_profile(&self, profile_name: &str) -> VpnStatus {
+        let status = self.get_system_status(profile_name).await;
+
+        let mut connections = self.connections.write().await;
+        let conn = connections
+            .entry(profile_name.to_string())
+            .or_insert_with(|| VpnConnection::new(profile_name, VpnStatus::Disconnected));
+        match &status {
+            // A degraded tunnel stays degraded until it is cycled
+            VpnStatus::Connected => {
+                if !matches!(conn.status, VpnStatus::Connected | VpnStatus::Degraded(_)) {
+                    conn.status = VpnStatus::Connected;
+                    conn.connected_since
+                        .get_or_insert_with(|| self.found_up_since(&conn.profile_name, None));
+                }
+            }
+            other => {
+                if other.is_in_progress() && !conn.status.is_in_progress() {
+                    conn.connecting_since = Some(std::time::Instant::now());
+                }
+                conn.status = other.clone();
+                if matches!(other, VpnStatus::Disconnected | VpnStatus::NotConfigured) {
+                    conn.connected_since = None;
+                    conn.ip_address = None;
+                    conn.verified_ip = None;
+                    conn.public_ip = None;
+                    conn.owned_by_remipn = false;
+                }
+            }
+        }
+        conn.status.clone()
+    }
+
+    /// Forget the connection entry of a deleted profile
+    pub async fn remove_connection(&self, profile_name: &str) {
+        self.connections.write().await.remove(profile_name);
+    }
+
+    /// Move a connection entry to a profile's new name
+    pub async fn rename_connection(&self, old_name: &str, new_name: &str) {
+        let mut connections = self.connections.write().await;
+        if let Some(mut conn) = connections.remove(old_name) {
+            conn.profile_name = new_name.to_string();
+            connections.insert(new_name.to_string(), conn);
+        }
+    }
+
+    /// Reset timed-out attempts so the next refresh reports the real state
+    pub async fn clear_timeouts(&self) {
+        let mut connections = self.connections.write().await;
+        for conn in connections.values_mut() {
+            if conn.status == VpnStatus::TimedOut {
+                conn.status = VpnStatus::Disconnected;
+            }
+        }
+    }
+
+    /// Check that a connected profile still has the IP it started the session
+    /// with and that its gateway answers. On failure the connection is marked
+    /// `Degraded` and the reason is returned; a degraded one that passes is
+    /// `Connected` again.
+    pub async fn verify_connection(&self, profile: &VpnProfile) -> Option<String> {
+        let (ip, verified_ip, degraded) = {
+            let connections = self.connections.read().await;
+            let conn = connections.get(&profile.name)?;
+            let degraded = match conn.status {
+                VpnStatus::Connected => false,
+                VpnStatus::Degraded(_) => true,
+                _ => return None,
+            };
+            (conn.ip_address.clone(), conn.verified_ip.clone(), degraded)
+        };
+
+        let reason = if let (Some(ip), Some(verified)) = (&ip, &verified_ip)
+            && ip != verified
+        {
+            Some(format!("VPN IP changed from {} to {}", verified, ip))
+        } else if self.probe_gateway(profile).await.is_none() {
+            Some(format!("Gateway {} unreachable", profile.gateway_host()))
+        } else {
+            None
+        };
+
+        match &reason {
+            Some(reason) => {
+                self.set_status(&profile.name, VpnStatus::Degraded(reason.clone()))
+                    .await
+            }
+            None if degraded => {
+                log::info!("{} passed verification again", profile.name);
+                self.set_status(&profile.name, VpnStatus::Connected).await;
+            }
+            None => {}
+        }
+        reason
+    }
+
+    /// Probe the profile's gateway, returning the round-trip time in
+    /// milliseconds when it answers. TCP-based tunnels are probed with a
+    /// connect to the gateway port, as their gateways often drop pings;
+    /// UDP-based ones fall back to a ping of the host.
+    pub async fn probe_gateway(&self, profile: &VpnProfile) -> Option<u32> {
+        match profile.gateway_port() {
+            Some(port) if profile.protocol.uses_tcp() => {
+                check_port(&profile.gateway_host(), port).await
+            }
+            _ => self.check_reachable(&profile.gateway_host()).await,
+        }
+    }
+
+    /// Probe a gateway with a single ping, returning the round-trip time in
+    /// milliseconds when it answers
+    pub async fn check_reachable(&self, host: &str) -> Option<u32> {
+        #[cfg(target_os = "windows")]
+        let args = ["-n", "1", "-w", "2000", host];
+        #[cfg(target_os = "macos")]
+        let args = ["-c", "1", "-t", "2", host];
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        let args = ["-c", "1", "-W", "2", host];
+
+        let output = self.runner.run("ping", &args).await.ok()?;
+        if !output.status.success() {
+            return None;
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let re_time = regex::Regex::new(r"time[=<]\s*([\d.]+)").unwrap();
+        let rtt = re_time
+            .captures(&stdout)
+            .and_then(|c| c[1].parse::<f64>().ok())
+            .unwrap_or(0.0);
+        Some(rtt.round() as u32)
+    }
+
+    /// Probe every profile's gateway concurrently, at most `parallelism` at a
+    /// time, without connecting. Results keep the order of `profiles`, each
+    /// with its round-trip time in ms when the gateway answered.
+    pub async fn check_all_reachable(
+        &self,
+        profiles: &[VpnProfile],
+        parallelism: usize,
+    ) -> Vec<(String, Option<u32>)> {
+        let semaphore = Arc::new(tokio::sync::Semaphore::new(parallelism.max(1)));
+        let mut tasks = tokio::task::JoinSet::new();
+        for (idx, profile) in profiles.iter().enumerate() {
+            let mgr = self.clone();
+            let semaphore = semaphore.clone();
+            let profile = profile.clone();
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (idx, mgr.probe_gateway(&profile).await)
+            });
+        }
+
+        let mut rtts = vec![None; profiles.len()];
+        while let Some(result) = tasks.join_next().await {
+            if let Ok((idx, rtt)) = result {
+                rtts[idx] = rtt;
+            }
+        }
+        profiles.iter().map(|p| p.name.clone()).zip(rtts).collect()
+    }
+
+    /// Check that the platform tools used for connecting and querying status
+    /// can be run. Returns each tool with the problem found, if any.
+    pub async fn check_tools(&self) -> Vec<(&'static str, Option<String>)> {
+        let mut results = Vec::new();
+        for (program, args) in REQUIRED_TOOLS {
+            let problem = match self.runner.run(program, args).await {
+                Ok(_) => None,
+                Err(e) => Some(spawn_error(*program)(e).to_string()),
+            };
+            results.push((*program, problem));
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let backend = self.linux_backend().await;
+            let problem = (backend == linux::Backend::Unavailable)
+                .then(|| "none of nmcli, wg, ipsec or ip found".to_string());
+            results.push((backend.label(), problem));
+        }
+
+        results
     }
 
     /// Get all connection states
@@ -302,22 +1675,89 @@ impl VpnManager {
         connections.values().cloned().collect()
     }
 
+    /// Save the password used when connecting `profile_name`
+    pub async fn store_password(&self, profile_name: &str, password: &str) -> Result<()> {
+        let secrets = self.secrets.clone();
+        let (name, password) = (profile_name.to_string(), password.to_string());
+        tokio::task::spawn_blocking(move || secrets.store(&name, &password)).await?
+    }
+
+    /// Forget the saved password of `profile_name`, if any
+    pub async fn delete_password(&self, profile_name: &str) -> Result<()> {
+        let secrets = self.secrets.clone();
+        let name = profile_name.to_string();
+        tokio::task::spawn_blocking(move || secrets.delete(&name)).await?
+    }
+
+    /// The saved password of `profile_name`. A store that can't be read is
+    /// logged and treated as having none, so the connect is still attempted.
+    async fn password(&self, profile_name: &str) -> Option<String> {
+        let secrets = self.secrets.clone();
+        let name = profile_name.to_string();
+        match tokio::task::spawn_blocking(move || secrets.retrieve(&name)).await {
+            Ok(Ok(password)) => password,
+            Ok(Err(e)) => {
+                log::warn!("{}", e);
+                None
+            }
+            Err(e) => {
+                log::warn!("Password lookup for {} failed: {}", profile_name, e);
+                None
+            }
+        }
+    }
+
     /// Execute platform-specific VPN connect command
     async fn execute_vpn_connect(&self, profile: &VpnProfile) -> Result<()> {
+        // AnyConnect gateways are reached with openconnect on every platform
+        if profile.protocol == Protocol::AnyConnect {
+            let password = self.password(&profile.name).await;
+            return openconnect::connect(&self.run_dir()?, profile, password.as_deref()).await;
+        }
+
+        // WireGuard profiles with a .conf go through wg-quick; others fall
+        // through to the platform tooling below
+        #[cfg(unix)]
+        if let Some(config) = wireguard::config_file(profile) {
+            self.wireguard_configs
+                .write()
+                .await
+                .insert(profile.name.clone(), config.clone());
+            return wireguard::up(self.runner.as_ref(), &config).await;
+        }
+
         #[cfg(target_os = "windows")]
         {
-            // Windows: Use rasdial or PowerShell
-            let output = Command::new("powershell")
-                .arg("-Command")
-                .arg(format!(
-                    "rasdial '{}' /disconnect; rasdial '{}' '{}' '{}'",
-                    profile.name,
-                    profile.name,
-                    profile.username.as_deref().unwrap_or(""),
-                    "" // Password would be handled securely
-                ))
-                .output()
-                .await?;
+            if matches!(profile.protocol, Protocol::OpenVpn | Protocol::WireGuard) {
+                return Err(anyhow!(
+                    "rasdial can't connect {} profiles; use the {} client for '{}'",
+                    profile.protocol,
+                    profile.protocol,
+                    profile.name
+                ));
+            }
+
+            // Windows: Use rasdial or PowerShell. Single quotes are doubled
+            // so names can't end the PowerShell string early. The password
+            // goes in through the environment to keep it off PowerShell's
+            // command line; rasdial itself only takes it as an argument.
+            let quote = |s: &str| s.replace('\'', "''");
+            let password = self.password(&profile.name).await.unwrap_or_default();
+            let script = format!(
+                "rasdial '{}' /disconnect; rasdial '{}' '{}' $env:REMIPN_PASSWORD",
+                quote(&profile.name),
+                quote(&profile.name),
+                quote(profile.username.as_deref().unwrap_or("")),
+            );
+            let output = self
+                .runner
+                .run_with_env(
+                    "powershell",
+                    &["-Command", &script],
+                    &[("REMIPN_PASSWORD", password.as_str())],
+                )
+                .await
+                .map_err(spawn_error("powershell"))?;
 
             if !output.status.success() {
                 return Err(anyhow!(
@@ -329,32 +1769,34 @@ impl VpnManager {
 
         #[cfg(target_os = "linux")]
         {
-            // Linux: Use NetworkManager or strongSwan
-            let output = Command::new("nmcli")
-                .arg("connection")
-                .arg("up")
-                .arg(&profile.name)
-                .output()
+            // Linux: NetworkManager, WireGuard or strongSwan, whichever is installed
+            // and suits the profile's protocol
+            self.linux_backend()
+                .await
+                .for_protocol(&profile.protocol)?
+                .connect(self.runner.as_ref(), &profile.name)
                 .await?;
-
-            if !output.status.success() {
-                return Err(anyhow!(
-                    "Failed to connect: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
         }
 
         #[cfg(target_os = "macos")]
         {
             // macOS: Use scutil or networksetup
             // First, try to start the service. If we get "No service", provide guidance.
-            let output = Command::new("scutil")
-                .arg("--nc")
-                .arg("start")
-                .arg(&profile.name)
-                .output()
-                .await?;
+            // scutil has no way to read the password other than as an
+            // argument, so it is visible in the process list while it runs.
+            let password = self.password(&profile.name).await;
+            let mut args = vec!["--nc", "start", profile.name.as_str()];
+            if let Some(password) = &password {
+                if let Some(user) = &profile.username {
+                    args.extend(["--user", user.as_str()]);
+                }
+                args.extend(["--password", password.as_str()]);
+            }
+            let output = self
+                .runner
+                .run("scutil", &args)
+                .await
+                .map_err(spawn_error("scutil"))?;
 
             let stdout = String::from_utf8_lossy(&output.stdout);
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -363,10 +1805,7 @@ impl VpnManager {
                 let combined = format!("{}\n{}", stdout, stderr);
                 // Common macOS message when the service isn't registered
                 if combined.contains("No service") || combined.contains("No such service") {
-                    return Err(anyhow!(
-                        "No system VPN service found for '{}'.\n- If this is an Azure profile, import the .azvpn/.xml file into the 'Azure VPN Client' App (e.g.: open -a 'Azure VPN Client' /path/to/profile.azvpn).\n- Alternatively, open Azure VPN Client and create/import a profile with the same name.\n- Then try again from remipn.",
-                        profile.name
-                    ));
+                    return Err(anyhow!(not_configured_help(&profile.name)));
                 }
                 if combined.to_lowercase().contains("authentication")
                     || combined.to_lowercase().contains("login")
@@ -385,13 +1824,30 @@ impl VpnManager {
 
     /// Execute platform-specific VPN disconnect command
     async fn execute_vpn_disconnect(&self, profile_name: &str) -> Result<()> {
+        if openconnect::disconnect(self.runner.as_ref(), &self.run_dir()?, profile_name).await? {
+            return Ok(());
+        }
+
+        #[cfg(unix)]
+        {
+            let config = self
+                .wireguard_configs
+                .read()
+                .await
+                .get(profile_name)
+                .cloned();
+            if let Some(config) = config {
+                return wireguard::down(self.runner.as_ref(), &config).await;
+            }
+        }
+
         #[cfg(target_os = "windows")]
         {
-            let output = Command::new("rasdial")
-                .arg(profile_name)
-                .arg("/disconnect")
-                .output()
-                .await?;
+            let output = self
+                .runner
+                .run("rasdial", &[profile_name, "/disconnect"])
+                .await
+                .map_err(spawn_error("rasdial"))?;
 
             if !output.status.success() {
                 return Err(anyhow!(
@@ -403,29 +1859,20 @@ impl VpnManager {
 
         #[cfg(target_os = "linux")]
         {
-            let output = Command::new("nmcli")
-                .arg("connection")
-                .arg("down")
-                .arg(profile_name)
-                .output()
+            self.linux_backend()
+                .await
+                .for_interface(profile_name)
+                .disconnect(self.runner.as_ref(), profile_name)
                 .await?;
-
-            if !output.status.success() {
-                return Err(anyhow!(
-                    "Failed to disconnect: {}",
-                    String::from_utf8_lossy(&output.stderr)
-                ));
-            }
         }
 
         #[cfg(target_os = "macos")]
         {
-            let output = Command::new("scutil")
-                .arg("--nc")
-                .arg("stop")
-                .arg(profile_name)
-                .output()
-                .await?;
+            let output = self
+                .runner
+                .run("scutil", &["--nc", "stop", profile_name])
+                .await
+                .map_err(spawn_error("scutil"))?;
 
             if !output.status.success() {
                 return Err(anyhow!(
@@ -444,7 +1891,11 @@ impl VpnManager {
 
         #[cfg(target_os = "windows")]
         {
-            let output = Command::new("rasdial").output().await?;
+            let output = self
+                .runner
+                .run("rasdial", &[])
+                .await
+                .map_err(spawn_error("rasdial"))?;
 
             let stdout = String::from_utf8_lossy(&output.stdout);
             for line in stdout.lines() {
@@ -459,41 +1910,26 @@ impl VpnManager {
 
         #[cfg(target_os = "linux")]
         {
-            let output = Command::new("nmcli")
-                .arg("-t")
-                .arg("-f")
-                .arg("NAME,TYPE,STATE,IP4.ADDRESS")
-                .arg("connection")
-                .arg("show")
-                .arg("--active")
-                .output()
-                .await?;
-
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            for line in stdout.lines() {
-                let parts: Vec<&str> = line.split(':').collect();
-                if parts.len() >= 3 && parts[1].contains("vpn") {
-                    let name = parts[0].to_string();
-                    let ip = parts
-                        .get(3)
-                        .filter(|s| !s.is_empty())
-                        .map(|s| s.to_string());
-                    active.push((name, ip));
-                }
-            }
+            active.extend(
+                self.linux_backend()
+                    .await
+                    .active(self.runner.as_ref())
+                    .await?,
+            );
         }
 
         #[cfg(target_os = "macos")]
         {
-            let output = Command::new("scutil")
-                .arg("--nc")
-                .arg("list")
-                .output()
-                .await?;
+            let output = self
+                .runner
+                .run("scutil", &["--nc", "list"])
+                .await
+                .map_err(spawn_error("scutil"))?;
 
             let stdout = String::from_utf8_lossy(&output.stdout);
             for line in stdout.lines() {
-                if line.contains("Connected")
+                // "(Disconnected)" contains "Connected" too
+                if line.contains("(Connected)")
                     && let Some(name) = line.split('"').nth(1)
                 {
                     active.push((name.to_string(), self.get_macos_ip(name).await));
@@ -501,13 +1937,79 @@ impl VpnManager {
             }
         }
 
+        if let Ok(dir) = self.run_dir() {
+            active.extend(
+                openconnect::active(self.runner.as_ref(), &dir)
+                    .await
+                    .into_iter()
+                    .map(|name| (name, None)),
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            let configs = self.wireguard_configs.read().await.clone();
+            for (name, config) in configs {
+                let interface = wireguard::interface(&config);
+                if !wireguard::is_up(self.runner.as_ref(), &interface).await {
+                    continue;
+                }
+                // The wg-quick interface may also be listed under its own name
+                active.retain(|(n, _)| n != &interface);
+                #[cfg(target_os = "linux")]
+                let ip = linux::interface_ip(self.runner.as_ref(), &interface).await;
+                #[cfg(not(target_os = "linux"))]
+                let ip = None;
+                active.push((name, ip));
+            }
+        }
+
         Ok(active)
     }
 
+    /// Names of the VPN services registered with the system, when the
+    /// platform can enumerate them
+    #[cfg(target_os = "macos")]
+    async fn get_registered_services(&self) -> Option<Vec<String>> {
+        let output = self.runner.run("scutil", &["--nc", "list"]).await.ok()?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Some(
+            stdout
+                .lines()
+                .filter_map(|line| line.split('"').nth(1))
+                .map(|name| name.to_string())
+                .collect(),
+        )
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    async fn get_registered_services(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Interface that holds `ip`, as listed by `ifconfig`
+    #[cfg(target_os = "macos")]
+    async fn get_macos_interface(&self, ip: &str) -> Option<String> {
+        let output = self.runner.run("ifconfig", &[]).await.ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut current_interface = None;
+        for line in stdout.lines() {
+            if !line.starts_with('\t') {
+                current_interface = line.split(':').next();
+            } else if line.split_whitespace().nth(1) == Some(ip)
+                && line.trim_start().starts_with("inet ")
+            {
+                return current_interface.map(str::to_string);
+            }
+        }
+        None
+    }
+
     #[cfg(target_os = "macos")]
     async fn get_macos_ip(&self, _name: &str) -> Option<String> {
         // This is a heuristic: look for utun interfaces which are common for VPNs
-        let output = Command::new("ifconfig").output().await.ok()?;
+        let output = self.runner.run("ifconfig", &[]).await.ok()?;
 
         let stdout = String::from_utf8_lossy(&output.stdout);
         let mut current_interface = None;
@@ -534,3 +2036,412 @@ impl Default for VpnManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+impl VpnManager {
+    /// A manager running tools through `runner`, with a run dir and history
+    /// of its own so tests never touch the files of real sessions
+    pub fn mocked(runner: MockRunner) -> Self {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "remipn-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::create_dir_all(&dir);
+        Self::new()
+            .with_runner(runner)
+            .with_history_file(dir.join("history.jsonl"))
+            .with_run_dir(dir)
+    }
+}
+
+/// Time a TCP connect to `host:port`, giving up after two seconds like the
+/// ping probe does
+async fn check_port(host: &str, port: u16) -> Option<u32> {
+    let started = std::time::Instant::now();
+    let connect = tokio::net::TcpStream::connect((host, port));
+    match tokio::time::timeout(std::time::Duration::from_secs(2), connect).await {
+        Ok(Ok(_)) => Some(started.elapsed().as_millis() as u32),
+        Ok(Err(e)) => {
+            log::debug!("Gateway {}:{} refused the probe: {}", host, port, e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two profiles on one gateway host, and a tunnel reported under it
+    #[test]
+    fn shared_gateway_tunnels_are_attributed_only_when_clear() {
+        let profile = |name: &str| VpnProfile {
+            name: name.to_string(),
+            gateway_address: "vpn.example.com:443".to_string(),
+            ..Default::default()
+        };
+        let profiles = [profile("Office"), profile("Office Admin")];
+        let tunnel = ("VPN.example.com".to_string(), Some("10.8.0.2".to_string()));
+        let attributed = |connections: &HashMap<String, VpnConnection>,
+                          active: &[(String, Option<String>)]| {
+            let mut names: Vec<String> = correlate_by_gateway(&profiles, connections, active)
+                .into_keys()
+                .collect();
+            names.sort();
+            names
+        };
+
+        // Nothing tells the two apart
+        let mut connections = HashMap::new();
+        assert!(attributed(&connections, std::slice::from_ref(&tunnel)).is_empty());
+
+        // The one already shown as up
+        let mut up = VpnConnection::new("Office Admin", VpnStatus::Connected);
+        connections.insert(up.profile_name.clone(), up.clone());
+        assert_eq!(
+            attributed(&connections, std::slice::from_ref(&tunnel)),
+            vec!["Office Admin"]
+        );
+
+        // The one remipn connected beats the one shown as up
+        up.profile_name = "Office".to_string();
+        up.owned_by_remipn = true;
+        connections.insert(up.profile_name.clone(), up);
+        assert_eq!(
+            attributed(&connections, std::slice::from_ref(&tunnel)),
+            vec!["Office"]
+        );
+
+        // A profile matched by name isn't a candidate
+        let active = [("Office".to_string(), None), tunnel.clone()];
+        assert_eq!(attributed(&HashMap::new(), &active), vec!["Office Admin"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    const NMCLI_ACTIVE: &str = "\
+Work VPN:vpn:activated:10.8.0.2/24
+Wired connection 1:802-3-ethernet:activated:192.168.1.5/24
+home-wg:wireguard:activated:10.9.0.3/32
+";
+
+    #[cfg(target_os = "linux")]
+    fn nmcli() -> MockRunner {
+        MockRunner::new()
+            .with_output("nmcli", &["--version"], "nmcli tool, version 1.46.0\n")
+            .with_output(
+                "nmcli",
+                &["-t", "-f", "NAME,STATE"],
+                "Work VPN:activated\nWired connection 1:activated\nStaging:activating\n",
+            )
+            .with_output(
+                "nmcli",
+                &["-t", "-f", "NAME,TYPE,STATE,IP4.ADDRESS"],
+                NMCLI_ACTIVE,
+            )
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn nmcli_active_lists_only_tunnels() {
+        let mgr = VpnManager::mocked(nmcli());
+        let active = mgr.get_active_vpns().await.unwrap();
+        assert_eq!(
+            active,
+            vec![
+                ("Work VPN".to_string(), Some("10.8.0.2".to_string())),
+                ("home-wg".to_string(), Some("10.9.0.3".to_string())),
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn nmcli_status_follows_connection_state() {
+        let mgr = VpnManager::mocked(nmcli());
+        assert_eq!(
+            mgr.get_system_status("Work VPN").await,
+            VpnStatus::Connected
+        );
+        assert_eq!(
+            mgr.get_system_status("Staging").await,
+            VpnStatus::Connecting
+        );
+        assert_eq!(
+            mgr.get_system_status("Other").await,
+            VpnStatus::Disconnected
+        );
+    }
+
+    #[test]
+    fn missing_tools_come_with_an_install_hint() {
+        for (program, hint) in [
+            ("nmcli", "install NetworkManager (nmcli)"),
+            ("scutil", "it ships with macOS, so check PATH"),
+            ("rasdial", "ensure RAS (Remote Access Service) is installed"),
+            ("powershell", "ensure Windows PowerShell is installed"),
+            ("wg-quick", "ensure it is installed and on PATH"),
+        ] {
+            assert_eq!(
+                ToolMissing { program }.to_string(),
+                format!("{} not found; {}", program, hint)
+            );
+        }
+
+        let missing = spawn_error("wg-quick")(std::io::ErrorKind::NotFound.into());
+        assert!(missing.downcast_ref::<ToolMissing>().is_some());
+        let denied = spawn_error("wg-quick")(std::io::ErrorKind::PermissionDenied.into());
+        assert!(denied.downcast_ref::<ToolMissing>().is_none());
+    }
+
+    /// Without the platform's status tool every profile shows Unknown with
+    /// the hint, instead of Disconnected
+    #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+    #[tokio::test]
+    async fn missing_status_tool_shows_as_unknown() {
+        #[cfg(target_os = "linux")]
+        let program = "nmcli";
+        #[cfg(target_os = "macos")]
+        let program = "scutil";
+        #[cfg(target_os = "windows")]
+        let program = "rasdial";
+
+        let mgr = VpnManager::mocked(MockRunner::new());
+        let profiles = [VpnProfile {
+            name: "Office".to_string(),
+            ..Default::default()
+        }];
+        mgr.refresh_all_status(&profiles).await.unwrap();
+        assert_eq!(
+            mgr.get_status("Office").await,
+            VpnStatus::Unknown(ToolMissing { program }.to_string())
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn missing_status_tool_is_reported() {
+        let mgr = VpnManager::mocked(MockRunner::new());
+        let err = mgr.get_active_vpns().await.unwrap_err();
+        assert!(err.downcast_ref::<ToolMissing>().is_some());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn other_tunnels_are_left_up_when_multiple_are_allowed() {
+        let runner = nmcli();
+        let mgr = VpnManager::mocked(runner.clone()).with_multiple_connections(true);
+        assert!(mgr.enforce_exclusive("Work VPN").await.is_empty());
+        assert!(
+            !runner.calls().iter().any(|call| call.contains("down")),
+            "{:?}",
+            runner.calls()
+        );
+
+        let runner = nmcli();
+        let mgr = VpnManager::mocked(runner.clone());
+        assert_eq!(mgr.enforce_exclusive("Work VPN").await, vec!["home-wg"]);
+        assert!(
+            runner
+                .calls()
+                .contains(&"nmcli connection down home-wg".to_string())
+        );
+    }
+
+    /// A tunnel missing from one status round is held as connected; one
+    /// missing for as many rounds as the debounce asks for is reported
+    #[cfg(target_os = "linux")]
+    #[tokio::test(start_paused = true)]
+    async fn status_debounce_holds_a_one_round_blip() {
+        let runner = nmcli();
+        let mgr = VpnManager::mocked(runner.clone()).with_status_debounce(2);
+        let profiles = [VpnProfile {
+            name: "Work VPN".to_string(),
+            ..Default::default()
+        }];
+        let interval = std::time::Duration::from_secs(5);
+        let round = || async {
+            tokio::time::sleep(interval).await;
+            mgr.refresh_all_status(&profiles).await.unwrap();
+            mgr.get_status("Work VPN").await
+        };
+        let active = ["-t", "-f", "NAME,TYPE,STATE,IP4.ADDRESS"];
+
+        assert_eq!(round().await, VpnStatus::Disconnected);
+        assert_eq!(round().await, VpnStatus::Connected);
+
+        runner.set_output("nmcli", &active, "");
+        assert_eq!(round().await, VpnStatus::Connected);
+        runner.set_output("nmcli", &active, NMCLI_ACTIVE);
+        assert_eq!(round().await, VpnStatus::Connected);
+
+        runner.set_output("nmcli", &active, "");
+        assert_eq!(round().await, VpnStatus::Connected);
+        assert_eq!(round().await, VpnStatus::Disconnected);
+    }
+
+    /// Tunnels already up are dated once, from the recorded last connect
+    /// when there is one, and keep that time on later rounds
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn connected_since_holds_across_refreshes() {
+        let mgr = VpnManager::mocked(nmcli()).with_status_debounce(1);
+        let recorded = chrono::DateTime::parse_from_rfc3339("2026-03-02T09:30:00+01:00")
+            .unwrap()
+            .with_timezone(&chrono::Local);
+        let profiles = [
+            VpnProfile {
+                name: "Work VPN".to_string(),
+                last_connected: Some(recorded),
+                ..Default::default()
+            },
+            VpnProfile {
+                name: "home-wg".to_string(),
+                ..Default::default()
+            },
+        ];
+        let since = || async {
+            mgr.refresh_all_status(&profiles).await.unwrap();
+            let mut connections = mgr.get_all_connections().await;
+            connections.sort_by(|a, b| a.profile_name.cmp(&b.profile_name));
+            connections
+                .into_iter()
+                .map(|c| (c.profile_name, c.connected_since))
+                .collect::<Vec<_>>()
+        };
+
+        let first = since().await;
+        assert_eq!(first[0], ("Work VPN".to_string(), Some(recorded)));
+        assert!(first[1].1.is_some());
+        for _ in 0..3 {
+            assert_eq!(since().await, first);
+        }
+    }
+
+    #[tokio::test]
+    async fn deleted_profiles_lose_their_connection() {
+        let mgr = VpnManager::mocked(MockRunner::new());
+        mgr.set_status("Office", VpnStatus::Connected).await;
+        mgr.set_status("Home", VpnStatus::Connected).await;
+
+        mgr.remove_connection("Office").await;
+        let names: Vec<String> = mgr
+            .get_all_connections()
+            .await
+            .into_iter()
+            .map(|c| c.profile_name)
+            .collect();
+        assert_eq!(names, vec!["Home"]);
+        assert_eq!(mgr.get_status("Office").await, VpnStatus::Disconnected);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn openconnect_pid_files_live_in_the_run_dir() {
+        let live = VpnManager::mocked(MockRunner::new().with_output("kill", &["-0"], ""));
+        let dir = live.run_dir().unwrap();
+        std::fs::write(dir.join("Office.pid"), "4242").unwrap();
+        assert_eq!(live.get_system_status("Office").await, VpnStatus::Connected);
+
+        // Without a live process the pid file is stale
+        let stale = VpnManager::mocked(MockRunner::new()).with_run_dir(&dir);
+        assert_eq!(
+            stale.get_system_status("Office").await,
+            VpnStatus::Disconnected
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Every interface lookup takes a second; eight of them should still
+    /// finish in about one
+    #[cfg(target_os = "linux")]
+    #[tokio::test(start_paused = true)]
+    async fn traffic_is_gathered_concurrently() {
+        let delay = std::time::Duration::from_secs(1);
+        let runner = MockRunner::new()
+            .with_output("nmcli", &["-g", "GENERAL.DEVICES"], "lo\n")
+            .with_delay(delay);
+        let mgr = VpnManager::mocked(runner);
+        let targets: Vec<(String, Option<String>)> = (0..REFRESH_PARALLELISM)
+            .map(|i| (format!("Office {}", i), None))
+            .collect();
+
+        let started = tokio::time::Instant::now();
+        let traffic = mgr.gather_traffic(targets).await;
+        let took = started.elapsed();
+
+        assert_eq!(traffic.len(), REFRESH_PARALLELISM);
+        assert!(took < delay * 2, "took {:?}", took);
+    }
+
+    #[cfg(target_os = "macos")]
+    const SCUTIL_LIST: &str = "\
+Available network connection services in the current set (*=enabled):
+* (Connected)      6D1C2E3A-1111 PPP --> L2TP       \"Work VPN\"                 [PPP:L2TP]
+* (Disconnected)   7A2B3C4D-2222 IPSec              \"Home\"                     [IPSec]
+";
+
+    #[cfg(target_os = "macos")]
+    const IFCONFIG: &str = "\
+lo0: flags=8049<UP,LOOPBACK,RUNNING,MULTICAST> mtu 16384
+\tinet 127.0.0.1 netmask 0xff000000
+en0: flags=8863<UP,BROADCAST,SMART,RUNNING,SIMPLEX,MULTICAST> mtu 1500
+\tinet 192.168.1.5 netmask 0xffffff00 broadcast 192.168.1.255
+utun3: flags=8051<UP,POINTOPOINT,RUNNING,MULTICAST> mtu 1400
+\tinet 10.8.0.2 --> 10.8.0.1 netmask 0xffffffff
+";
+
+    #[cfg(target_os = "macos")]
+    fn scutil() -> MockRunner {
+        MockRunner::new()
+            .with_output("scutil", &["--nc", "list"], SCUTIL_LIST)
+            .with_output(
+                "scutil",
+                &["--nc", "status", "Work VPN"],
+                "Connected\nExtended Status <dictionary> {\n}\n",
+            )
+            .with_output("scutil", &["--nc", "status", "Home"], "Disconnected\n")
+            .with_output("scutil", &["--nc", "status", "Gone"], "No service\n")
+            .with_output("ifconfig", &[], IFCONFIG)
+    }
+
+    #[cfg(target_os = "macos")]
+    #[tokio::test]
+    async fn scutil_active_takes_the_utun_address() {
+        let mgr = VpnManager::mocked(scutil());
+        let active = mgr.get_active_vpns().await.unwrap();
+        assert_eq!(
+            active,
+            vec![("Work VPN".to_string(), Some("10.8.0.2".to_string()))]
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[tokio::test]
+    async fn scutil_status_is_parsed() {
+        let mgr = VpnManager::mocked(scutil());
+        assert_eq!(
+            mgr.get_system_status("Work VPN").await,
+            VpnStatus::Connected
+        );
+        assert_eq!(mgr.get_system_status("Home").await, VpnStatus::Disconnected);
+        assert_eq!(
+            mgr.get_system_status("Gone").await,
+            VpnStatus::NotConfigured
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[tokio::test]
+    async fn interface_is_found_by_address() {
+        let mgr = VpnManager::mocked(scutil());
+        assert_eq!(
+            mgr.get_macos_interface("10.8.0.2").await.as_deref(),
+            Some("utun3")
+        );
+        assert_eq!(mgr.get_macos_interface("10.0.0.99").await, None);
+    }
+}
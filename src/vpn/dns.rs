@@ -0,0 +1,140 @@
+//! DNS leak and split-tunnel check run after a tunnel comes up: is the
+//! system resolver reached through the tunnel interface, and does traffic
+//! to the internet leave through it? Linux asks `resolvectl` and `ip`, macOS
+//! `scutil --dns` and `route`; other platforms aren't checked.
+
+use super::runner::CommandRunner;
+
+/// Address whose route stands for "the internet"
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+const PROBE_ADDRESS: &str = "1.1.1.1";
+
+/// Problems found with a tunnel on `interface`; empty when DNS and the
+/// default route both go through it, or when they can't be determined
+pub async fn leak_warnings(runner: &dyn CommandRunner, interface: &str) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if let Some(resolver) = dns_interface(runner, interface).await
+        && resolver != interface
+    {
+        warnings.push(format!(
+            "Possible DNS leak: queries go through {} instead of {}",
+            resolver, interface
+        ));
+    }
+    if let Some(route) = route_interface(runner).await
+        && route != interface
+    {
+        warnings.push(format!(
+            "Internet traffic leaves through {} instead of {} (split tunnel?)",
+            route, interface
+        ));
+    }
+    warnings
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+async fn stdout(runner: &dyn CommandRunner, program: &str, args: &[&str]) -> Option<String> {
+    match runner.run(program, args).await {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(_) => None,
+        Err(e) => {
+            log::debug!("DNS leak check: {} failed: {}", program, e);
+            None
+        }
+    }
+}
+
+/// Interface the default resolver is reached through. With systemd-resolved
+/// the tunnel wins when it takes all domains (`~.`); otherwise any other
+/// link able to answer default-route queries may be used.
+#[cfg(target_os = "linux")]
+async fn dns_interface(runner: &dyn CommandRunner, interface: &str) -> Option<String> {
+    let status = stdout(runner, "resolvectl", &["status"]).await?;
+    let mut links: Vec<(String, String)> = Vec::new();
+    for line in status.lines() {
+        if let Some(rest) = line.strip_prefix("Link ") {
+            // "Link 5 (tun0)"
+            let Some((name, _)) = rest
+                .split_once('(')
+                .and_then(|(_, name)| name.split_once(')'))
+            else {
+                continue;
+            };
+            links.push((name.to_string(), String::new()));
+        } else if !line.starts_with(' ') && !line.trim().is_empty() && !line.contains(':') {
+            // "Global" and any other unindented section header
+            links.push((line.trim().to_string(), String::new()));
+        } else if let Some((_, body)) = links.last_mut() {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+
+    let has_dns = |body: &str| body.contains("DNS Servers:");
+    let takes_default =
+        |body: &str| body.contains("+DefaultRoute") || body.contains("Default Route: yes");
+    let tunnel = links.iter().find(|(name, _)| name == interface);
+    if let Some((_, body)) = tunnel
+        && has_dns(body)
+        && body.contains("~.")
+    {
+        return Some(interface.to_string());
+    }
+    let other = links.iter().find(|(name, body)| {
+        name != interface && name != "Global" && has_dns(body) && takes_default(body)
+    });
+    match (tunnel, other) {
+        (_, Some((name, _))) => Some(name.clone()),
+        (Some((_, body)), None) if has_dns(body) => Some(interface.to_string()),
+        _ => None,
+    }
+}
+
+/// Interface of the first resolver `scutil --dns` lists for unscoped
+/// queries, when it is bound to one
+#[cfg(target_os = "macos")]
+async fn dns_interface(runner: &dyn CommandRunner, _interface: &str) -> Option<String> {
+    let dns = stdout(runner, "scutil", &["--dns"]).await?;
+    let unscoped = dns.split("for scoped queries").next()?;
+    let first = unscoped.split("resolver #").nth(1)?;
+    first.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim() != "if_index" {
+            return None;
+        }
+        // "if_index : 12 (utun3)"
+        let (_, name) = value.split_once('(')?;
+        Some(name.trim_end_matches(')').trim().to_string())
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn dns_interface(_runner: &dyn CommandRunner, _interface: &str) -> Option<String> {
+    None
+}
+
+/// Interface traffic to [`PROBE_ADDRESS`] is routed through
+#[cfg(target_os = "linux")]
+async fn route_interface(runner: &dyn CommandRunner) -> Option<String> {
+    // "1.1.1.1 via 192.168.1.1 dev wlan0 src 192.168.1.5 uid 1000"
+    let route = stdout(runner, "ip", &["route", "get", PROBE_ADDRESS]).await?;
+    let mut words = route.split_whitespace();
+    words.find(|w| *w == "dev")?;
+    words.next().map(str::to_string)
+}
+
+#[cfg(target_os = "macos")]
+async fn route_interface(runner: &dyn CommandRunner) -> Option<String> {
+    let route = stdout(runner, "route", &["-n", "get", PROBE_ADDRESS]).await?;
+    route.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "interface").then(|| value.trim().to_string())
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn route_interface(_runner: &dyn CommandRunner) -> Option<String> {
+    None
+}
@@ -0,0 +1,238 @@
+//! Kill switch: firewall rules that only let traffic out through the VPN
+//! interface, plus loopback, DHCP and the gateway itself so the tunnel can
+//! be rebuilt after a drop. Linux gets an iptables/ip6tables chain, macOS a
+//! pf anchor under `com.apple/`, which the stock pf.conf already loads.
+//! The rules stay until [`disable`] removes them, through drops and remipn
+//! restarts; a marker in the run dir records the profile they protect.
+
+use super::runner::CommandRunner;
+use super::spawn_error;
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "linux")]
+const CHAIN: &str = "remipn-killswitch";
+#[cfg(target_os = "macos")]
+const ANCHOR: &str = "com.apple/remipn-killswitch";
+
+/// A firewall tool invocation
+type FirewallCommand = (&'static str, Vec<String>);
+
+fn marker_file(run_dir: &Path) -> PathBuf {
+    run_dir.join("killswitch")
+}
+
+#[cfg(target_os = "macos")]
+fn pf_file(run_dir: &Path) -> PathBuf {
+    run_dir.join("killswitch.pf")
+}
+
+/// Profile the installed rules protect, if any
+pub fn active_profile(run_dir: &Path) -> Option<String> {
+    let name = fs::read_to_string(marker_file(run_dir)).ok()?;
+    Some(name.trim().to_string()).filter(|n| !n.is_empty())
+}
+
+fn command(program: &'static str, args: &[&str]) -> FirewallCommand {
+    (program, args.iter().map(|a| a.to_string()).collect())
+}
+
+/// The pf anchor: block everything outbound, then let the exceptions pass
+#[cfg(target_os = "macos")]
+fn pf_rules(interface: &str, gateway: &str) -> String {
+    [
+        "block drop out all".to_string(),
+        "pass out quick on lo0 all".to_string(),
+        format!("pass out quick on {} all", interface),
+        format!("pass out quick to {}", gateway),
+        "pass out quick proto udp from any port 68 to any port 67".to_string(),
+    ]
+    .join("\n")
+        + "\n"
+}
+
+#[cfg(target_os = "linux")]
+fn install_commands(
+    _run_dir: &Path,
+    interface: &str,
+    gateway: &str,
+) -> Result<Vec<FirewallCommand>> {
+    let mut commands = Vec::new();
+    for program in ["iptables", "ip6tables"] {
+        commands.push(command(program, &["-N", CHAIN]));
+        commands.push(command(program, &["-A", CHAIN, "-o", "lo", "-j", "ACCEPT"]));
+        commands.push(command(
+            program,
+            &["-A", CHAIN, "-o", interface, "-j", "ACCEPT"],
+        ));
+        if program == "iptables" {
+            commands.push(command(
+                program,
+                &["-A", CHAIN, "-d", gateway, "-j", "ACCEPT"],
+            ));
+            commands.push(command(
+                program,
+                &["-A", CHAIN, "-p", "udp", "--dport", "67", "-j", "ACCEPT"],
+            ));
+        }
+        commands.push(command(program, &["-A", CHAIN, "-j", "REJECT"]));
+        commands.push(command(program, &["-I", "OUTPUT", "-j", CHAIN]));
+    }
+    Ok(commands)
+}
+
+#[cfg(target_os = "macos")]
+fn install_commands(
+    run_dir: &Path,
+    _interface: &str,
+    _gateway: &str,
+) -> Result<Vec<FirewallCommand>> {
+    let file = pf_file(run_dir).display().to_string();
+    Ok(vec![
+        command("pfctl", &["-a", ANCHOR, "-f", &file]),
+        command("pfctl", &["-E"]),
+    ])
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn install_commands(
+    _run_dir: &Path,
+    _interface: &str,
+    _gateway: &str,
+) -> Result<Vec<FirewallCommand>> {
+    Err(anyhow!(
+        "The kill switch needs iptables (Linux) or pf (macOS)"
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn remove_commands() -> Vec<FirewallCommand> {
+    ["iptables", "ip6tables"]
+        .into_iter()
+        .flat_map(|program| {
+            [
+                command(program, &["-D", "OUTPUT", "-j", CHAIN]),
+                command(program, &["-F", CHAIN]),
+                command(program, &["-X", CHAIN]),
+            ]
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn remove_commands() -> Vec<FirewallCommand> {
+    vec![command("pfctl", &["-a", ANCHOR, "-F", "all"])]
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn remove_commands() -> Vec<FirewallCommand> {
+    Vec::new()
+}
+
+async fn run(runner: &dyn CommandRunner, (program, args): &FirewallCommand) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let output = runner
+        .run(program, &args)
+        .await
+        .map_err(spawn_error(program))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Install the rules for `profile_name`'s tunnel on `interface` to
+/// `gateway`, replacing any installed before, and record them in `run_dir`.
+/// With `dry_run` the rules are only logged.
+pub async fn enable(
+    runner: &dyn CommandRunner,
+    run_dir: &Path,
+    profile_name: &str,
+    interface: &str,
+    gateway: &str,
+    dry_run: bool,
+) -> Result<()> {
+    let commands = install_commands(run_dir, interface, gateway)?;
+    let mode = if dry_run { " (dry run)" } else { "" };
+    #[cfg(target_os = "macos")]
+    let rules = pf_rules(interface, gateway);
+    #[cfg(target_os = "macos")]
+    for line in rules.lines() {
+        log::info!("Kill switch{} pf rule: {}", mode, line);
+    }
+    for (program, args) in &commands {
+        log::info!("Kill switch{}: {} {}", mode, program, args.join(" "));
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    if active_profile(run_dir).is_some() {
+        disable(runner, run_dir).await?;
+    }
+    #[cfg(target_os = "macos")]
+    fs::write(pf_file(run_dir), rules)?;
+    for cmd in &commands {
+        if let Err(e) = run(runner, cmd).await {
+            // A partial rule set may block everything; take it back down
+            for cmd in remove_commands() {
+                let _ = run(runner, &cmd).await;
+            }
+            return Err(e);
+        }
+    }
+    fs::write(marker_file(run_dir), profile_name)?;
+    Ok(())
+}
+
+/// Remove the rules so traffic flows normally again. Steps for rules that
+/// are already gone are skipped; an error means they may still be in place.
+pub async fn disable(runner: &dyn CommandRunner, run_dir: &Path) -> Result<()> {
+    for cmd in remove_commands() {
+        if let Err(e) = run(runner, &cmd).await {
+            if e.downcast_ref::<super::ToolMissing>().is_some() {
+                return Err(e);
+            }
+            log::debug!("Kill switch teardown: {}", e);
+        }
+    }
+    if still_installed(runner).await {
+        return Err(anyhow!(
+            "Kill switch rules are still installed; remove them as root"
+        ));
+    }
+    match fs::remove_file(marker_file(run_dir)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the firewall still lists the rules
+#[cfg(target_os = "linux")]
+async fn still_installed(runner: &dyn CommandRunner) -> bool {
+    for program in ["iptables", "ip6tables"] {
+        if run(runner, &command(program, &["-S", CHAIN])).await.is_ok() {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(target_os = "macos")]
+async fn still_installed(runner: &dyn CommandRunner) -> bool {
+    runner
+        .run("pfctl", &["-a", ANCHOR, "-s", "rules"])
+        .await
+        .is_ok_and(|output| !String::from_utf8_lossy(&output.stdout).trim().is_empty())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+async fn still_installed(_runner: &dyn CommandRunner) -> bool {
+    false
+}
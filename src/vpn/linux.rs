@@ -0,0 +1,388 @@
+use super::runner::CommandRunner;
+use super::{ToolMissing, VpnStatus, spawn_error};
+use crate::config::Protocol;
+use anyhow::{Result, anyhow};
+
+/// Tooling used to drive VPNs on Linux, picked by probing what is installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// NetworkManager connections via `nmcli`
+    NetworkManager,
+    /// WireGuard interfaces via `wg` and `wg-quick`
+    WireGuard,
+    /// strongSwan IPsec connections via `ipsec`
+    StrongSwan,
+    /// Only `ip` is available: tunnels can be observed but not started
+    IpOnly,
+    Unavailable,
+}
+
+impl Backend {
+    /// Probe the known tools in order of preference
+    pub async fn detect(runner: &dyn CommandRunner) -> Self {
+        let probes = [
+            ("nmcli", "--version", Backend::NetworkManager),
+            ("wg", "--version", Backend::WireGuard),
+            ("ipsec", "--version", Backend::StrongSwan),
+            ("ip", "-V", Backend::IpOnly),
+        ];
+        for (program, arg, backend) in probes {
+            if runner.run(program, &[arg]).await.is_ok() {
+                return backend;
+            }
+        }
+        Backend::Unavailable
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Backend::NetworkManager => "nmcli",
+            Backend::WireGuard => "wg",
+            Backend::StrongSwan => "ipsec",
+            Backend::IpOnly => "ip",
+            Backend::Unavailable => "none",
+        }
+    }
+
+    /// Backend to connect a profile of the given protocol with. NetworkManager
+    /// handles every protocol; otherwise WireGuard profiles go through
+    /// `wg-quick` and IPsec-based ones through strongSwan. Other protocols
+    /// need NetworkManager.
+    pub fn for_protocol(self, protocol: &Protocol) -> Result<Self> {
+        match (self, protocol) {
+            (Backend::NetworkManager, _) => Ok(self),
+            (_, Protocol::WireGuard) => Ok(Backend::WireGuard),
+            (_, Protocol::IKEv2 | Protocol::L2tp) => Ok(Backend::StrongSwan),
+            // connect() explains what is missing
+            (Backend::IpOnly | Backend::Unavailable, _) => Ok(self),
+            (Backend::WireGuard | Backend::StrongSwan, _) => Err(anyhow!(
+                "No backend for {} profiles: only {} is installed; install NetworkManager to connect them",
+                protocol,
+                self.label()
+            )),
+        }
+    }
+
+    /// Backend to tear down the named tunnel with, recognizing WireGuard
+    /// interfaces brought up by `for_protocol`
+    pub fn for_interface(self, name: &str) -> Self {
+        if self != Backend::NetworkManager && is_wireguard_interface(name) {
+            Backend::WireGuard
+        } else {
+            self
+        }
+    }
+
+    pub async fn connect(self, runner: &dyn CommandRunner, name: &str) -> Result<()> {
+        match self {
+            Backend::NetworkManager => {
+                run(runner, "nmcli", &["connection", "up", name], "connect").await
+            }
+            Backend::WireGuard => run(runner, "wg-quick", &["up", name], "connect").await,
+            Backend::StrongSwan => run(runner, "ipsec", &["up", name], "connect").await,
+            Backend::IpOnly => Err(anyhow!(
+                "No VPN tool to connect with; install NetworkManager, wireguard-tools or strongSwan"
+            )),
+            Backend::Unavailable => Err(ToolMissing { program: "nmcli" }.into()),
+        }
+    }
+
+    pub async fn disconnect(self, runner: &dyn CommandRunner, name: &str) -> Result<()> {
+        match self {
+            Backend::NetworkManager => {
+                run(runner, "nmcli", &["connection", "down", name], "disconnect").await
+            }
+            Backend::WireGuard => run(runner, "wg-quick", &["down", name], "disconnect").await,
+            Backend::StrongSwan => run(runner, "ipsec", &["down", name], "disconnect").await,
+            Backend::IpOnly => Err(anyhow!(
+                "No VPN tool to disconnect with; install NetworkManager, wireguard-tools or strongSwan"
+            )),
+            Backend::Unavailable => Err(ToolMissing { program: "nmcli" }.into()),
+        }
+    }
+
+    /// Active tunnels with their IPv4 address when known
+    pub async fn active(self, runner: &dyn CommandRunner) -> Result<Vec<(String, Option<String>)>> {
+        let mut active = Vec::new();
+        match self {
+            Backend::NetworkManager => {
+                let stdout = output(
+                    runner,
+                    "nmcli",
+                    &[
+                        "-t",
+                        "-f",
+                        "NAME,TYPE,STATE,IP4.ADDRESS",
+                        "connection",
+                        "show",
+                        "--active",
+                    ],
+                )
+                .await?;
+                active = parse_active_connections(&stdout);
+            }
+            Backend::WireGuard => {
+                let stdout = output(runner, "wg", &["show", "interfaces"]).await?;
+                for name in stdout.split_whitespace() {
+                    active.push((name.to_string(), interface_ip(runner, name).await));
+                }
+            }
+            Backend::StrongSwan => {
+                // e.g. "prod[3]: ESTABLISHED 5 minutes ago, 10.0.0.2[...]...10.1.0.1[...]"
+                let stdout = output(runner, "ipsec", &["status"]).await?;
+                for line in stdout.lines() {
+                    if line.contains("ESTABLISHED")
+                        && let Some((name, _)) = line.trim().split_once('[')
+                    {
+                        active.push((name.to_string(), None));
+                    }
+                }
+            }
+            Backend::IpOnly => {
+                // Tunnel interfaces (wg, tun, ppp) are point-to-point links
+                let stdout = output(runner, "ip", &["-o", "link", "show", "up"]).await?;
+                for line in stdout.lines() {
+                    if !line.contains("POINTOPOINT") {
+                        continue;
+                    }
+                    if let Some(name) = line.split(':').nth(1) {
+                        let name = name.trim().split('@').next().unwrap_or("");
+                        active.push((name.to_string(), interface_ip(runner, name).await));
+                    }
+                }
+            }
+            Backend::Unavailable => return Err(ToolMissing { program: "nmcli" }.into()),
+        }
+        Ok(active)
+    }
+
+    pub async fn status(self, runner: &dyn CommandRunner, name: &str) -> VpnStatus {
+        if self == Backend::NetworkManager {
+            let Ok(stdout) = output(
+                runner,
+                "nmcli",
+                &["-t", "-f", "NAME,STATE", "connection", "show", "--active"],
+            )
+            .await
+            else {
+                return VpnStatus::Disconnected;
+            };
+            for line in stdout.lines() {
+                let parts = split_terse(line);
+                if parts.len() >= 2 && parts[0] == name {
+                    let state = parts[1].to_lowercase();
+                    if state.contains("activated") && !state.contains("deactivated") {
+                        return VpnStatus::Connected;
+                    } else if state.contains("activating") {
+                        return VpnStatus::Connecting;
+                    } else if state.contains("deactivating") {
+                        return VpnStatus::Disconnecting;
+                    }
+                }
+            }
+            return VpnStatus::Disconnected;
+        }
+
+        match self.active(runner).await {
+            Ok(active) if active.iter().any(|(n, _)| n == name) => VpnStatus::Connected,
+            _ => VpnStatus::Disconnected,
+        }
+    }
+}
+
+/// Connection types `nmcli` reports for tunnels
+const TUNNEL_TYPES: [&str; 3] = ["vpn", "wireguard", "tun"];
+
+/// Fields of an `nmcli -t` line. Colons inside a value are escaped as `\:`
+/// and backslashes as `\\`, so a plain split would cut names apart.
+fn split_terse(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next()
+                    && let Some(field) = fields.last_mut()
+                {
+                    field.push(escaped);
+                }
+            }
+            ':' => fields.push(String::new()),
+            _ => {
+                if let Some(field) = fields.last_mut() {
+                    field.push(c);
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// Tunnels in `nmcli -t -f NAME,TYPE,STATE,IP4.ADDRESS connection show
+/// --active` output, with their address minus the `/prefix`
+fn parse_active_connections(stdout: &str) -> Vec<(String, Option<String>)> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let fields = split_terse(line);
+            let [name, kind, ..] = fields.as_slice() else {
+                return None;
+            };
+            if name.is_empty() || !TUNNEL_TYPES.contains(&kind.as_str()) {
+                return None;
+            }
+            // Several addresses are joined with " | "; the first one will do
+            let ip = fields
+                .get(3)
+                .and_then(|addresses| addresses.split('|').next())
+                .map(|address| address.trim().split('/').next().unwrap_or("").to_string())
+                .filter(|ip| !ip.is_empty());
+            Some((name.clone(), ip))
+        })
+        .collect()
+}
+
+fn is_wireguard_interface(name: &str) -> bool {
+    std::fs::read_to_string(format!("/sys/class/net/{}/uevent", name))
+        .is_ok_and(|uevent| uevent.lines().any(|l| l == "DEVTYPE=wireguard"))
+}
+
+/// Network interface carrying the named tunnel: an interface of that name,
+/// as wg-quick and most tun setups create, or else the device NetworkManager
+/// reports for the connection
+pub async fn interface_for(runner: &dyn CommandRunner, name: &str) -> Option<String> {
+    if std::path::Path::new("/sys/class/net").join(name).exists() {
+        return Some(name.to_string());
+    }
+    let devices = output(
+        runner,
+        "nmcli",
+        &["-g", "GENERAL.DEVICES", "connection", "show", name],
+    )
+    .await
+    .ok()?;
+    devices
+        .lines()
+        .next()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(str::to_string)
+}
+
+/// Session traffic `(sent, received)` of a tunnel interface. The interface
+/// is created on connect, so its counters start at zero for each session.
+pub fn interface_traffic(name: &str) -> Option<(u64, u64)> {
+    let read = |counter: &str| {
+        std::fs::read_to_string(format!("/sys/class/net/{}/statistics/{}", name, counter))
+            .ok()?
+            .trim()
+            .parse::<u64>()
+            .ok()
+    };
+    Some((read("tx_bytes")?, read("rx_bytes")?))
+}
+
+async fn run(
+    runner: &dyn CommandRunner,
+    program: &'static str,
+    args: &[&str],
+    action: &str,
+) -> Result<()> {
+    let output = runner
+        .run(program, args)
+        .await
+        .map_err(spawn_error(program))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to {}: {}",
+            action,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+async fn output(
+    runner: &dyn CommandRunner,
+    program: &'static str,
+    args: &[&str],
+) -> Result<String> {
+    let output = runner
+        .run(program, args)
+        .await
+        .map_err(spawn_error(program))?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// First IPv4 address of an interface, via `ip`
+pub async fn interface_ip(runner: &dyn CommandRunner, name: &str) -> Option<String> {
+    let stdout = output(runner, "ip", &["-o", "-4", "addr", "show", "dev", name])
+        .await
+        .ok()?;
+    // e.g. "7: wg0    inet 10.0.0.2/32 scope global wg0"
+    let mut words = stdout.split_whitespace();
+    words.find(|w| *w == "inet")?;
+    words
+        .next()
+        .and_then(|addr| addr.split('/').next())
+        .map(|addr| addr.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn protocols_without_a_backend_are_refused() {
+        let wg = Backend::WireGuard;
+        assert_eq!(
+            wg.for_protocol(&Protocol::WireGuard).unwrap(),
+            Backend::WireGuard
+        );
+        assert_eq!(
+            wg.for_protocol(&Protocol::IKEv2).unwrap(),
+            Backend::StrongSwan
+        );
+        assert!(wg.for_protocol(&Protocol::OpenVpn).is_err());
+        assert!(Backend::StrongSwan.for_protocol(&Protocol::Sstp).is_err());
+        assert_eq!(
+            Backend::NetworkManager
+                .for_protocol(&Protocol::OpenVpn)
+                .unwrap(),
+            Backend::NetworkManager
+        );
+    }
+
+    #[test]
+    fn terse_fields_unescape_colons_and_backslashes() {
+        assert_eq!(
+            split_terse(r"Office\: Berlin:vpn:activated"),
+            vec!["Office: Berlin", "vpn", "activated"]
+        );
+        assert_eq!(split_terse(r"C\\D:vpn"), vec![r"C\D", "vpn"]);
+        // An escaped colon at the end of a name doesn't start a new field
+        assert_eq!(split_terse(r"Office\::vpn"), vec!["Office:", "vpn"]);
+        assert_eq!(split_terse("Office:vpn:"), vec!["Office", "vpn", ""]);
+    }
+
+    #[test]
+    fn active_connections_keep_only_tunnels() {
+        let stdout = "\
+Office\\: Berlin:vpn:activated:10.8.0.2/24
+Lab\\::vpn:activated:
+Wired connection 1:802-3-ethernet:activated:192.168.1.5/24
+home-wg:wireguard:activated:10.9.0.3/32
+tun0:tun:activated:10.10.0.1/24 | 10.10.1.1/24
+";
+        assert_eq!(
+            parse_active_connections(stdout),
+            vec![
+                ("Office: Berlin".to_string(), Some("10.8.0.2".to_string())),
+                ("Lab:".to_string(), None),
+                ("home-wg".to_string(), Some("10.9.0.3".to_string())),
+                ("tun0".to_string(), Some("10.10.0.1".to_string())),
+            ]
+        );
+    }
+}
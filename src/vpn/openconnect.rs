@@ -0,0 +1,205 @@
+//! Cisco AnyConnect profiles, driven through `openconnect`. The client
+//! daemonizes itself, so its PID is kept in a per-profile pid file in the
+//! run dir (`~/.config/remipn/run/`); any remipn process can then tell
+//! whether the tunnel is up and tear it down.
+
+use super::runner::CommandRunner;
+use super::spawn_error;
+use crate::config::VpnProfile;
+use anyhow::{Result, anyhow};
+use async_process::{Command, Stdio};
+use futures_lite::io::AsyncWriteExt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Profile names may contain characters that aren't valid in file names;
+/// escape them as `%XX` so the name can be recovered from the file name
+fn encode_name(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b' ' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn decode_name(encoded: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut rest = encoded.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn pid_file(run_dir: &Path, profile_name: &str) -> PathBuf {
+    run_dir.join(format!("{}.pid", encode_name(profile_name)))
+}
+
+fn read_pid(run_dir: &Path, profile_name: &str) -> Option<u32> {
+    let path = pid_file(run_dir, profile_name);
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(unix)]
+async fn is_alive(runner: &dyn CommandRunner, pid: u32) -> bool {
+    runner
+        .run("kill", &["-0", &pid.to_string()])
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(windows)]
+async fn is_alive(runner: &dyn CommandRunner, pid: u32) -> bool {
+    runner
+        .run("tasklist", &["/FI", &format!("PID eq {}", pid)])
+        .await
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+}
+
+/// Start `openconnect` in the background. It runs non-interactively, so the
+/// gateway must accept the profile's certificate, cached credentials or the
+/// `password` fed to it on stdin.
+pub async fn connect(run_dir: &Path, profile: &VpnProfile, password: Option<&str>) -> Result<()> {
+    let pid_path = pid_file(run_dir, &profile.name);
+    let log_path = pid_path.with_extension("log");
+    let log = fs::File::create(&log_path)?;
+
+    let mut cmd = Command::new("openconnect");
+    cmd.arg("--protocol=anyconnect")
+        .arg("--background")
+        .arg("--non-inter")
+        .arg(format!("--pid-file={}", pid_path.display()));
+    if let Some(user) = &profile.username {
+        cmd.arg(format!("--user={}", user));
+    }
+    if let Some(cert) = &profile.cert_path {
+        cmd.arg(format!("--certificate={}", cert));
+    }
+    // The daemonized client keeps its stdio open, so send it to a log file
+    // rather than a pipe that would never reach EOF. That needs the stdio
+    // redirection a `CommandRunner` doesn't offer, so this is spawned directly.
+    if password.is_some() {
+        cmd.arg("--passwd-on-stdin");
+    }
+    let mut child = cmd
+        .arg(profile.gateway_display())
+        .stdin(if password.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::null())
+        .stderr(log)
+        .spawn()
+        .map_err(spawn_error("openconnect"))?;
+    if let (Some(password), Some(mut stdin)) = (password, child.stdin.take()) {
+        stdin.write_all(password.as_bytes()).await?;
+        stdin.write_all(b"\n").await?;
+        // Dropping the pipe closes it, so openconnect sees EOF after one line
+    }
+    let status = child.status().await?;
+
+    if !status.success() {
+        let _ = fs::remove_file(&pid_path);
+        let output = fs::read_to_string(&log_path).unwrap_or_default();
+        return Err(anyhow!(
+            "openconnect failed for '{}': {}",
+            profile.name,
+            output.lines().last().unwrap_or("no output")
+        ));
+    }
+    Ok(())
+}
+
+/// Stop the `openconnect` started for this profile. Returns false when
+/// remipn isn't tracking one, so the caller can fall back to the system tools.
+pub async fn disconnect(
+    runner: &dyn CommandRunner,
+    run_dir: &Path,
+    profile_name: &str,
+) -> Result<bool> {
+    let Some(pid) = read_pid(run_dir, profile_name) else {
+        return Ok(false);
+    };
+    if is_alive(runner, pid).await {
+        let pid_arg = pid.to_string();
+        // SIGINT makes openconnect log the session off before exiting
+        #[cfg(unix)]
+        let output = runner
+            .run("kill", &["-INT", &pid_arg])
+            .await
+            .map_err(spawn_error("kill"))?;
+        #[cfg(windows)]
+        let output = runner
+            .run("taskkill", &["/PID", &pid_arg])
+            .await
+            .map_err(spawn_error("taskkill"))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Failed to stop openconnect ({}): {}",
+                pid,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+    }
+    let _ = fs::remove_file(pid_file(run_dir, profile_name));
+    Ok(true)
+}
+
+/// Whether remipn has an `openconnect` running for this profile, or `None`
+/// when it isn't tracking one
+pub async fn is_running(
+    runner: &dyn CommandRunner,
+    run_dir: &Path,
+    profile_name: &str,
+) -> Option<bool> {
+    let pid = read_pid(run_dir, profile_name)?;
+    Some(is_alive(runner, pid).await)
+}
+
+/// When the tracked `openconnect` came up, from its pid file's write time
+pub fn started_at(run_dir: &Path, profile_name: &str) -> Option<chrono::DateTime<chrono::Local>> {
+    let modified = fs::metadata(pid_file(run_dir, profile_name))
+        .ok()?
+        .modified()
+        .ok()?;
+    Some(modified.into())
+}
+
+/// Profiles with a live `openconnect`; stale pid files are removed
+pub async fn active(runner: &dyn CommandRunner, run_dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(run_dir) else {
+        return Vec::new();
+    };
+    let mut active = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("pid") {
+            continue;
+        }
+        let Some(name) = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(decode_name)
+        else {
+            continue;
+        };
+        match is_running(runner, run_dir, &name).await {
+            Some(true) => active.push(name),
+            _ => {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+    active
+}
@@ -0,0 +1,155 @@
+//! Every platform tool `VpnManager` invokes goes through a `CommandRunner`,
+//! so the parsers can be fed canned `scutil`/`nmcli`/`wg` output instead of
+//! shelling out.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::process::Output;
+
+pub type RunFuture<'a> = Pin<Box<dyn Future<Output = std::io::Result<Output>> + Send + 'a>>;
+
+/// `Debug` so managers holding a runner can still be printed
+pub trait CommandRunner: std::fmt::Debug + Send + Sync {
+    /// Run `program` with `args` to completion, capturing stdout and stderr.
+    /// Spawn failures keep their `io::ErrorKind` so a missing tool can be
+    /// told apart from one that failed. Dropping the future before it
+    /// completes, e.g. on a timeout, kills the process.
+    fn run<'a>(&'a self, program: &'a str, args: &'a [&'a str]) -> RunFuture<'a> {
+        self.run_with_env(program, args, &[])
+    }
+
+    /// `run` with extra environment variables, for secrets that must not
+    /// show up in the process list as arguments would
+    fn run_with_env<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        env: &'a [(&'a str, &'a str)],
+    ) -> RunFuture<'a>;
+}
+
+/// Runs commands on the host with `async_process`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run_with_env<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        env: &'a [(&'a str, &'a str)],
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            async_process::Command::new(program)
+                .args(args)
+                .envs(env.iter().copied())
+                .kill_on_drop(true)
+                .output()
+                .await
+        })
+    }
+}
+
+/// Answers with canned output instead of running anything. A program with no
+/// canned output fails to spawn with `NotFound`, like a missing tool.
+/// Clones share the canned output and the record of calls made.
+#[cfg(test)]
+#[derive(Debug, Default, Clone)]
+pub struct MockRunner {
+    /// Program, leading arguments to match and the stdout returned
+    outputs: std::sync::Arc<std::sync::Mutex<Vec<(String, Vec<String>, String)>>>,
+    /// How long every call takes
+    delay: Option<std::time::Duration>,
+    /// Every command run, as `program arg arg...`
+    calls: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[cfg(test)]
+impl MockRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer `program` called with arguments starting with `args` with
+    /// `stdout`. Earlier entries win, so list specific ones first.
+    pub fn with_output(self, program: &str, args: &[&str], stdout: &str) -> Self {
+        self.outputs.lock().unwrap().push((
+            program.to_string(),
+            args.iter().map(|a| a.to_string()).collect(),
+            stdout.to_string(),
+        ));
+        self
+    }
+
+    /// Change what `program` called with exactly the leading `args` of an
+    /// earlier [`Self::with_output`] answers from now on
+    pub fn set_output(&self, program: &str, args: &[&str], stdout: &str) {
+        let mut outputs = self.outputs.lock().unwrap();
+        if let Some(entry) = outputs.iter_mut().find(|(p, prefix, _)| {
+            p == program
+                && prefix.len() == args.len()
+                && prefix.iter().zip(args).all(|(a, b)| a == b)
+        }) {
+            entry.2 = stdout.to_string();
+        }
+    }
+
+    /// Make every call take `delay`, as a slow system tool would
+    pub fn with_delay(mut self, delay: std::time::Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Commands run so far, oldest first, as `program arg arg...`
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+fn success() -> std::process::ExitStatus {
+    #[cfg(unix)]
+    use std::os::unix::process::ExitStatusExt;
+    #[cfg(windows)]
+    use std::os::windows::process::ExitStatusExt;
+    std::process::ExitStatus::from_raw(0)
+}
+
+#[cfg(test)]
+impl CommandRunner for MockRunner {
+    fn run_with_env<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        _env: &'a [(&'a str, &'a str)],
+    ) -> RunFuture<'a> {
+        Box::pin(async move {
+            self.calls.lock().unwrap().push(
+                std::iter::once(program)
+                    .chain(args.iter().copied())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+            if let Some(delay) = self.delay {
+                tokio::time::sleep(delay).await;
+            }
+            let stdout = self
+                .outputs
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(p, prefix, _)| {
+                    p == program
+                        && prefix.len() <= args.len()
+                        && prefix.iter().zip(args).all(|(a, b)| a == b)
+                })
+                .map(|(_, _, stdout)| stdout.clone())
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))?;
+            Ok(Output {
+                status: success(),
+                stdout: stdout.into_bytes(),
+                stderr: Vec::new(),
+            })
+        })
+    }
+}
@@ -0,0 +1,178 @@
+//! WireGuard profiles that point at a `.conf` file, driven with `wg-quick`
+//! on Linux and macOS. wg-quick names the interface after the file on Linux;
+//! on macOS it creates a `utunN` interface and records which one under
+//! `/var/run/wireguard/`, so that is where remipn looks it up for `wg show`.
+
+use super::runner::CommandRunner;
+use super::{VpnStatus, spawn_error};
+use crate::config::{Protocol, VpnProfile};
+use anyhow::{Result, anyhow};
+use std::path::Path;
+
+/// A peer that hasn't completed a handshake for this long is probably gone;
+/// WireGuard re-handshakes every two minutes while traffic flows
+const HANDSHAKE_STALE_SECS: i64 = 180;
+
+/// The wg-quick config of a WireGuard profile: `config_file`, or `cert_path`
+/// when it names a `.conf` file. Profiles without one keep using the
+/// platform's own VPN tooling.
+pub fn config_file(profile: &VpnProfile) -> Option<String> {
+    if profile.protocol != Protocol::WireGuard {
+        return None;
+    }
+    profile.config_file.clone().or_else(|| {
+        profile
+            .cert_path
+            .clone()
+            .filter(|path| path.ends_with(".conf"))
+    })
+}
+
+/// Where wg-quick on macOS records the `utunN` behind each interface name
+const NAME_DIR: &str = "/var/run/wireguard";
+
+/// Interface wg-quick creates for a config file
+pub fn interface(config_file: &str) -> String {
+    interface_in(Path::new(NAME_DIR), config_file)
+}
+
+/// The config's file name without `.conf`, or the `utunN` recorded for that
+/// name in `name_dir/<name>.name`
+fn interface_in(name_dir: &Path, config_file: &str) -> String {
+    let name = Path::new(config_file)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(config_file);
+    std::fs::read_to_string(name_dir.join(format!("{}.name", name)))
+        .ok()
+        .map(|real| real.trim().to_string())
+        .filter(|real| !real.is_empty())
+        .unwrap_or_else(|| name.to_string())
+}
+
+pub async fn up(runner: &dyn CommandRunner, config_file: &str) -> Result<()> {
+    wg_quick(runner, "up", config_file).await
+}
+
+pub async fn down(runner: &dyn CommandRunner, config_file: &str) -> Result<()> {
+    wg_quick(runner, "down", config_file).await
+}
+
+async fn wg_quick(runner: &dyn CommandRunner, action: &str, config_file: &str) -> Result<()> {
+    let output = runner
+        .run("wg-quick", &[action, config_file])
+        .await
+        .map_err(spawn_error("wg-quick"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "wg-quick {} {} failed: {}",
+            action,
+            config_file,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the interface exists, i.e. `wg show` knows it
+pub async fn is_up(runner: &dyn CommandRunner, interface: &str) -> bool {
+    runner
+        .run("wg", &["show", interface])
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Status from the newest peer handshake: connected while handshakes are
+/// recent, degraded when they stopped or never happened, disconnected when
+/// the interface is gone
+pub async fn status(runner: &dyn CommandRunner, interface: &str) -> VpnStatus {
+    let Ok(output) = runner
+        .run("wg", &["show", interface, "latest-handshakes"])
+        .await
+    else {
+        return VpnStatus::Disconnected;
+    };
+    if !output.status.success() {
+        return VpnStatus::Disconnected;
+    }
+
+    // One "<peer public key>\t<unix time>" line per peer; 0 means never
+    let newest = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1)?.parse::<i64>().ok())
+        .max()
+        .unwrap_or(0);
+    if newest == 0 {
+        return VpnStatus::Degraded("no WireGuard handshake yet".to_string());
+    }
+    let age = chrono::Utc::now().timestamp() - newest;
+    if age > HANDSHAKE_STALE_SECS {
+        VpnStatus::Degraded(format!("last WireGuard handshake {}s ago", age))
+    } else {
+        VpnStatus::Connected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vpn::MockRunner;
+
+    #[test]
+    fn interface_follows_the_macos_name_file() {
+        let dir = std::env::temp_dir().join(format!("remipn-wg-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(interface_in(&dir, "/etc/wireguard/office.conf"), "office");
+
+        std::fs::write(dir.join("office.name"), "utun4\n").unwrap();
+        assert_eq!(interface_in(&dir, "/etc/wireguard/office.conf"), "utun4");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn wg_quick_gets_the_config_file() {
+        let runner = MockRunner::new().with_output("wg-quick", &[], "");
+        up(&runner, "/etc/wireguard/office.conf").await.unwrap();
+        down(&runner, "/etc/wireguard/office.conf").await.unwrap();
+        assert_eq!(
+            runner.calls(),
+            vec![
+                "wg-quick up /etc/wireguard/office.conf",
+                "wg-quick down /etc/wireguard/office.conf",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn status_follows_the_newest_handshake() {
+        let status_with = |handshakes: String| async move {
+            let runner = MockRunner::new().with_output(
+                "wg",
+                &["show", "utun4", "latest-handshakes"],
+                &handshakes,
+            );
+            let status = status(&runner, "utun4").await;
+            assert_eq!(runner.calls(), vec!["wg show utun4 latest-handshakes"]);
+            status
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        assert_eq!(
+            status_with(format!("peerA=\t{}\npeerB=\t{}\n", now - 600, now - 10)).await,
+            VpnStatus::Connected
+        );
+        assert!(matches!(
+            status_with(format!("peerA=\t{}\n", now - 200)).await,
+            VpnStatus::Degraded(_)
+        ));
+        assert_eq!(
+            status_with("peerA=\t0\n".to_string()).await,
+            VpnStatus::Degraded("no WireGuard handshake yet".to_string())
+        );
+        // wg missing or the interface gone
+        assert_eq!(
+            status(&MockRunner::new(), "utun4").await,
+            VpnStatus::Disconnected
+        );
+    }
+}